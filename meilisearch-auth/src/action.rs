@@ -36,6 +36,12 @@ pub enum Action {
     DumpsGet = actions::DUMPS_GET,
     #[serde(rename = "version")]
     Version = actions::VERSION,
+    #[serde(rename = "tasks.cancel")]
+    TasksCancel = actions::TASKS_CANCEL,
+    #[serde(rename = "tasks.retry")]
+    TasksRetry = actions::TASKS_RETRY,
+    #[serde(rename = "transactions.create")]
+    TransactionsCreate = actions::TRANSACTIONS_CREATE,
 }
 
 impl Action {
@@ -58,6 +64,9 @@ impl Action {
             DUMPS_CREATE => Some(Self::DumpsCreate),
             DUMPS_GET => Some(Self::DumpsGet),
             VERSION => Some(Self::Version),
+            TASKS_CANCEL => Some(Self::TasksCancel),
+            TASKS_RETRY => Some(Self::TasksRetry),
+            TRANSACTIONS_CREATE => Some(Self::TransactionsCreate),
             _otherwise => None,
         }
     }
@@ -81,6 +90,9 @@ impl Action {
             Self::DumpsCreate => DUMPS_CREATE,
             Self::DumpsGet => DUMPS_GET,
             Self::Version => VERSION,
+            Self::TasksCancel => TASKS_CANCEL,
+            Self::TasksRetry => TASKS_RETRY,
+            Self::TransactionsCreate => TRANSACTIONS_CREATE,
         }
     }
 }
@@ -101,4 +113,7 @@ pub mod actions {
     pub const DUMPS_CREATE: u8 = 13;
     pub const DUMPS_GET: u8 = 14;
     pub const VERSION: u8 = 15;
+    pub const TASKS_CANCEL: u8 = 16;
+    pub const TASKS_RETRY: u8 = 17;
+    pub const TRANSACTIONS_CREATE: u8 = 18;
 }
@@ -166,6 +166,29 @@ pub enum Code {
     InvalidApiKeyIndexes,
     InvalidApiKeyExpiresAt,
     InvalidApiKeyDescription,
+
+    TaskAlreadyProcessing,
+    TaskAlreadyFinished,
+
+    InvalidTaskStatus,
+    InvalidTaskType,
+
+    InvalidWebhookUrl,
+    InvalidWebhookHeader,
+    WebhookNotFound,
+
+    TaskNotRetryable,
+    TaskAlreadyRetried,
+    TaskPayloadDeleted,
+
+    InvalidIdempotencyKey,
+    IdempotencyKeyReused,
+
+    InvalidTransaction,
+
+    BatchNotFound,
+
+    InvalidFacetSearchFacetName,
 }
 
 impl Code {
@@ -272,6 +295,47 @@ impl Code {
             InvalidApiKeyDescription => {
                 ErrCode::invalid("invalid_api_key_description", StatusCode::BAD_REQUEST)
             }
+
+            // error related to task cancellation
+            TaskAlreadyProcessing => {
+                ErrCode::invalid("task_already_processing", StatusCode::CONFLICT)
+            }
+            TaskAlreadyFinished => ErrCode::invalid("task_already_finished", StatusCode::CONFLICT),
+
+            // error related to task filtering
+            InvalidTaskStatus => ErrCode::invalid("invalid_task_status", StatusCode::BAD_REQUEST),
+            InvalidTaskType => ErrCode::invalid("invalid_task_type", StatusCode::BAD_REQUEST),
+
+            // error related to webhooks
+            InvalidWebhookUrl => ErrCode::invalid("invalid_webhook_url", StatusCode::BAD_REQUEST),
+            InvalidWebhookHeader => {
+                ErrCode::invalid("invalid_webhook_header", StatusCode::BAD_REQUEST)
+            }
+            WebhookNotFound => ErrCode::invalid("webhook_not_found", StatusCode::NOT_FOUND),
+
+            // error related to task retries
+            TaskNotRetryable => ErrCode::invalid("task_not_retryable", StatusCode::CONFLICT),
+            TaskAlreadyRetried => ErrCode::invalid("task_already_retried", StatusCode::CONFLICT),
+            TaskPayloadDeleted => ErrCode::invalid("task_payload_deleted", StatusCode::GONE),
+
+            // error related to idempotency keys
+            InvalidIdempotencyKey => {
+                ErrCode::invalid("invalid_idempotency_key", StatusCode::BAD_REQUEST)
+            }
+            IdempotencyKeyReused => {
+                ErrCode::invalid("idempotency_key_reused", StatusCode::CONFLICT)
+            }
+
+            // error related to transactions
+            InvalidTransaction => ErrCode::invalid("invalid_transaction", StatusCode::BAD_REQUEST),
+
+            // error related to batches
+            BatchNotFound => ErrCode::invalid("batch_not_found", StatusCode::NOT_FOUND),
+
+            // error related to facet search
+            InvalidFacetSearchFacetName => {
+                ErrCode::invalid("invalid_facet_search_facet_name", StatusCode::BAD_REQUEST)
+            }
         }
     }
 
@@ -331,6 +331,8 @@ pub struct SearchAggregator {
     // pagination
     max_limit: usize,
     max_offset: usize,
+    // everytime a request is paginated by pages, this field must be incremented by one
+    finite_pagination: usize,
 }
 
 impl SearchAggregator {
@@ -378,6 +380,9 @@ impl SearchAggregator {
 
         ret.max_limit = query.limit;
         ret.max_offset = query.offset.unwrap_or_default();
+        if query.page.is_some() || query.hits_per_page.is_some() {
+            ret.finite_pagination = 1;
+        }
 
         ret
     }
@@ -422,6 +427,9 @@ impl SearchAggregator {
         // pagination
         self.max_limit = self.max_limit.max(other.max_limit);
         self.max_offset = self.max_offset.max(other.max_offset);
+        self.finite_pagination = self
+            .finite_pagination
+            .saturating_add(other.finite_pagination);
     }
 
     pub fn into_event(self, user: &User, event_name: &str) -> Option<Track> {
@@ -458,6 +466,7 @@ impl SearchAggregator {
                 "pagination": {
                    "max_limit": self.max_limit,
                    "max_offset": self.max_offset,
+                   "finite_pagination": self.finite_pagination,
                 },
             });
 
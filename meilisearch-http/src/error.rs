@@ -12,6 +12,12 @@ pub enum MeilisearchHttpError {
         .1.iter().map(|s| format!("`{}`", s)).collect::<Vec<_>>().join(", ")
     )]
     InvalidContentType(String, Vec<String>),
+    #[error("Task status `{0}` is invalid. Available task statuses are: `enqueued`, `scheduled`, `processing`, `succeeded`, `failed`, `canceled`.")]
+    InvalidTaskStatus(String),
+    #[error("Task type `{0}` is invalid. Available task types are: `indexCreation`, `indexUpdate`, `indexDeletion`, `documentAddition`, `documentPartial`, `documentDeletion`, `settingsUpdate`, `clearAll`, `transaction`.")]
+    InvalidTaskType(String),
+    #[error("The `Idempotency-Key` header is invalid. It must contain between 1 and {0} visible ASCII characters.")]
+    InvalidIdempotencyKey(usize),
 }
 
 impl ErrorCode for MeilisearchHttpError {
@@ -19,6 +25,9 @@ impl ErrorCode for MeilisearchHttpError {
         match self {
             MeilisearchHttpError::MissingContentType(_) => Code::MissingContentType,
             MeilisearchHttpError::InvalidContentType(_, _) => Code::InvalidContentType,
+            MeilisearchHttpError::InvalidTaskStatus(_) => Code::InvalidTaskStatus,
+            MeilisearchHttpError::InvalidTaskType(_) => Code::InvalidTaskType,
+            MeilisearchHttpError::InvalidIdempotencyKey(_) => Code::InvalidIdempotencyKey,
         }
     }
 }
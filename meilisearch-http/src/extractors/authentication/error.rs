@@ -6,6 +6,10 @@ pub enum AuthenticationError {
     MissingAuthorizationHeader,
     #[error("The provided API key is invalid.")]
     InvalidToken(String),
+    // Triggered when a request targets an index the API key can't access, on the routes where
+    // the index isn't part of the path.
+    #[error("The provided API key is invalid.")]
+    UnauthorizedIndex(String),
     // Triggered on configuration error.
     #[error("An internal error has occurred. `Irretrievable state`.")]
     IrretrievableState,
@@ -16,6 +20,7 @@ impl ErrorCode for AuthenticationError {
         match self {
             AuthenticationError::MissingAuthorizationHeader => Code::MissingAuthorizationHeader,
             AuthenticationError::InvalidToken(_) => Code::InvalidToken,
+            AuthenticationError::UnauthorizedIndex(_) => Code::InvalidToken,
             AuthenticationError::IrretrievableState => Code::Internal,
         }
     }
@@ -0,0 +1,46 @@
+use actix_web::{dev, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use meilisearch_error::ResponseError;
+
+use crate::error::MeilisearchHttpError;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// The maximum length of a key, which must fit in an LMDB key.
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// The value of the optional `Idempotency-Key` header. The tasks registered with the same key
+/// are only registered once.
+pub struct IdempotencyKey(pub Option<String>);
+
+impl IdempotencyKey {
+    pub fn into_inner(self) -> Option<String> {
+        self.0
+    }
+}
+
+impl FromRequest for IdempotencyKey {
+    type Config = ();
+
+    type Error = ResponseError;
+
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut dev::Payload) -> Self::Future {
+        let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+            Some(value) => match value.to_str() {
+                Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH => {
+                    Some(key.to_string())
+                }
+                _ => {
+                    return ready(Err(MeilisearchHttpError::InvalidIdempotencyKey(
+                        MAX_IDEMPOTENCY_KEY_LENGTH,
+                    )
+                    .into()))
+                }
+            },
+            None => None,
+        };
+
+        ready(Ok(IdempotencyKey(key)))
+    }
+}
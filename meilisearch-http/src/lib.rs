@@ -34,7 +34,11 @@ pub fn setup_meilisearch(opt: &Opt) -> anyhow::Result<MeiliSearch> {
         .set_ignore_snapshot_if_db_exists(opt.ignore_snapshot_if_db_exists)
         .set_dump_dst(opt.dumps_dir.clone())
         .set_snapshot_interval(Duration::from_secs(opt.snapshot_interval_sec))
-        .set_snapshot_dir(opt.snapshot_dir.clone());
+        .set_snapshot_dir(opt.snapshot_dir.clone())
+        .set_scheduler_config(opt.scheduler_options.clone())
+        .set_task_retention_config(opt.task_retention_options.clone())
+        .set_webhook_config(opt.webhook_options.clone())
+        .set_search_cache_size(opt.search_cache_size.get_bytes() as usize);
 
     if let Some(ref path) = opt.import_snapshot {
         meilisearch.set_import_snapshot(path.clone());
@@ -48,7 +52,10 @@ pub fn setup_meilisearch(opt: &Opt) -> anyhow::Result<MeiliSearch> {
         meilisearch.set_schedule_snapshot();
     }
 
-    meilisearch.build(opt.db_path.clone(), opt.indexer_options.clone())
+    let meilisearch = meilisearch.build(opt.db_path.clone(), opt.indexer_options.clone())?;
+    task::spawn_webhook_notifier(&meilisearch);
+
+    Ok(meilisearch)
 }
 
 pub fn configure_data(
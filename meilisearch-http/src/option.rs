@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use byte_unit::Byte;
-use meilisearch_lib::options::IndexerOpts;
+use meilisearch_lib::options::{IndexerOpts, SchedulerConfig, TaskRetentionConfig, WebhookConfig};
 use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
 use rustls::{
     AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, NoClientAuth,
@@ -52,6 +52,11 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_HTTP_PAYLOAD_SIZE_LIMIT", default_value = "100 MB")]
     pub http_payload_size_limit: Byte,
 
+    /// The maximum size, in bytes, of the in-memory cache of the search results. The least
+    /// recently used results are evicted first. The cache is disabled when the size is 0.
+    #[structopt(long, env = "MEILI_SEARCH_CACHE_SIZE", default_value = "0")]
+    pub search_cache_size: Byte,
+
     /// Read server certificates from CERTFILE.
     /// This should contain PEM-format certificates
     /// in the right order (the first certificate should
@@ -126,6 +131,15 @@ pub struct Opt {
 
     #[structopt(skip)]
     pub indexer_options: IndexerOpts,
+
+    #[structopt(flatten)]
+    pub scheduler_options: SchedulerConfig,
+
+    #[structopt(flatten)]
+    pub task_retention_options: TaskRetentionConfig,
+
+    #[structopt(flatten)]
+    pub webhook_options: WebhookConfig,
 }
 
 impl Opt {
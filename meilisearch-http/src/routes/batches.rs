@@ -0,0 +1,158 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use meilisearch_error::ResponseError;
+use meilisearch_lib::tasks::batch::{BatchId, BatchInfo, IndexingStep};
+use meilisearch_lib::tasks::task::TaskId;
+use meilisearch_lib::tasks::TaskFilter;
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::analytics::Analytics;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::task::serialize_duration;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::get().to(get_batches)))
+        .service(web::resource("/{batch_id}").route(web::get().to(get_batch)));
+}
+
+const DEFAULT_LIST_BATCHES_LIMIT: usize = 20;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BatchesQuery {
+    limit: Option<usize>,
+    from: Option<BatchId>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexingStepView {
+    name: String,
+    started_at: DateTime<Utc>,
+    finished_at: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_duration")]
+    duration: Option<Duration>,
+}
+
+impl From<IndexingStep> for IndexingStepView {
+    fn from(step: IndexingStep) -> Self {
+        Self {
+            duration: Some(step.finished_at - step.started_at),
+            name: step.name,
+            started_at: step.started_at,
+            finished_at: step.finished_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchView {
+    uid: BatchId,
+    index_uid: String,
+    task_uids: Vec<TaskId>,
+    total_documents: usize,
+    steps: Vec<IndexingStepView>,
+    #[serde(serialize_with = "serialize_duration")]
+    duration: Option<Duration>,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<BatchInfo> for BatchView {
+    fn from(batch: BatchInfo) -> Self {
+        Self {
+            uid: batch.id,
+            index_uid: batch.index_uid,
+            task_uids: batch.task_ids,
+            total_documents: batch.total_documents,
+            steps: batch.steps.into_iter().map(Into::into).collect(),
+            duration: batch
+                .finished_at
+                .map(|finished_at| finished_at - batch.started_at),
+            started_at: batch.started_at,
+            finished_at: batch.finished_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BatchListView {
+    results: Vec<BatchView>,
+    limit: usize,
+    from: Option<BatchId>,
+    next: Option<BatchId>,
+}
+
+async fn get_batches(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_GET }>, MeiliSearch>,
+    params: web::Query<BatchesQuery>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    analytics.publish(
+        "Batches Seen".to_string(),
+        json!({ "per_batch_uid": false }),
+        Some(&req),
+    );
+
+    let BatchesQuery { limit, from } = params.into_inner();
+    let filters = meilisearch.filters().indexes.as_ref().map(|indexes| {
+        let mut filters = TaskFilter::default();
+        for index in indexes {
+            filters.filter_index(index.to_string());
+        }
+        filters
+    });
+
+    let limit = limit.unwrap_or(DEFAULT_LIST_BATCHES_LIMIT);
+    // We fetch one more batch to know if there is a next page.
+    let mut batches = meilisearch
+        .list_batches(filters, Some(limit.saturating_add(1)), from)
+        .await?;
+
+    let next = if batches.len() > limit {
+        batches.pop().map(|batch| batch.id)
+    } else {
+        None
+    };
+
+    let results: Vec<BatchView> = batches.into_iter().map(Into::into).collect();
+    let from = results.first().map(|batch| batch.uid);
+
+    Ok(HttpResponse::Ok().json(BatchListView {
+        results,
+        limit,
+        from,
+        next,
+    }))
+}
+
+async fn get_batch(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_GET }>, MeiliSearch>,
+    batch_id: web::Path<BatchId>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    analytics.publish(
+        "Batches Seen".to_string(),
+        json!({ "per_batch_uid": true }),
+        Some(&req),
+    );
+
+    let filters = meilisearch.filters().indexes.as_ref().map(|indexes| {
+        let mut filters = TaskFilter::default();
+        for index in indexes {
+            filters.filter_index(index.to_string());
+        }
+        filters
+    });
+    let batch: BatchView = meilisearch
+        .get_batch(batch_id.into_inner(), filters)
+        .await?
+        .into();
+
+    Ok(HttpResponse::Ok().json(batch))
+}
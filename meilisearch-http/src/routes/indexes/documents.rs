@@ -4,6 +4,7 @@ use actix_web::web::Bytes;
 use actix_web::HttpMessage;
 use actix_web::{web, HttpRequest, HttpResponse};
 use bstr::ByteSlice;
+use chrono::{DateTime, Utc};
 use futures::{Stream, StreamExt};
 use log::debug;
 use meilisearch_error::ResponseError;
@@ -19,8 +20,9 @@ use tokio::sync::mpsc;
 use crate::analytics::Analytics;
 use crate::error::MeilisearchHttpError;
 use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::idempotency_key::IdempotencyKey;
 use crate::extractors::payload::Payload;
-use crate::task::SummarizedTaskView;
+use crate::task::{ScheduleQuery, SummarizedTaskView};
 
 const DEFAULT_RETRIEVE_DOCUMENTS_OFFSET: usize = 0;
 const DEFAULT_RETRIEVE_DOCUMENTS_LIMIT: usize = 20;
@@ -101,13 +103,23 @@ pub async fn get_document(
 pub async fn delete_document(
     meilisearch: GuardedData<ActionPolicy<{ actions::DOCUMENTS_DELETE }>, MeiliSearch>,
     path: web::Path<DocumentParam>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
 ) -> Result<HttpResponse, ResponseError> {
     let DocumentParam {
         document_id,
         index_uid,
     } = path.into_inner();
     let update = Update::DeleteDocuments(vec![document_id]);
-    let task: SummarizedTaskView = meilisearch.register_update(index_uid, update).await?.into();
+    let task: SummarizedTaskView = meilisearch
+        .register_update(
+            index_uid,
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
+        .await?
+        .into();
     debug!("returns: {:?}", task);
     Ok(HttpResponse::Accepted().json(task))
 }
@@ -153,12 +165,14 @@ pub async fn get_all_documents(
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct UpdateDocumentsQuery {
     pub primary_key: Option<String>,
+    pub not_before: Option<DateTime<Utc>>,
 }
 
 pub async fn add_documents(
     meilisearch: GuardedData<ActionPolicy<{ actions::DOCUMENTS_ADD }>, MeiliSearch>,
     path: web::Path<String>,
     params: web::Query<UpdateDocumentsQuery>,
+    idempotency_key: IdempotencyKey,
     body: Payload,
     req: HttpRequest,
     analytics: web::Data<dyn Analytics>,
@@ -182,6 +196,8 @@ pub async fn add_documents(
         body,
         IndexDocumentsMethod::ReplaceDocuments,
         allow_index_creation,
+        params.not_before,
+        idempotency_key.into_inner(),
     )
     .await?;
 
@@ -192,6 +208,7 @@ pub async fn update_documents(
     meilisearch: GuardedData<ActionPolicy<{ actions::DOCUMENTS_ADD }>, MeiliSearch>,
     path: web::Path<String>,
     params: web::Query<UpdateDocumentsQuery>,
+    idempotency_key: IdempotencyKey,
     body: Payload,
     req: HttpRequest,
     analytics: web::Data<dyn Analytics>,
@@ -210,10 +227,12 @@ pub async fn update_documents(
         extract_mime_type(&req)?,
         meilisearch,
         index_uid,
-        params.into_inner().primary_key,
+        params.primary_key.clone(),
         body,
         IndexDocumentsMethod::UpdateDocuments,
         allow_index_creation,
+        params.not_before,
+        idempotency_key.into_inner(),
     )
     .await?;
 
@@ -228,6 +247,8 @@ async fn document_addition(
     body: Payload,
     method: IndexDocumentsMethod,
     allow_index_creation: bool,
+    not_before: Option<DateTime<Utc>>,
+    idempotency_key: Option<String>,
 ) -> Result<SummarizedTaskView, ResponseError> {
     let format = match mime_type
         .as_ref()
@@ -258,7 +279,10 @@ async fn document_addition(
         allow_index_creation,
     };
 
-    let task = meilisearch.register_update(index_uid, update).await?.into();
+    let task = meilisearch
+        .register_update(index_uid, update, not_before, idempotency_key)
+        .await?
+        .into();
 
     debug!("returns: {:?}", task);
     Ok(task)
@@ -268,6 +292,8 @@ pub async fn delete_documents(
     meilisearch: GuardedData<ActionPolicy<{ actions::DOCUMENTS_DELETE }>, MeiliSearch>,
     path: web::Path<String>,
     body: web::Json<Vec<Value>>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
 ) -> Result<HttpResponse, ResponseError> {
     debug!("called with params: {:?}", body);
     let ids = body
@@ -281,7 +307,12 @@ pub async fn delete_documents(
 
     let update = Update::DeleteDocuments(ids);
     let task: SummarizedTaskView = meilisearch
-        .register_update(path.into_inner(), update)
+        .register_update(
+            path.into_inner(),
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
         .await?
         .into();
 
@@ -292,10 +323,17 @@ pub async fn delete_documents(
 pub async fn clear_all_documents(
     meilisearch: GuardedData<ActionPolicy<{ actions::DOCUMENTS_DELETE }>, MeiliSearch>,
     path: web::Path<String>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
 ) -> Result<HttpResponse, ResponseError> {
     let update = Update::ClearDocuments;
     let task: SummarizedTaskView = meilisearch
-        .register_update(path.into_inner(), update)
+        .register_update(
+            path.into_inner(),
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
         .await?
         .into();
 
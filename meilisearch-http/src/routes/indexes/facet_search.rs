@@ -0,0 +1,39 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::debug;
+use meilisearch_error::ResponseError;
+use meilisearch_lib::index::FacetSearchQuery;
+use meilisearch_lib::MeiliSearch;
+use serde_json::json;
+
+use crate::analytics::Analytics;
+use crate::extractors::authentication::{policies::*, GuardedData};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::post().to(facet_search_with_post)));
+}
+
+pub async fn facet_search_with_post(
+    meilisearch: GuardedData<ActionPolicy<{ actions::SEARCH }>, MeiliSearch>,
+    path: web::Path<String>,
+    params: web::Json<FacetSearchQuery>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    let query = params.into_inner();
+    debug!("facet search called with params: {:?}", query);
+
+    analytics.publish(
+        "Facet Searched".to_string(),
+        json!({
+            "has_facet_query": query.facet_query.is_some(),
+            "has_q": query.q.is_some(),
+            "has_filter": query.filter.is_some(),
+        }),
+        Some(&req),
+    );
+
+    let search_result = meilisearch.facet_search(path.into_inner(), query).await?;
+
+    debug!("returns: {:?}", search_result);
+    Ok(HttpResponse::Ok().json(search_result))
+}
@@ -9,12 +9,15 @@ use serde_json::json;
 
 use crate::analytics::Analytics;
 use crate::extractors::authentication::{policies::*, GuardedData};
-use crate::task::SummarizedTaskView;
+use crate::extractors::idempotency_key::IdempotencyKey;
+use crate::task::{ScheduleQuery, SummarizedTaskView};
 
 pub mod documents;
+pub mod facet_search;
 pub mod search;
 pub mod settings;
 pub mod tasks;
+pub mod transaction;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -33,8 +36,10 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .service(web::resource("/stats").route(web::get().to(get_index_stats)))
             .service(web::scope("/documents").configure(documents::configure))
             .service(web::scope("/search").configure(search::configure))
+            .service(web::scope("/facet-search").configure(facet_search::configure))
             .service(web::scope("/tasks").configure(tasks::configure))
-            .service(web::scope("/settings").configure(settings::configure)),
+            .service(web::scope("/settings").configure(settings::configure))
+            .service(web::scope("/transaction").configure(transaction::configure)),
     );
 }
 
@@ -64,6 +69,8 @@ pub struct IndexCreateRequest {
 pub async fn create_index(
     meilisearch: GuardedData<ActionPolicy<{ actions::INDEXES_CREATE }>, MeiliSearch>,
     body: web::Json<IndexCreateRequest>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
     req: HttpRequest,
     analytics: web::Data<dyn Analytics>,
 ) -> Result<HttpResponse, ResponseError> {
@@ -78,7 +85,15 @@ pub async fn create_index(
     );
 
     let update = Update::CreateIndex { primary_key };
-    let task: SummarizedTaskView = meilisearch.register_update(uid, update).await?.into();
+    let task: SummarizedTaskView = meilisearch
+        .register_update(
+            uid,
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
+        .await?
+        .into();
 
     Ok(HttpResponse::Accepted().json(task))
 }
@@ -114,6 +129,8 @@ pub async fn update_index(
     meilisearch: GuardedData<ActionPolicy<{ actions::INDEXES_UPDATE }>, MeiliSearch>,
     path: web::Path<String>,
     body: web::Json<UpdateIndexRequest>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
     req: HttpRequest,
     analytics: web::Data<dyn Analytics>,
 ) -> Result<HttpResponse, ResponseError> {
@@ -130,7 +147,12 @@ pub async fn update_index(
     };
 
     let task: SummarizedTaskView = meilisearch
-        .register_update(path.into_inner(), update)
+        .register_update(
+            path.into_inner(),
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
         .await?
         .into();
 
@@ -141,10 +163,20 @@ pub async fn update_index(
 pub async fn delete_index(
     meilisearch: GuardedData<ActionPolicy<{ actions::INDEXES_DELETE }>, MeiliSearch>,
     path: web::Path<String>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
 ) -> Result<HttpResponse, ResponseError> {
     let uid = path.into_inner();
     let update = Update::DeleteIndex;
-    let task: SummarizedTaskView = meilisearch.register_update(uid, update).await?.into();
+    let task: SummarizedTaskView = meilisearch
+        .register_update(
+            uid,
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
+        .await?
+        .into();
 
     Ok(HttpResponse::Accepted().json(task))
 }
@@ -1,7 +1,10 @@
 use actix_web::{web, HttpRequest, HttpResponse};
 use log::debug;
 use meilisearch_error::ResponseError;
-use meilisearch_lib::index::{default_crop_length, SearchQuery, DEFAULT_SEARCH_LIMIT};
+use meilisearch_lib::index::{
+    default_crop_length, default_crop_snippets, default_max_values_per_facet, SearchQuery,
+    DEFAULT_SEARCH_LIMIT,
+};
 use meilisearch_lib::MeiliSearch;
 use serde::Deserialize;
 use serde_json::Value;
@@ -23,16 +26,33 @@ pub struct SearchQueryGet {
     q: Option<String>,
     offset: Option<usize>,
     limit: Option<usize>,
+    page: Option<usize>,
+    hits_per_page: Option<usize>,
     attributes_to_retrieve: Option<String>,
     attributes_to_crop: Option<String>,
     #[serde(default = "default_crop_length")]
     crop_length: usize,
+    #[serde(default = "default_crop_snippets")]
+    crop_snippets: usize,
     attributes_to_highlight: Option<String>,
+    highlight_pre_tag: Option<String>,
+    highlight_post_tag: Option<String>,
+    crop_marker: Option<String>,
     filter: Option<String>,
     sort: Option<String>,
     #[serde(default = "Default::default")]
     matches: bool,
+    #[serde(default = "Default::default")]
+    show_ranking_score: bool,
+    #[serde(default = "Default::default")]
+    show_ranking_score_details: bool,
     facets_distribution: Option<String>,
+    #[serde(default = "Default::default")]
+    exhaustive_facets_count: bool,
+    #[serde(default = "default_max_values_per_facet")]
+    max_values_per_facet: usize,
+    #[serde(default = "Default::default")]
+    show_facet_stats: bool,
 }
 
 impl From<SearchQueryGet> for SearchQuery {
@@ -67,14 +87,25 @@ impl From<SearchQueryGet> for SearchQuery {
             q: other.q,
             offset: other.offset,
             limit: other.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+            page: other.page,
+            hits_per_page: other.hits_per_page,
             attributes_to_retrieve,
             attributes_to_crop,
             crop_length: other.crop_length,
+            crop_snippets: other.crop_snippets,
             attributes_to_highlight,
+            highlight_pre_tag: other.highlight_pre_tag,
+            highlight_post_tag: other.highlight_post_tag,
+            crop_marker: other.crop_marker,
             filter,
             sort,
             matches: other.matches,
+            show_ranking_score: other.show_ranking_score,
+            show_ranking_score_details: other.show_ranking_score_details,
             facets_distribution,
+            exhaustive_facets_count: other.exhaustive_facets_count,
+            max_values_per_facet: other.max_values_per_facet,
+            show_facet_stats: other.show_facet_stats,
         }
     }
 }
@@ -125,10 +156,6 @@ pub async fn search_with_url_query(
 
     let search_result = search_result?;
 
-    // Tests that the nb_hits is always set to false
-    #[cfg(test)]
-    assert!(!search_result.exhaustive_nb_hits);
-
     debug!("returns: {:?}", search_result);
     Ok(HttpResponse::Ok().json(search_result))
 }
@@ -153,10 +180,6 @@ pub async fn search_with_post(
 
     let search_result = search_result?;
 
-    // Tests that the nb_hits is always set to false
-    #[cfg(test)]
-    assert!(!search_result.exhaustive_nb_hits);
-
     debug!("returns: {:?}", search_result);
     Ok(HttpResponse::Ok().json(search_result))
 }
@@ -9,7 +9,8 @@ use serde_json::json;
 
 use crate::analytics::Analytics;
 use crate::extractors::authentication::{policies::*, GuardedData};
-use crate::task::SummarizedTaskView;
+use crate::extractors::idempotency_key::IdempotencyKey;
+use crate::task::{ScheduleQuery, SummarizedTaskView};
 
 #[macro_export]
 macro_rules! make_setting_route {
@@ -23,12 +24,15 @@ macro_rules! make_setting_route {
 
             use crate::analytics::Analytics;
             use crate::extractors::authentication::{policies::*, GuardedData};
-            use crate::task::SummarizedTaskView;
+            use crate::extractors::idempotency_key::IdempotencyKey;
+            use crate::task::{ScheduleQuery, SummarizedTaskView};
             use meilisearch_error::ResponseError;
 
             pub async fn delete(
                 meilisearch: GuardedData<ActionPolicy<{ actions::SETTINGS_UPDATE }>, MeiliSearch>,
                 index_uid: web::Path<String>,
+                schedule: web::Query<ScheduleQuery>,
+                idempotency_key: IdempotencyKey,
             ) -> Result<HttpResponse, ResponseError> {
                 let settings = Settings {
                     $attr: Setting::Reset,
@@ -42,7 +46,12 @@ macro_rules! make_setting_route {
                     allow_index_creation,
                 };
                 let task: SummarizedTaskView = meilisearch
-                    .register_update(index_uid.into_inner(), update)
+                    .register_update(
+                        index_uid.into_inner(),
+                        update,
+                        schedule.not_before,
+                        idempotency_key.into_inner(),
+                    )
                     .await?
                     .into();
 
@@ -54,6 +63,8 @@ macro_rules! make_setting_route {
                 meilisearch: GuardedData<ActionPolicy<{ actions::SETTINGS_UPDATE }>, MeiliSearch>,
                 index_uid: actix_web::web::Path<String>,
                 body: actix_web::web::Json<Option<$type>>,
+                schedule: web::Query<ScheduleQuery>,
+                idempotency_key: IdempotencyKey,
                 req: HttpRequest,
                 $analytics_var: web::Data<dyn Analytics>,
             ) -> std::result::Result<HttpResponse, ResponseError> {
@@ -76,7 +87,12 @@ macro_rules! make_setting_route {
                     allow_index_creation,
                 };
                 let task: SummarizedTaskView = meilisearch
-                    .register_update(index_uid.into_inner(), update)
+                    .register_update(
+                        index_uid.into_inner(),
+                        update,
+                        schedule.not_before,
+                        idempotency_key.into_inner(),
+                    )
                     .await?
                     .into();
 
@@ -223,6 +239,24 @@ make_setting_route!(
     }
 );
 
+make_setting_route!("/max-total-hits", usize, max_total_hits, "maxTotalHits");
+
+make_setting_route!(
+    "/highlight-pre-tag",
+    String,
+    highlight_pre_tag,
+    "highlightPreTag"
+);
+
+make_setting_route!(
+    "/highlight-post-tag",
+    String,
+    highlight_post_tag,
+    "highlightPostTag"
+);
+
+make_setting_route!("/crop-marker", String, crop_marker, "cropMarker");
+
 macro_rules! generate_configure {
     ($($mod:ident),*) => {
         pub fn configure(cfg: &mut web::ServiceConfig) {
@@ -244,13 +278,19 @@ generate_configure!(
     distinct_attribute,
     stop_words,
     synonyms,
-    ranking_rules
+    ranking_rules,
+    max_total_hits,
+    highlight_pre_tag,
+    highlight_post_tag,
+    crop_marker
 );
 
 pub async fn update_all(
     meilisearch: GuardedData<ActionPolicy<{ actions::SETTINGS_UPDATE }>, MeiliSearch>,
     index_uid: web::Path<String>,
     body: web::Json<Settings<Unchecked>>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
     req: HttpRequest,
     analytics: web::Data<dyn Analytics>,
 ) -> Result<HttpResponse, ResponseError> {
@@ -284,7 +324,12 @@ pub async fn update_all(
         allow_index_creation,
     };
     let task: SummarizedTaskView = meilisearch
-        .register_update(index_uid.into_inner(), update)
+        .register_update(
+            index_uid.into_inner(),
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
         .await?
         .into();
 
@@ -304,6 +349,8 @@ pub async fn get_all(
 pub async fn delete_all(
     data: GuardedData<ActionPolicy<{ actions::SETTINGS_UPDATE }>, MeiliSearch>,
     index_uid: web::Path<String>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
 ) -> Result<HttpResponse, ResponseError> {
     let settings = Settings::cleared().into_unchecked();
 
@@ -314,7 +361,12 @@ pub async fn delete_all(
         allow_index_creation,
     };
     let task: SummarizedTaskView = data
-        .register_update(index_uid.into_inner(), update)
+        .register_update(
+            index_uid.into_inner(),
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
         .await?
         .into();
 
@@ -2,17 +2,24 @@ use actix_web::{web, HttpRequest, HttpResponse};
 use chrono::{DateTime, Utc};
 use log::debug;
 use meilisearch_error::ResponseError;
+use meilisearch_lib::tasks::TaskFilter;
 use meilisearch_lib::MeiliSearch;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::analytics::Analytics;
 use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::routes::tasks::task_event_stream;
 use crate::task::{TaskListView, TaskView};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::resource("").route(web::get().to(get_all_tasks_status)))
-        .service(web::resource("{task_id}").route(web::get().to(get_task_status)));
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(get_all_tasks_status))
+            .route(web::delete().to(cancel_all_tasks)),
+    )
+    .service(web::resource("/stream").route(web::get().to(stream_index_tasks)))
+    .service(web::resource("{task_id}").route(web::get().to(get_task_status)));
 }
 
 #[derive(Debug, Serialize)]
@@ -46,6 +53,7 @@ pub async fn get_task_status(
     let UpdateParam { index_uid, task_id } = index_uid.into_inner();
 
     let task: TaskView = meilisearch.get_index_task(index_uid, task_id).await?.into();
+    let task = task.with_progress(meilisearch.get_task_progress(task_id));
 
     debug!("returns: {:?}", task);
     Ok(HttpResponse::Ok().json(task))
@@ -67,6 +75,55 @@ pub async fn get_all_tasks_status(
         .list_index_task(index_uid.into_inner(), None, None)
         .await?
         .into_iter()
+        .map(|task| {
+            let progress = meilisearch.get_task_progress(task.id);
+            TaskView::from(task).with_progress(progress)
+        })
+        .collect::<Vec<_>>()
+        .into();
+
+    debug!("returns: {:?}", tasks);
+    Ok(HttpResponse::Ok().json(tasks))
+}
+
+pub async fn stream_index_tasks(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_GET }>, MeiliSearch>,
+    index_uid: web::Path<String>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    analytics.publish(
+        "Tasks Streamed".to_string(),
+        json!({ "per_index_uid": true }),
+        Some(&req),
+    );
+
+    let index_uid = index_uid.into_inner();
+    // Returns an error if the index doesn't exist.
+    meilisearch.get_index(index_uid.clone()).await?;
+
+    let mut filters = TaskFilter::default();
+    filters.filter_index(index_uid);
+
+    Ok(task_event_stream(&meilisearch, filters))
+}
+
+pub async fn cancel_all_tasks(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_CANCEL }>, MeiliSearch>,
+    index_uid: web::Path<String>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    analytics.publish(
+        "Task Canceled".to_string(),
+        json!({ "per_index_uid": true }),
+        Some(&req),
+    );
+
+    let tasks: TaskListView = meilisearch
+        .cancel_index_tasks(index_uid.into_inner())
+        .await?
+        .into_iter()
         .map(TaskView::from)
         .collect::<Vec<_>>()
         .into();
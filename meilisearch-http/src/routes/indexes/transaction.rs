@@ -0,0 +1,135 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::debug;
+use meilisearch_error::ResponseError;
+use meilisearch_lib::index::{Document, Settings, Unchecked};
+use meilisearch_lib::index_controller::{TransactionUpdate, Update};
+use meilisearch_lib::milli::update::IndexDocumentsMethod;
+use meilisearch_lib::MeiliSearch;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::analytics::Analytics;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::idempotency_key::IdempotencyKey;
+use crate::task::{ScheduleQuery, SummarizedTaskView};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::post().to(create_transaction)));
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TransactionPayload {
+    operations: Vec<TransactionOperationPayload>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum TransactionOperationPayload {
+    #[serde(rename_all = "camelCase")]
+    DocumentAddition {
+        documents: Vec<Document>,
+        primary_key: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    DocumentPartial {
+        documents: Vec<Document>,
+        primary_key: Option<String>,
+    },
+    DocumentDeletion {
+        ids: Vec<Value>,
+    },
+    ClearAll,
+    SettingsUpdate {
+        settings: Settings<Unchecked>,
+    },
+}
+
+impl TransactionOperationPayload {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::DocumentAddition { .. } => "documentAddition",
+            Self::DocumentPartial { .. } => "documentPartial",
+            Self::DocumentDeletion { .. } => "documentDeletion",
+            Self::ClearAll => "clearAll",
+            Self::SettingsUpdate { .. } => "settingsUpdate",
+        }
+    }
+}
+
+impl From<TransactionOperationPayload> for TransactionUpdate {
+    fn from(operation: TransactionOperationPayload) -> Self {
+        match operation {
+            TransactionOperationPayload::DocumentAddition {
+                documents,
+                primary_key,
+            } => TransactionUpdate::DocumentAddition {
+                documents,
+                primary_key,
+                method: IndexDocumentsMethod::ReplaceDocuments,
+            },
+            TransactionOperationPayload::DocumentPartial {
+                documents,
+                primary_key,
+            } => TransactionUpdate::DocumentAddition {
+                documents,
+                primary_key,
+                method: IndexDocumentsMethod::UpdateDocuments,
+            },
+            TransactionOperationPayload::DocumentDeletion { ids } => {
+                let ids = ids
+                    .iter()
+                    .map(|v| {
+                        v.as_str()
+                            .map(String::from)
+                            .unwrap_or_else(|| v.to_string())
+                    })
+                    .collect();
+                TransactionUpdate::DeleteDocuments(ids)
+            }
+            TransactionOperationPayload::ClearAll => TransactionUpdate::ClearDocuments,
+            TransactionOperationPayload::SettingsUpdate { settings } => {
+                TransactionUpdate::Settings(settings)
+            }
+        }
+    }
+}
+
+pub async fn create_transaction(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TRANSACTIONS_CREATE }>, MeiliSearch>,
+    index_uid: web::Path<String>,
+    body: web::Json<TransactionPayload>,
+    schedule: web::Query<ScheduleQuery>,
+    idempotency_key: IdempotencyKey,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    let TransactionPayload { operations } = body.into_inner();
+
+    analytics.publish(
+        "Transaction Created".to_string(),
+        json!({
+            "total_operations": operations.len(),
+            "operations": operations.iter().map(|operation| operation.name()).collect::<Vec<_>>(),
+        }),
+        Some(&req),
+    );
+
+    let allow_index_creation = meilisearch.filters().allow_index_creation;
+    let update = Update::Transaction {
+        operations: operations.into_iter().map(Into::into).collect(),
+        allow_index_creation,
+    };
+    let task: SummarizedTaskView = meilisearch
+        .register_update(
+            index_uid.into_inner(),
+            update,
+            schedule.not_before,
+            idempotency_key.into_inner(),
+        )
+        .await?
+        .into();
+
+    debug!("returns: {:?}", task);
+    Ok(HttpResponse::Accepted().json(task))
+}
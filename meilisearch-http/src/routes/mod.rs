@@ -1,4 +1,4 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use chrono::{DateTime, Utc};
 use log::debug;
 use serde::{Deserialize, Serialize};
@@ -10,18 +10,24 @@ use meilisearch_lib::MeiliSearch;
 use crate::extractors::authentication::{policies::*, GuardedData};
 
 mod api_key;
+mod batches;
 mod dump;
 pub mod indexes;
+mod multi_search;
 mod tasks;
+mod webhooks;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/tasks").configure(tasks::configure))
+        .service(web::scope("/batches").configure(batches::configure))
         .service(web::resource("/health").route(web::get().to(get_health)))
         .service(web::scope("/keys").configure(api_key::configure))
         .service(web::scope("/dumps").configure(dump::configure))
         .service(web::resource("/stats").route(web::get().to(get_stats)))
         .service(web::resource("/version").route(web::get().to(get_version)))
-        .service(web::scope("/indexes").configure(indexes::configure));
+        .service(web::scope("/webhooks").configure(webhooks::configure))
+        .service(web::scope("/indexes").configure(indexes::configure))
+        .service(web::scope("/multi-search").configure(multi_search::configure));
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -162,6 +168,13 @@ struct KeysResponse {
     public: Option<String>,
 }
 
-pub async fn get_health() -> Result<HttpResponse, ResponseError> {
-    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "available" })))
+pub async fn get_health(req: HttpRequest) -> Result<HttpResponse, ResponseError> {
+    let is_indexing_paused = req
+        .app_data::<MeiliSearch>()
+        .map_or(false, |meilisearch| meilisearch.is_indexing_paused());
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "available",
+        "isIndexingPaused": is_indexing_paused,
+    })))
 }
@@ -0,0 +1,234 @@
+use std::cmp::Ordering;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures::future::join_all;
+use log::debug;
+use meilisearch_error::ResponseError;
+use meilisearch_lib::index::{SearchHit, SearchQuery, SearchResult, DEFAULT_SEARCH_LIMIT};
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::analytics::Analytics;
+use crate::extractors::authentication::error::AuthenticationError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::post().to(multi_search_with_post)));
+}
+
+const fn default_search_limit() -> usize {
+    DEFAULT_SEARCH_LIMIT
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MultiSearchPayload {
+    queries: Vec<IndexSearchQuery>,
+    /// When given, the hits of all the queries are merged in a single list.
+    federation: Option<Federation>,
+}
+
+/// A search query along with the index it targets. `SearchQuery` denies the unknown fields, so it
+/// can't be flattened in this struct: the `indexUid` is taken out of the object before the rest
+/// of it is deserialized as a `SearchQuery`.
+#[derive(Deserialize, Debug)]
+#[serde(try_from = "Map<String, Value>")]
+struct IndexSearchQuery {
+    index_uid: String,
+    query: SearchQuery,
+}
+
+impl TryFrom<Map<String, Value>> for IndexSearchQuery {
+    type Error = String;
+
+    fn try_from(mut object: Map<String, Value>) -> Result<Self, Self::Error> {
+        let index_uid = match object.remove("indexUid") {
+            Some(Value::String(index_uid)) => index_uid,
+            Some(_) => return Err("`indexUid` must be a string".to_string()),
+            None => return Err("missing field `indexUid`".to_string()),
+        };
+        let query = serde_json::from_value(Value::Object(object)).map_err(|e| e.to_string())?;
+
+        Ok(Self { index_uid, query })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Federation {
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+    #[serde(default)]
+    offset: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IndexSearchResult {
+    index_uid: String,
+    #[serde(flatten)]
+    result: SearchResult,
+}
+
+#[derive(Serialize, Debug)]
+struct MultiSearchResult {
+    results: Vec<IndexSearchResult>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HitFederation {
+    index_uid: String,
+    /// The position of the query the hit comes from
+    queries_position: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct FederatedHit {
+    #[serde(flatten)]
+    hit: SearchHit,
+    #[serde(rename = "_federation")]
+    federation: HitFederation,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FederatedSearchResult {
+    hits: Vec<FederatedHit>,
+    nb_hits: u64,
+    limit: usize,
+    offset: usize,
+    processing_time_ms: u128,
+}
+
+/// Merges the results of the queries in a single list of hits, by descending ranking score. The
+/// hits with the same score are kept in the order of the queries, then in the order of their
+/// query. The ranking score is only kept on the hits of the queries that asked for it in
+/// `show_ranking_score`.
+fn merge_results(
+    results: Vec<IndexSearchResult>,
+    show_ranking_score: &[bool],
+    federation: &Federation,
+) -> FederatedSearchResult {
+    let nb_hits = results.iter().map(|result| result.result.nb_hits).sum();
+    let processing_time_ms = results
+        .iter()
+        .map(|result| result.result.processing_time_ms)
+        .max()
+        .unwrap_or_default();
+
+    let mut scored_hits: Vec<_> = results
+        .into_iter()
+        .enumerate()
+        .flat_map(|(position, result)| {
+            let index_uid = result.index_uid;
+            result.result.hits.into_iter().map(move |hit| {
+                let score = hit.ranking_score.unwrap_or_default();
+                let hit = FederatedHit {
+                    hit,
+                    federation: HitFederation {
+                        index_uid: index_uid.clone(),
+                        queries_position: position,
+                    },
+                };
+                (score, hit)
+            })
+        })
+        .collect();
+    // The sort is stable, and the scores are never NaN.
+    scored_hits.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+    let hits = scored_hits
+        .into_iter()
+        .skip(federation.offset)
+        .take(federation.limit)
+        .map(|(_, mut hit)| {
+            if !show_ranking_score[hit.federation.queries_position] {
+                hit.hit.ranking_score = None;
+            }
+            hit
+        })
+        .collect();
+
+    FederatedSearchResult {
+        hits,
+        nb_hits,
+        limit: federation.limit,
+        offset: federation.offset,
+        processing_time_ms,
+    }
+}
+
+pub async fn multi_search_with_post(
+    meilisearch: GuardedData<ActionPolicy<{ actions::SEARCH }>, MeiliSearch>,
+    params: web::Json<MultiSearchPayload>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    let MultiSearchPayload {
+        mut queries,
+        federation,
+    } = params.into_inner();
+    debug!("multi search called with params: {:?}", queries);
+
+    analytics.publish(
+        "Multi Search".to_string(),
+        json!({
+            "total_queries": queries.len(),
+            "federated": federation.is_some(),
+        }),
+        Some(&req),
+    );
+
+    // The index routes check the index against the API key before the request is handled, here
+    // each query must be checked.
+    if let Some(indexes) = meilisearch.filters().indexes.as_ref() {
+        if let Some(query) = queries.iter().find(|q| !indexes.contains(&q.index_uid)) {
+            return Err(AuthenticationError::UnauthorizedIndex(query.index_uid.clone()).into());
+        }
+    }
+
+    let show_ranking_score: Vec<bool> =
+        queries.iter().map(|q| q.query.show_ranking_score).collect();
+    if let Some(ref federation) = federation {
+        // Each query must return enough hits to fill the requested page of the merged list, with
+        // their ranking score to merge them.
+        for IndexSearchQuery { query, .. } in &mut queries {
+            query.limit = federation.offset + federation.limit;
+            query.offset = None;
+            query.page = None;
+            query.hits_per_page = None;
+            query.show_ranking_score = true;
+        }
+    }
+
+    let index_uids: Vec<String> = queries.iter().map(|q| q.index_uid.clone()).collect();
+    let searches = queries
+        .into_iter()
+        .map(|IndexSearchQuery { index_uid, query }| meilisearch.search(index_uid, query));
+    let results = join_all(searches)
+        .await
+        .into_iter()
+        .zip(index_uids)
+        .map(|(result, index_uid)| {
+            Ok(IndexSearchResult {
+                index_uid,
+                result: result?,
+            })
+        })
+        .collect::<Result<Vec<_>, ResponseError>>()?;
+
+    match federation {
+        Some(federation) => {
+            let result = merge_results(results, &show_ranking_score, &federation);
+            debug!("returns: {:?}", result);
+            Ok(HttpResponse::Ok().json(result))
+        }
+        None => {
+            let result = MultiSearchResult { results };
+            debug!("returns: {:?}", result);
+            Ok(HttpResponse::Ok().json(result))
+        }
+    }
+}
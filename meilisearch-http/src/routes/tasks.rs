@@ -1,45 +1,148 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use actix_web::http::header::{CACHE_CONTROL, CONTENT_ENCODING};
 use actix_web::{web, HttpRequest, HttpResponse};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use meilisearch_error::ResponseError;
-use meilisearch_lib::tasks::task::TaskId;
+use meilisearch_lib::tasks::task::{TaskEvent, TaskId};
 use meilisearch_lib::tasks::TaskFilter;
 use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::analytics::Analytics;
 use crate::extractors::authentication::{policies::*, GuardedData};
-use crate::task::{TaskListView, TaskView};
+use crate::task::{SummarizedTaskView, TaskListView, TaskStatus, TaskType, TaskView};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(web::resource("").route(web::get().to(get_tasks)))
-        .service(web::resource("/{task_id}").route(web::get().to(get_task)));
+        .service(web::resource("/prune").route(web::post().to(prune_tasks)))
+        .service(web::resource("/pause").route(web::post().to(pause_tasks)))
+        .service(web::resource("/resume").route(web::post().to(resume_tasks)))
+        .service(web::resource("/stream").route(web::get().to(stream_tasks)))
+        .service(
+            web::resource("/{task_id}")
+                .route(web::get().to(get_task))
+                .route(web::delete().to(cancel_task)),
+        )
+        .service(web::resource("/{task_id}/retry").route(web::post().to(retry_task)));
+}
+
+/// The interval at which a comment is sent on the task streams, so that idle connections aren't
+/// closed by proxies.
+const TASK_STREAM_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The query parameters of the task list. Unknown parameters are ignored, and when no `limit` is
+/// given all the matching tasks are returned, as before the pagination was introduced.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TasksFilterQuery {
+    #[serde(rename = "type")]
+    type_: Option<String>,
+    status: Option<String>,
+    enqueued_after: Option<DateTime<Utc>>,
+    enqueued_before: Option<DateTime<Utc>>,
+    finished_after: Option<DateTime<Utc>>,
+    finished_before: Option<DateTime<Utc>>,
+    limit: Option<usize>,
+    from: Option<TaskId>,
+}
+
+/// Parses a comma separated list of values.
+fn parse_list<T: FromStr>(list: &str) -> Result<Vec<T>, T::Err> {
+    list.split(',').map(|s| s.trim().parse()).collect()
 }
 
 async fn get_tasks(
     meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_GET }>, MeiliSearch>,
+    params: web::Query<TasksFilterQuery>,
     req: HttpRequest,
     analytics: web::Data<dyn Analytics>,
 ) -> Result<HttpResponse, ResponseError> {
     analytics.publish(
         "Tasks Seen".to_string(),
-        json!({ "per_task_uid": false }),
+        json!({
+            "per_task_uid": false,
+            "filtered_by_type": params.type_.is_some(),
+            "filtered_by_status": params.status.is_some(),
+        }),
         Some(&req),
     );
 
-    let filters = meilisearch.filters().indexes.as_ref().map(|indexes| {
-        let mut filters = TaskFilter::default();
+    let TasksFilterQuery {
+        type_,
+        status,
+        enqueued_after,
+        enqueued_before,
+        finished_after,
+        finished_before,
+        limit,
+        from,
+    } = params.into_inner();
+
+    let mut filters = TaskFilter::default();
+    if let Some(indexes) = meilisearch.filters().indexes.as_ref() {
         for index in indexes {
             filters.filter_index(index.to_string());
         }
-        filters
-    });
+    }
 
-    let tasks: TaskListView = meilisearch
-        .list_tasks(filters, None, None)
-        .await?
+    if let Some(types) = type_ {
+        let types: Vec<TaskType> = parse_list(&types)?;
+        filters.filter_fn(move |task| types.contains(&TaskType::from(&task.content)));
+    }
+
+    if let Some(statuses) = status {
+        let statuses: Vec<TaskStatus> = parse_list(&statuses)?;
+        filters.filter_fn(move |task| statuses.contains(&TaskStatus::from(task)));
+    }
+
+    if enqueued_after.is_some() || enqueued_before.is_some() {
+        filters.filter_fn(move |task| {
+            task.enqueued_at().map_or(false, |enqueued_at| {
+                enqueued_after.map_or(true, |after| enqueued_at > after)
+                    && enqueued_before.map_or(true, |before| enqueued_at < before)
+            })
+        });
+    }
+
+    if finished_after.is_some() || finished_before.is_some() {
+        filters.filter_fn(move |task| {
+            task.finished_at().map_or(false, |finished_at| {
+                finished_after.map_or(true, |after| finished_at > after)
+                    && finished_before.map_or(true, |before| finished_at < before)
+            })
+        });
+    }
+
+    // We fetch one more task to know if there is a next page.
+    let mut tasks = meilisearch
+        .list_tasks(
+            Some(filters),
+            limit.map(|limit| limit.saturating_add(1)),
+            from,
+        )
+        .await?;
+
+    let next = match limit {
+        Some(limit) if tasks.len() > limit => tasks.pop().map(|task| task.id),
+        _ => None,
+    };
+
+    let results = tasks
         .into_iter()
-        .map(TaskView::from)
-        .collect::<Vec<_>>()
-        .into();
+        .map(|task| {
+            let progress = meilisearch.get_task_progress(task.id);
+            TaskView::from(task).with_progress(progress)
+        })
+        .collect::<Vec<_>>();
+    let tasks = match limit {
+        Some(limit) => TaskListView::paginated(results, limit, next),
+        None => TaskListView::from(results),
+    };
 
     Ok(HttpResponse::Ok().json(tasks))
 }
@@ -64,10 +167,185 @@ async fn get_task(
         filters
     });
 
+    let task_id = task_id.into_inner();
+    let task: TaskView = meilisearch.get_task(task_id, filters).await?.into();
+    let task = task.with_progress(meilisearch.get_task_progress(task_id));
+
+    Ok(HttpResponse::Ok().json(task))
+}
+
+async fn cancel_task(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_CANCEL }>, MeiliSearch>,
+    task_id: web::Path<TaskId>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    analytics.publish(
+        "Task Canceled".to_string(),
+        json!({ "per_index_uid": false }),
+        Some(&req),
+    );
+
+    let filters = meilisearch.filters().indexes.as_ref().map(|indexes| {
+        let mut filters = TaskFilter::default();
+        for index in indexes {
+            filters.filter_index(index.to_string());
+        }
+        filters
+    });
+
     let task: TaskView = meilisearch
-        .get_task(task_id.into_inner(), filters)
+        .cancel_task(task_id.into_inner(), filters)
         .await?
         .into();
 
     Ok(HttpResponse::Ok().json(task))
 }
+
+async fn retry_task(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_RETRY }>, MeiliSearch>,
+    task_id: web::Path<TaskId>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    analytics.publish("Task Retried".to_string(), json!({}), Some(&req));
+
+    let filters = meilisearch.filters().indexes.as_ref().map(|indexes| {
+        let mut filters = TaskFilter::default();
+        for index in indexes {
+            filters.filter_index(index.to_string());
+        }
+        filters
+    });
+
+    let task: SummarizedTaskView = meilisearch
+        .retry_task(task_id.into_inner(), filters)
+        .await?
+        .into();
+
+    Ok(HttpResponse::Accepted().json(task))
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PruneTasksPayload {
+    max_age_sec: Option<u64>,
+    max_count: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PruneTasksResponse {
+    deleted_tasks: usize,
+}
+
+async fn prune_tasks(
+    meilisearch: GuardedData<MasterPolicy, MeiliSearch>,
+    body: Option<web::Json<PruneTasksPayload>>,
+) -> Result<HttpResponse, ResponseError> {
+    let PruneTasksPayload {
+        max_age_sec,
+        max_count,
+    } = body.map(web::Json::into_inner).unwrap_or_default();
+
+    let deleted_tasks = meilisearch.prune_tasks(max_age_sec, max_count).await?;
+
+    Ok(HttpResponse::Ok().json(PruneTasksResponse { deleted_tasks }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PausedResponse {
+    is_indexing_paused: bool,
+}
+
+async fn pause_tasks(
+    meilisearch: GuardedData<MasterPolicy, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    meilisearch.pause_tasks().await?;
+
+    Ok(HttpResponse::Ok().json(PausedResponse {
+        is_indexing_paused: meilisearch.is_indexing_paused(),
+    }))
+}
+
+async fn resume_tasks(
+    meilisearch: GuardedData<MasterPolicy, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    meilisearch.resume_tasks().await?;
+
+    Ok(HttpResponse::Ok().json(PausedResponse {
+        is_indexing_paused: meilisearch.is_indexing_paused(),
+    }))
+}
+
+async fn stream_tasks(
+    meilisearch: GuardedData<ActionPolicy<{ actions::TASKS_GET }>, MeiliSearch>,
+    req: HttpRequest,
+    analytics: web::Data<dyn Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    analytics.publish(
+        "Tasks Streamed".to_string(),
+        json!({ "per_index_uid": false }),
+        Some(&req),
+    );
+
+    let mut filters = TaskFilter::default();
+    if let Some(indexes) = meilisearch.filters().indexes.as_ref() {
+        for index in indexes {
+            filters.filter_index(index.to_string());
+        }
+    }
+
+    Ok(task_event_stream(&meilisearch, filters))
+}
+
+/// Returns a response that sends a server-sent event each time a task matching the filter gains
+/// a new event. The event is named after the task event, and its data is the `TaskView` of the
+/// task at that point.
+pub(super) fn task_event_stream(meilisearch: &MeiliSearch, filter: TaskFilter) -> HttpResponse {
+    // We subscribe before returning the response so that no event is missed.
+    let mut receiver = meilisearch.subscribe_tasks();
+
+    let events = async_stream::stream! {
+        let mut keep_alive = tokio::time::interval(TASK_STREAM_KEEP_ALIVE_INTERVAL);
+        loop {
+            let event = tokio::select! {
+                update = receiver.recv() => match update {
+                    Ok(task) if filter.pass(&task) => {
+                        let name = task.events.last().map_or("unknown", event_name);
+                        serde_json::to_string(&TaskView::from(task))
+                            .ok()
+                            .map(|data| format!("event: {}\ndata: {}\n\n", name, data))
+                    }
+                    Ok(_) => None,
+                    Err(RecvError::Lagged(missed)) => Some(format!(": missed {} events\n\n", missed)),
+                    Err(RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => Some(": keep-alive\n\n".to_string()),
+            };
+
+            if let Some(event) = event {
+                yield Ok::<_, ResponseError>(Bytes::from(event));
+            }
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        // Prevents the compression middleware from buffering the events.
+        .insert_header((CONTENT_ENCODING, "identity"))
+        .streaming(events)
+}
+
+fn event_name(event: &TaskEvent) -> &'static str {
+    match event {
+        TaskEvent::Created(_) => "created",
+        TaskEvent::Batched { .. } => "batched",
+        TaskEvent::Processing(_) => "processing",
+        TaskEvent::Succeded { .. } => "succeeded",
+        TaskEvent::Failed { .. } => "failed",
+        TaskEvent::Cancelled(_) => "canceled",
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use meilisearch_error::ResponseError;
+use meilisearch_lib::tasks::webhook::Webhook;
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::extractors::authentication::{policies::*, GuardedData};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(list_webhooks))
+            .route(web::post().to(register_webhook)),
+    )
+    .service(web::resource("/{webhook_uid}").route(web::delete().to(delete_webhook)));
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RegisterWebhookPayload {
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+/// Shown in place of the values of the headers, which often hold secrets such as the tokens of
+/// the `Authorization` header.
+const MASKED_HEADER_VALUE: &str = "***";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookView {
+    uid: Uuid,
+    url: String,
+    headers: BTreeMap<String, &'static str>,
+}
+
+impl From<Webhook> for WebhookView {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            uid: webhook.uid,
+            url: webhook.url,
+            headers: webhook
+                .headers
+                .into_keys()
+                .map(|name| (name, MASKED_HEADER_VALUE))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookListView {
+    results: Vec<WebhookView>,
+}
+
+async fn list_webhooks(
+    meilisearch: GuardedData<MasterPolicy, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let results = meilisearch
+        .list_webhooks()
+        .await
+        .into_iter()
+        .map(WebhookView::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(WebhookListView { results }))
+}
+
+async fn register_webhook(
+    meilisearch: GuardedData<MasterPolicy, MeiliSearch>,
+    body: web::Json<RegisterWebhookPayload>,
+) -> Result<HttpResponse, ResponseError> {
+    let RegisterWebhookPayload { url, headers } = body.into_inner();
+    let webhook = meilisearch.register_webhook(url, headers).await?;
+
+    Ok(HttpResponse::Created().json(WebhookView::from(webhook)))
+}
+
+async fn delete_webhook(
+    meilisearch: GuardedData<MasterPolicy, MeiliSearch>,
+    webhook_uid: web::Path<String>,
+) -> Result<HttpResponse, ResponseError> {
+    meilisearch.delete_webhook(webhook_uid.into_inner()).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
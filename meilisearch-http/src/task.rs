@@ -1,15 +1,22 @@
+use std::str::FromStr;
+
 use chrono::{DateTime, Duration, Utc};
 use meilisearch_error::ResponseError;
 use meilisearch_lib::index::{Settings, Unchecked};
 use meilisearch_lib::milli::update::IndexDocumentsMethod;
+use meilisearch_lib::tasks::batch::BatchId;
 use meilisearch_lib::tasks::task::{
-    DocumentDeletion, Task, TaskContent, TaskEvent, TaskId, TaskResult,
+    DocumentDeletion, Task, TaskContent, TaskEvent, TaskId, TaskProgress, TaskResult,
+    TransactionOperation,
 };
-use serde::{Serialize, Serializer};
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize, Serializer};
 
-#[derive(Debug, Serialize)]
+use crate::error::MeilisearchHttpError;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-enum TaskType {
+pub enum TaskType {
     IndexCreation,
     IndexUpdate,
     IndexDeletion,
@@ -18,10 +25,17 @@ enum TaskType {
     DocumentDeletion,
     SettingsUpdate,
     ClearAll,
+    Transaction,
 }
 
 impl From<TaskContent> for TaskType {
     fn from(other: TaskContent) -> Self {
+        Self::from(&other)
+    }
+}
+
+impl From<&TaskContent> for TaskType {
+    fn from(other: &TaskContent) -> Self {
         match other {
             TaskContent::DocumentAddition {
                 merge_strategy: IndexDocumentsMethod::ReplaceDocuments,
@@ -37,18 +51,79 @@ impl From<TaskContent> for TaskType {
             TaskContent::IndexDeletion => TaskType::IndexDeletion,
             TaskContent::IndexCreation { .. } => TaskType::IndexCreation,
             TaskContent::IndexUpdate { .. } => TaskType::IndexUpdate,
+            TaskContent::Transaction { .. } => TaskType::Transaction,
             _ => unreachable!("unexpected task type"),
         }
     }
 }
 
-#[derive(Debug, Serialize)]
+impl FromStr for TaskType {
+    type Err = MeilisearchHttpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "indexCreation" => Ok(TaskType::IndexCreation),
+            "indexUpdate" => Ok(TaskType::IndexUpdate),
+            "indexDeletion" => Ok(TaskType::IndexDeletion),
+            "documentAddition" => Ok(TaskType::DocumentAddition),
+            "documentPartial" => Ok(TaskType::DocumentPartial),
+            "documentDeletion" => Ok(TaskType::DocumentDeletion),
+            "settingsUpdate" => Ok(TaskType::SettingsUpdate),
+            "clearAll" => Ok(TaskType::ClearAll),
+            "transaction" => Ok(TaskType::Transaction),
+            _ => Err(MeilisearchHttpError::InvalidTaskType(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-enum TaskStatus {
+pub enum TaskStatus {
     Enqueued,
+    Scheduled,
     Processing,
     Succeeded,
     Failed,
+    Canceled,
+}
+
+impl From<&TaskEvent> for TaskStatus {
+    fn from(other: &TaskEvent) -> Self {
+        match other {
+            TaskEvent::Created(_) | TaskEvent::Batched { .. } => TaskStatus::Enqueued,
+            TaskEvent::Processing(_) => TaskStatus::Processing,
+            TaskEvent::Succeded { .. } => TaskStatus::Succeeded,
+            TaskEvent::Failed { .. } => TaskStatus::Failed,
+            TaskEvent::Cancelled(_) => TaskStatus::Canceled,
+        }
+    }
+}
+
+impl From<&Task> for TaskStatus {
+    fn from(task: &Task) -> Self {
+        if task.is_scheduled() {
+            return TaskStatus::Scheduled;
+        }
+
+        // A task always has at least one event: "Created"
+        TaskStatus::from(task.events.last().unwrap())
+    }
+}
+
+impl FromStr for TaskStatus {
+    type Err = MeilisearchHttpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "enqueued" => Ok(TaskStatus::Enqueued),
+            "scheduled" => Ok(TaskStatus::Scheduled),
+            "processing" => Ok(TaskStatus::Processing),
+            "succeeded" => Ok(TaskStatus::Succeeded),
+            "failed" => Ok(TaskStatus::Failed),
+            "canceled" => Ok(TaskStatus::Canceled),
+            _ => Err(MeilisearchHttpError::InvalidTaskStatus(s.to_string())),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -59,6 +134,8 @@ enum TaskDetails {
     DocumentAddition {
         received_documents: usize,
         indexed_documents: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retry_of: Option<TaskId>,
     },
     #[serde(rename_all = "camelCase")]
     Settings {
@@ -74,9 +151,139 @@ enum TaskDetails {
     },
     #[serde(rename_all = "camelCase")]
     ClearAll { deleted_documents: Option<u64> },
+    #[serde(rename_all = "camelCase")]
+    Transaction {
+        operations: Vec<TransactionOperationView>,
+    },
 }
 
-fn serialize_duration<S: Serializer>(
+#[derive(Debug, Serialize)]
+struct TransactionOperationView {
+    #[serde(rename = "type")]
+    operation_type: TaskType,
+    #[serde(flatten)]
+    details: TaskDetails,
+}
+
+impl From<TransactionOperation> for TransactionOperationView {
+    fn from(operation: TransactionOperation) -> Self {
+        let (operation_type, details) = match operation {
+            TransactionOperation::DocumentAddition {
+                merge_strategy,
+                documents_count,
+                ..
+            } => {
+                let details = TaskDetails::DocumentAddition {
+                    received_documents: documents_count,
+                    indexed_documents: None,
+                    retry_of: None,
+                };
+
+                let operation_type = match merge_strategy {
+                    IndexDocumentsMethod::UpdateDocuments => TaskType::DocumentPartial,
+                    IndexDocumentsMethod::ReplaceDocuments => TaskType::DocumentAddition,
+                    _ => unreachable!("Unexpected document merge strategy."),
+                };
+
+                (operation_type, details)
+            }
+            TransactionOperation::DocumentDeletion(DocumentDeletion::Ids(ids)) => (
+                TaskType::DocumentDeletion,
+                TaskDetails::DocumentDeletion {
+                    received_document_ids: ids.len(),
+                    deleted_documents: None,
+                },
+            ),
+            TransactionOperation::DocumentDeletion(DocumentDeletion::Clear) => (
+                TaskType::ClearAll,
+                TaskDetails::ClearAll {
+                    deleted_documents: None,
+                },
+            ),
+            TransactionOperation::SettingsUpdate { settings } => {
+                (TaskType::SettingsUpdate, TaskDetails::Settings { settings })
+            }
+        };
+
+        Self {
+            operation_type,
+            details,
+        }
+    }
+}
+
+impl TaskDetails {
+    /// Fills the details with the result of the succeeded task.
+    fn fill_with_result(&mut self, result: &TaskResult) {
+        match (result, self) {
+            (
+                TaskResult::DocumentAddition {
+                    indexed_documents: num,
+                    ..
+                },
+                TaskDetails::DocumentAddition {
+                    indexed_documents, ..
+                },
+            ) => {
+                indexed_documents.replace(*num);
+            }
+            (
+                TaskResult::DocumentDeletion {
+                    deleted_documents: docs,
+                    ..
+                },
+                TaskDetails::DocumentDeletion {
+                    deleted_documents, ..
+                },
+            ) => {
+                deleted_documents.replace(*docs);
+            }
+            (
+                TaskResult::ClearAll {
+                    deleted_documents: docs,
+                },
+                TaskDetails::ClearAll { deleted_documents },
+            ) => {
+                deleted_documents.replace(*docs);
+            }
+            (TaskResult::Transaction { results }, TaskDetails::Transaction { operations }) => {
+                for (result, operation) in results.iter().zip(operations) {
+                    operation.details.fill_with_result(result);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Fills the details of a failed task: nothing was applied.
+    fn fill_with_failure(&mut self) {
+        match self {
+            TaskDetails::DocumentDeletion {
+                deleted_documents, ..
+            } => {
+                deleted_documents.replace(0);
+            }
+            TaskDetails::ClearAll {
+                deleted_documents, ..
+            } => {
+                deleted_documents.replace(0);
+            }
+            TaskDetails::DocumentAddition {
+                indexed_documents, ..
+            } => {
+                indexed_documents.replace(0);
+            }
+            TaskDetails::Transaction { operations } => {
+                for operation in operations {
+                    operation.details.fill_with_failure();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+pub fn serialize_duration<S: Serializer>(
     duration: &Option<Duration>,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
@@ -100,32 +307,42 @@ pub struct TaskView {
     #[serde(skip_serializing_if = "Option::is_none")]
     details: Option<TaskDetails>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<TaskProgress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<ResponseError>,
     #[serde(serialize_with = "serialize_duration")]
     duration: Option<Duration>,
     enqueued_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_before: Option<DateTime<Utc>>,
     started_at: Option<DateTime<Utc>>,
     finished_at: Option<DateTime<Utc>>,
+    /// The batch the task was put in, once it is batched
+    batch_uid: Option<BatchId>,
 }
 
 impl From<Task> for TaskView {
     fn from(task: Task) -> Self {
+        let is_scheduled = task.is_scheduled();
         let Task {
             id,
             index_uid,
             content,
             events,
+            not_before,
         } = task;
 
         let (task_type, mut details) = match content {
             TaskContent::DocumentAddition {
                 merge_strategy,
                 documents_count,
+                retry_of,
                 ..
             } => {
                 let details = TaskDetails::DocumentAddition {
                     received_documents: documents_count,
                     indexed_documents: None,
+                    retry_of,
                 };
 
                 let task_type = match merge_strategy {
@@ -167,77 +384,33 @@ impl From<Task> for TaskView {
                 TaskType::IndexUpdate,
                 Some(TaskDetails::IndexInfo { primary_key }),
             ),
+            TaskContent::Transaction { operations, .. } => (
+                TaskType::Transaction,
+                Some(TaskDetails::Transaction {
+                    operations: operations.into_iter().map(Into::into).collect(),
+                }),
+            ),
         };
 
         // An event always has at least one event: "Created"
         let (status, error, finished_at) = match events.last().unwrap() {
+            TaskEvent::Created(_) if is_scheduled => (TaskStatus::Scheduled, None, None),
             TaskEvent::Created(_) => (TaskStatus::Enqueued, None, None),
             TaskEvent::Batched { .. } => (TaskStatus::Enqueued, None, None),
             TaskEvent::Processing(_) => (TaskStatus::Processing, None, None),
             TaskEvent::Succeded { timestamp, result } => {
-                match (result, &mut details) {
-                    (
-                        TaskResult::DocumentAddition {
-                            indexed_documents: num,
-                            ..
-                        },
-                        Some(TaskDetails::DocumentAddition {
-                            ref mut indexed_documents,
-                            ..
-                        }),
-                    ) => {
-                        indexed_documents.replace(*num);
-                    }
-                    (
-                        TaskResult::DocumentDeletion {
-                            deleted_documents: docs,
-                            ..
-                        },
-                        Some(TaskDetails::DocumentDeletion {
-                            ref mut deleted_documents,
-                            ..
-                        }),
-                    ) => {
-                        deleted_documents.replace(*docs);
-                    }
-                    (
-                        TaskResult::ClearAll {
-                            deleted_documents: docs,
-                        },
-                        Some(TaskDetails::ClearAll {
-                            ref mut deleted_documents,
-                        }),
-                    ) => {
-                        deleted_documents.replace(*docs);
-                    }
-                    _ => (),
+                if let Some(details) = details.as_mut() {
+                    details.fill_with_result(result);
                 }
                 (TaskStatus::Succeeded, None, Some(*timestamp))
             }
             TaskEvent::Failed { timestamp, error } => {
-                match details {
-                    Some(TaskDetails::DocumentDeletion {
-                        ref mut deleted_documents,
-                        ..
-                    }) => {
-                        deleted_documents.replace(0);
-                    }
-                    Some(TaskDetails::ClearAll {
-                        ref mut deleted_documents,
-                        ..
-                    }) => {
-                        deleted_documents.replace(0);
-                    }
-                    Some(TaskDetails::DocumentAddition {
-                        ref mut indexed_documents,
-                        ..
-                    }) => {
-                        indexed_documents.replace(0);
-                    }
-                    _ => (),
+                if let Some(details) = details.as_mut() {
+                    details.fill_with_failure();
                 }
                 (TaskStatus::Failed, Some(error.clone()), Some(*timestamp))
             }
+            TaskEvent::Cancelled(timestamp) => (TaskStatus::Canceled, None, Some(*timestamp)),
         };
 
         let enqueued_at = match events.first() {
@@ -252,32 +425,80 @@ impl From<Task> for TaskView {
 
         let duration = finished_at.zip(started_at).map(|(tf, ts)| (tf - ts));
 
+        let batch_uid = events.iter().rev().find_map(|e| match e {
+            TaskEvent::Batched { batch_id, .. } => Some(*batch_id),
+            _ => None,
+        });
+
         Self {
             uid: id,
             index_uid: index_uid.into_inner(),
             status,
             task_type,
             details,
+            progress: None,
             error,
             duration,
             enqueued_at,
+            not_before,
             started_at,
             finished_at,
+            batch_uid,
         }
     }
 }
 
+impl TaskView {
+    /// Sets the indexing progress of the task, which is only known while it is processing.
+    pub fn with_progress(mut self, progress: Option<TaskProgress>) -> Self {
+        self.progress = progress;
+        self
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct TaskListView {
     results: Vec<TaskView>,
+    #[serde(flatten)]
+    cursor: Option<TaskListCursor>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskListCursor {
+    limit: usize,
+    from: Option<TaskId>,
+    next: Option<TaskId>,
+}
+
+impl TaskListView {
+    /// Creates a page of tasks. `next` is the id of the first task of the following page, if
+    /// there is one.
+    pub fn paginated(results: Vec<TaskView>, limit: usize, next: Option<TaskId>) -> Self {
+        let from = results.first().map(|task| task.uid);
+        Self {
+            results,
+            cursor: Some(TaskListCursor { limit, from, next }),
+        }
+    }
 }
 
 impl From<Vec<TaskView>> for TaskListView {
     fn from(results: Vec<TaskView>) -> Self {
-        Self { results }
+        Self {
+            results,
+            cursor: None,
+        }
     }
 }
 
+/// The query parameters accepted by the routes that register a task.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleQuery {
+    /// The task is not processed before this date.
+    pub not_before: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SummarizedTaskView {
@@ -291,6 +512,7 @@ pub struct SummarizedTaskView {
 
 impl From<Task> for SummarizedTaskView {
     fn from(mut other: Task) -> Self {
+        let status = TaskStatus::from(&other);
         let created_event = other
             .events
             .drain(..1)
@@ -305,9 +527,25 @@ impl From<Task> for SummarizedTaskView {
         Self {
             uid: other.id,
             index_uid: other.index_uid.to_string(),
-            status: TaskStatus::Enqueued,
+            status,
             task_type: other.content.into(),
             enqueued_at,
         }
     }
 }
+
+/// Sends the `TaskView` of each finished task to the registered webhooks. The finished tasks are
+/// queued by the task store until they are sent, so a slow webhook never makes us miss one.
+pub fn spawn_webhook_notifier(meilisearch: &MeiliSearch) {
+    let mut receiver = meilisearch.finished_tasks();
+    let meilisearch = meilisearch.clone();
+
+    tokio::task::spawn_local(async move {
+        while let Some(task) = receiver.recv().await {
+            let view = TaskView::from(task);
+            if let Err(e) = meilisearch.notify_webhooks(&view).await {
+                log::error!("error notifying the webhooks: {}", e);
+            }
+        }
+    });
+}
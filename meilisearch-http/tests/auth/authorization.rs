@@ -10,6 +10,8 @@ static AUTHORIZATIONS: Lazy<HashMap<(&'static str, &'static str), &'static str>>
         hashmap! {
             ("POST",    "/indexes/products/search") =>                         "search",
             ("GET",     "/indexes/products/search") =>                         "search",
+            ("POST",    "/indexes/products/facet-search") =>                   "search",
+            ("POST",    "/multi-search") =>                                    "search",
             ("POST",    "/indexes/products/documents") =>                      "documents.add",
             ("GET",     "/indexes/products/documents") =>                      "documents.get",
             ("GET",     "/indexes/products/documents/0") =>                    "documents.get",
@@ -17,15 +19,25 @@ static AUTHORIZATIONS: Lazy<HashMap<(&'static str, &'static str), &'static str>>
             ("GET",     "/tasks") =>                                           "tasks.get",
             ("GET",     "/indexes/products/tasks") =>                          "tasks.get",
             ("GET",     "/indexes/products/tasks/0") =>                        "tasks.get",
+            ("DELETE",  "/tasks/0") =>                                         "tasks.cancel",
+            ("DELETE",  "/indexes/products/tasks") =>                          "tasks.cancel",
+            ("POST",    "/tasks/0/retry") =>                                   "tasks.retry",
+            ("GET",     "/batches") =>                                         "tasks.get",
+            ("GET",     "/batches/0") =>                                       "tasks.get",
+            ("POST",    "/indexes/products/transaction") =>                   "transactions.create",
             ("PUT",     "/indexes/products/") =>                               "indexes.update",
             ("GET",     "/indexes/products/") =>                               "indexes.get",
             ("DELETE",  "/indexes/products/") =>                               "indexes.delete",
             ("POST",    "/indexes") =>                                         "indexes.create",
             ("GET",     "/indexes") =>                                         "indexes.get",
             ("GET",     "/indexes/products/settings") =>                       "settings.get",
+            ("GET",     "/indexes/products/settings/crop-marker") =>           "settings.get",
             ("GET",     "/indexes/products/settings/displayed-attributes") =>  "settings.get",
             ("GET",     "/indexes/products/settings/distinct-attribute") =>    "settings.get",
             ("GET",     "/indexes/products/settings/filterable-attributes") => "settings.get",
+            ("GET",     "/indexes/products/settings/highlight-post-tag") =>    "settings.get",
+            ("GET",     "/indexes/products/settings/highlight-pre-tag") =>     "settings.get",
+            ("GET",     "/indexes/products/settings/max-total-hits") =>        "settings.get",
             ("GET",     "/indexes/products/settings/ranking-rules") =>         "settings.get",
             ("GET",     "/indexes/products/settings/searchable-attributes") => "settings.get",
             ("GET",     "/indexes/products/settings/sortable-attributes") =>   "settings.get",
@@ -33,9 +45,13 @@ static AUTHORIZATIONS: Lazy<HashMap<(&'static str, &'static str), &'static str>>
             ("GET",     "/indexes/products/settings/synonyms") =>              "settings.get",
             ("DELETE",  "/indexes/products/settings") =>                       "settings.update",
             ("POST",    "/indexes/products/settings") =>                       "settings.update",
+            ("POST",    "/indexes/products/settings/crop-marker") =>           "settings.update",
             ("POST",    "/indexes/products/settings/displayed-attributes") =>  "settings.update",
             ("POST",    "/indexes/products/settings/distinct-attribute") =>    "settings.update",
             ("POST",    "/indexes/products/settings/filterable-attributes") => "settings.update",
+            ("POST",    "/indexes/products/settings/highlight-post-tag") =>    "settings.update",
+            ("POST",    "/indexes/products/settings/highlight-pre-tag") =>     "settings.update",
+            ("POST",    "/indexes/products/settings/max-total-hits") =>        "settings.update",
             ("POST",    "/indexes/products/settings/ranking-rules") =>         "settings.update",
             ("POST",    "/indexes/products/settings/searchable-attributes") => "settings.update",
             ("POST",    "/indexes/products/settings/sortable-attributes") =>   "settings.update",
@@ -0,0 +1,59 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn get_batch_of_a_task() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1 }, { "id": 2 }, { "id": 3 }]), None)
+        .await;
+    let task = index.wait_task(0).await;
+    assert_eq!(task["status"], "succeeded", "{}", task);
+    let batch_uid = task["batchUid"].as_u64().unwrap();
+
+    let (response, code) = server.service.get(format!("/batches/{}", batch_uid)).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["uid"], batch_uid);
+    assert_eq!(response["indexUid"], "test");
+    assert_eq!(response["taskUids"], json!([0]));
+    assert_eq!(response["totalDocuments"], 3);
+    assert!(response["steps"].is_array());
+    assert!(response["startedAt"].is_string());
+    assert!(response["finishedAt"].is_string());
+    assert!(response["duration"].is_string());
+}
+
+#[actix_rt::test]
+async fn list_batches() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+    index.add_documents(json!([{ "id": 1 }]), None).await;
+    index.wait_task(1).await;
+
+    let (response, code) = server.service.get("/batches").await;
+    assert_eq!(code, 200, "{}", response);
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    // the most recent batch comes first.
+    assert_eq!(results[0]["taskUids"], json!([1]));
+    assert_eq!(results[1]["taskUids"], json!([0]));
+    assert_eq!(response["next"], json!(null));
+
+    let (response, code) = server.service.get("/batches?limit=1").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+    assert_eq!(response["next"], results[1]["uid"]);
+}
+
+#[actix_rt::test]
+async fn get_unexisting_batch() {
+    let server = Server::new().await;
+
+    let (response, code) = server.service.get("/batches/42").await;
+    assert_eq!(code, 404, "{}", response);
+    assert_eq!(response["code"], "batch_not_found");
+}
@@ -227,6 +227,16 @@ impl Index<'_> {
         self.service.delete(url).await
     }
 
+    pub async fn transaction(&self, operations: Value) -> (Value, StatusCode) {
+        let url = format!(
+            "/indexes/{}/transaction",
+            encode(self.uid.as_ref()).to_string()
+        );
+        self.service
+            .post(url, json!({ "operations": operations }))
+            .await
+    }
+
     pub async fn stats(&self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/stats", encode(self.uid.as_ref()).to_string());
         self.service.get(url).await
@@ -257,6 +267,14 @@ impl Index<'_> {
         self.service.post(url, query).await
     }
 
+    pub async fn facet_search(&self, query: Value) -> (Value, StatusCode) {
+        let url = format!(
+            "/indexes/{}/facet-search",
+            encode(self.uid.as_ref()).to_string()
+        );
+        self.service.post(url, query).await
+    }
+
     pub async fn search_get(&self, query: Value) -> (Value, StatusCode) {
         let params = serde_url_params::to_string(&query).unwrap();
         let url = format!(
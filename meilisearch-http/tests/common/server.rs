@@ -5,7 +5,9 @@ use actix_web::http::StatusCode;
 use byte_unit::{Byte, ByteUnit};
 use meilisearch_auth::AuthController;
 use meilisearch_http::setup_meilisearch;
-use meilisearch_lib::options::{IndexerOpts, MaxMemory};
+use meilisearch_lib::options::{
+    IndexerOpts, MaxMemory, SchedulerConfig, TaskRetentionConfig, WebhookConfig,
+};
 use once_cell::sync::Lazy;
 use serde_json::Value;
 use tempfile::TempDir;
@@ -134,6 +136,7 @@ pub fn default_settings(dir: impl AsRef<Path>) -> Opt {
         max_index_size: Byte::from_unit(4.0, ByteUnit::GiB).unwrap(),
         max_task_db_size: Byte::from_unit(4.0, ByteUnit::GiB).unwrap(),
         http_payload_size_limit: Byte::from_unit(10.0, ByteUnit::MiB).unwrap(),
+        search_cache_size: Byte::from_bytes(0),
         ssl_cert_path: None,
         ssl_key_path: None,
         ssl_auth_path: None,
@@ -154,5 +157,8 @@ pub fn default_settings(dir: impl AsRef<Path>) -> Opt {
             ..Default::default()
         },
         log_level: "off".into(),
+        scheduler_options: SchedulerConfig::default(),
+        task_retention_options: TaskRetentionConfig::default(),
+        webhook_options: WebhookConfig::default(),
     }
 }
@@ -1055,3 +1055,100 @@ async fn add_documents_with_primary_key_twice() {
     let (response, _code) = index.get_task(1).await;
     assert_eq!(response["status"], "succeeded");
 }
+
+#[actix_rt::test]
+async fn add_documents_with_idempotency_key() {
+    let document = json!([
+        {
+            "id": 1,
+            "content": "Bouvier Bernois",
+        }
+    ]);
+
+    let server = Server::new().await;
+    let app = test::init_service(create_app!(
+        &server.service.meilisearch,
+        &server.service.auth,
+        true,
+        &server.service.options,
+        analytics::MockAnalytics::new(&server.service.options).0
+    ))
+    .await;
+
+    for (key, expected_uid) in [("first-key", 0), ("first-key", 0), ("second-key", 1)] {
+        let req = test::TestRequest::post()
+            .uri("/indexes/dog/documents")
+            .set_payload(document.to_string())
+            .insert_header(("content-type", "application/json"))
+            .insert_header(("Idempotency-Key", key))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status_code = res.status();
+        let body = test::read_body(res).await;
+        let response: Value = serde_json::from_slice(&body).unwrap_or_default();
+        assert_eq!(status_code, 202, "{}", response);
+        assert_eq!(response["uid"], expected_uid);
+    }
+
+    let (response, code) = server.service.get("/tasks").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["results"].as_array().unwrap().len(), 2);
+
+    let req = test::TestRequest::post()
+        .uri("/indexes/dog/documents")
+        .set_payload(document.to_string())
+        .insert_header(("content-type", "application/json"))
+        .insert_header(("Idempotency-Key", ""))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    let status_code = res.status();
+    let body = test::read_body(res).await;
+    let response: Value = serde_json::from_slice(&body).unwrap_or_default();
+    assert_eq!(status_code, 400);
+    assert_eq!(response["code"], "invalid_idempotency_key");
+}
+
+#[actix_rt::test]
+async fn error_reuse_idempotency_key_for_another_request() {
+    let server = Server::new().await;
+    let app = test::init_service(create_app!(
+        &server.service.meilisearch,
+        &server.service.auth,
+        true,
+        &server.service.options,
+        analytics::MockAnalytics::new(&server.service.options).0
+    ))
+    .await;
+
+    let requests = [
+        ("/indexes/dog/documents", json!([{ "id": 1 }]), 202),
+        // another index
+        ("/indexes/cat/documents", json!([{ "id": 1 }]), 409),
+        // another payload
+        ("/indexes/dog/documents", json!([{ "id": 2 }]), 409),
+        // the same request
+        ("/indexes/dog/documents", json!([{ "id": 1 }]), 202),
+    ];
+    for (uri, document, expected_code) in requests {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_payload(document.to_string())
+            .insert_header(("content-type", "application/json"))
+            .insert_header(("Idempotency-Key", "my-key"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status_code = res.status();
+        let body = test::read_body(res).await;
+        let response: Value = serde_json::from_slice(&body).unwrap_or_default();
+        assert_eq!(status_code, expected_code, "{}", response);
+        if expected_code == 409 {
+            assert_eq!(response["code"], "idempotency_key_reused");
+        } else {
+            assert_eq!(response["uid"], 0);
+        }
+    }
+
+    let (response, code) = server.service.get("/tasks").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+}
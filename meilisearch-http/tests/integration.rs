@@ -1,13 +1,17 @@
 mod auth;
+mod batches;
 mod common;
 mod dashboard;
 mod documents;
 mod index;
+mod multi_search;
 mod search;
 mod settings;
 mod snapshot;
 mod stats;
 mod tasks;
+mod transaction;
+mod webhooks;
 
 // Tests are isolated by features in different modules to allow better readability, test
 // targetability, and improved incremental compilation times.
@@ -0,0 +1,161 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::common::Server;
+
+async fn server_with_two_indexes() -> Server {
+    let server = Server::new().await;
+    server
+        .index("products")
+        .add_documents(
+            json!([
+                { "id": 1, "title": "red shoes" },
+                { "id": 2, "title": "red shirt" },
+                { "id": 3, "title": "blue shirt" },
+            ]),
+            None,
+        )
+        .await;
+    server
+        .index("articles")
+        .add_documents(
+            json!([
+                { "id": 1, "title": "how to wear red" },
+                { "id": 2, "title": "a guide to shoes" },
+            ]),
+            None,
+        )
+        .await;
+    server.index("products").wait_task(0).await;
+    server.index("articles").wait_task(1).await;
+    server
+}
+
+#[actix_rt::test]
+async fn multi_search_returns_one_result_per_query() {
+    let server = server_with_two_indexes().await;
+
+    let (response, code) = server
+        .service
+        .post(
+            "/multi-search",
+            json!({
+                "queries": [
+                    { "indexUid": "products", "q": "red" },
+                    { "indexUid": "articles", "q": "shoes", "limit": 1 },
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["indexUid"], "products");
+    assert_eq!(results[0]["nbHits"], 2);
+    assert_eq!(results[0]["hits"].as_array().unwrap().len(), 2);
+    assert_eq!(results[1]["indexUid"], "articles");
+    assert_eq!(results[1]["limit"], 1);
+    assert_eq!(
+        results[1]["hits"],
+        json!([{ "id": 2, "title": "a guide to shoes" }])
+    );
+}
+
+#[actix_rt::test]
+async fn federated_multi_search_merges_hits_by_ranking_score() {
+    let server = server_with_two_indexes().await;
+
+    let (response, code) = server
+        .service
+        .post(
+            "/multi-search",
+            json!({
+                "queries": [
+                    { "indexUid": "articles", "q": "red" },
+                    { "indexUid": "products", "q": "red", "showRankingScore": true },
+                ],
+                "federation": { "limit": 2, "offset": 1 },
+            }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    assert_eq!(response["nbHits"], 3);
+    assert_eq!(response["limit"], 2);
+    assert_eq!(response["offset"], 1);
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 2);
+    // The products starting with the query rank above the article, even though the articles are
+    // queried first, and the first product is skipped.
+    assert_eq!(
+        hits[0]["_federation"],
+        json!({ "indexUid": "products", "queriesPosition": 1 })
+    );
+    assert!(hits[0]["_rankingScore"].as_f64().is_some(), "{}", response);
+    assert_eq!(hits[1]["title"], "how to wear red");
+    assert_eq!(
+        hits[1]["_federation"],
+        json!({ "indexUid": "articles", "queriesPosition": 0 })
+    );
+    // The score is only shown to the queries that asked for it.
+    assert!(hits[1].get("_rankingScore").is_none(), "{}", response);
+}
+
+#[actix_rt::test]
+async fn error_multi_search_unexisting_index() {
+    let server = server_with_two_indexes().await;
+
+    let (response, code) = server
+        .service
+        .post(
+            "/multi-search",
+            json!({
+                "queries": [
+                    { "indexUid": "products", "q": "red" },
+                    { "indexUid": "unexisting", "q": "red" },
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(code, 404, "{}", response);
+    assert_eq!(response["code"], "index_not_found");
+
+    let (response, code) = server
+        .service
+        .post("/multi-search", json!({ "queries": [{ "q": "red" }] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+}
+
+#[actix_rt::test]
+#[cfg_attr(target_os = "windows", ignore)]
+async fn error_multi_search_unauthorized_index() {
+    let mut server = Server::new_auth().await;
+    server.use_api_key("MASTER_KEY");
+
+    let content = json!({
+        "indexes": ["products"],
+        "actions": ["search"],
+        "expiresAt": Utc::now() + Duration::hours(1),
+    });
+    let (response, code) = server.add_api_key(content).await;
+    assert_eq!(code, 201);
+    let key = response["key"].as_str().unwrap();
+    server.use_api_key(&key);
+
+    let (response, code) = server
+        .service
+        .post(
+            "/multi-search",
+            json!({
+                "queries": [
+                    { "indexUid": "products", "q": "red" },
+                    { "indexUid": "sales", "q": "red" },
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(code, 403, "{}", response);
+    assert_eq!(response["code"], "invalid_api_key");
+}
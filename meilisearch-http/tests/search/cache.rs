@@ -0,0 +1,67 @@
+use byte_unit::{Byte, ByteUnit};
+use meilisearch_http::option::Opt;
+use serde_json::json;
+use tempfile::TempDir;
+
+use super::DOCUMENTS;
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn search_cache_is_invalidated_by_writes() {
+    let dir = TempDir::new().unwrap();
+    let options = Opt {
+        search_cache_size: Byte::from_unit(1.0, ByteUnit::MiB).unwrap(),
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    let (first, code) = index.search_post(json!({ "q": "glass" })).await;
+    assert_eq!(code, 200, "{}", first);
+    let (second, code) = index.search_post(json!({ "q": "glass" })).await;
+    assert_eq!(code, 200, "{}", second);
+    assert_eq!(first["hits"], second["hits"]);
+
+    let (response, code) = server.stats().await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["searchCache"]["hits"], 1);
+    assert_eq!(response["searchCache"]["misses"], 1);
+    assert!(response["searchCache"]["size"].as_u64().unwrap() > 0);
+
+    index
+        .add_documents(json!([{ "title": "Glass Onion", "id": "661374" }]), None)
+        .await;
+    index.wait_task(1).await;
+
+    let (response, code) = index.search_post(json!({ "q": "glass" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+
+    let (response, _code) = server.stats().await;
+    assert_eq!(response["searchCache"]["hits"], 1);
+    assert_eq!(response["searchCache"]["misses"], 2);
+}
+
+#[actix_rt::test]
+async fn search_cache_is_disabled_by_default() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index.search_post(json!({ "q": "glass" })).await;
+    index.search_post(json!({ "q": "glass" })).await;
+
+    let (response, code) = server.stats().await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["searchCache"]["hits"], 0);
+    assert_eq!(response["searchCache"]["misses"], 0);
+    assert_eq!(response["searchCache"]["maxSize"], 0);
+}
@@ -0,0 +1,123 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::Server;
+
+async fn index_with_brands(server: &Server) -> Index<'_> {
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["brand", "color"] }))
+        .await;
+    index
+        .add_documents(
+            json!([
+                { "id": 1, "brand": "Samsung", "color": "red" },
+                { "id": 2, "brand": "Samsung", "color": "blue" },
+                { "id": 3, "brand": "Sony", "color": "red" },
+                { "id": 4, "brand": "Apple", "color": "red" },
+                { "id": 5, "brand": "Sandisk", "color": "blue" },
+            ]),
+            None,
+        )
+        .await;
+    index.wait_task(1).await;
+    index
+}
+
+#[actix_rt::test]
+async fn facet_search_by_prefix() {
+    let server = Server::new().await;
+    let index = index_with_brands(&server).await;
+
+    let (response, code) = index
+        .facet_search(json!({ "facetName": "brand", "facetQuery": "sa" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["facetHits"],
+        json!([
+            { "value": "Samsung", "count": 2 },
+            { "value": "Sandisk", "count": 1 },
+        ])
+    );
+    assert_eq!(response["facetQuery"], "sa");
+}
+
+#[actix_rt::test]
+async fn facet_search_with_typo() {
+    let server = Server::new().await;
+    let index = index_with_brands(&server).await;
+
+    let (response, code) = index
+        .facet_search(json!({ "facetName": "brand", "facetQuery": "samsumg" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["facetHits"],
+        json!([{ "value": "Samsung", "count": 2 }])
+    );
+}
+
+#[actix_rt::test]
+async fn facet_search_scoped_by_filter() {
+    let server = Server::new().await;
+    let index = index_with_brands(&server).await;
+
+    let (response, code) = index
+        .facet_search(json!({ "facetName": "brand", "filter": "color = blue" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["facetHits"],
+        json!([
+            { "value": "Samsung", "count": 1 },
+            { "value": "Sandisk", "count": 1 },
+        ])
+    );
+}
+
+#[actix_rt::test]
+async fn facet_search_groups_the_spellings_of_a_value() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["brand", "color"] }))
+        .await;
+    index
+        .add_documents(
+            json!([
+                { "id": 1, "brand": "Nike", "color": "blue" },
+                { "id": 2, "brand": "nike", "color": "red" },
+                { "id": 3, "brand": " NIKE ", "color": "red" },
+                { "id": 4, "brand": "Nikon", "color": "red" },
+            ]),
+            None,
+        )
+        .await;
+    index.wait_task(1).await;
+
+    let (response, code) = index
+        .facet_search(json!({
+            "facetName": "brand",
+            "facetQuery": "nik",
+            "filter": "color = red",
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let hits = response["facetHits"].as_array().unwrap();
+    assert_eq!(hits.len(), 2, "{}", response);
+    assert_eq!(hits[0]["count"], 2);
+    assert_eq!(hits[1], json!({ "value": "Nikon", "count": 1 }));
+}
+
+#[actix_rt::test]
+async fn error_facet_search_not_filterable() {
+    let server = Server::new().await;
+    let index = index_with_brands(&server).await;
+
+    let (response, code) = index
+        .facet_search(json!({ "facetName": "id", "facetQuery": "1" }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["code"], "invalid_facet_search_facet_name");
+}
@@ -0,0 +1,259 @@
+use serde_json::json;
+
+use super::DOCUMENTS;
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn formatted_with_default_highlight_tags_and_crop_marker() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index
+        .search(
+            json!({ "q": "train", "attributesToHighlight": ["title"] }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["title"],
+                    "How to <em>Train</em> Your Dragon: The Hidden World"
+                );
+            },
+        )
+        .await;
+
+    index
+        .search(
+            json!({ "q": "train", "attributesToCrop": ["title"], "cropLength": 3 }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["title"],
+                    "…to Train Your…"
+                );
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn formatted_with_custom_highlight_tags_and_crop_marker() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index
+        .search(
+            json!({
+                "q": "train",
+                "attributesToHighlight": ["title"],
+                "attributesToCrop": ["title"],
+                "cropLength": 3,
+                "highlightPreTag": "<b>",
+                "highlightPostTag": "</b>",
+                "cropMarker": "[…]",
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["title"],
+                    "[…]to <b>Train</b> Your[…]"
+                );
+            },
+        )
+        .await;
+
+    // The crop marker is only inserted where the text is truncated.
+    index
+        .search(
+            json!({
+                "q": "glass",
+                "attributesToCrop": ["title"],
+                "cropLength": 2,
+                "cropMarker": "[…]",
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["hits"][0]["_formatted"]["title"], "Glass");
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn formatted_with_highlight_tags_and_crop_marker_settings() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({
+            "highlightPreTag": "<mark>",
+            "highlightPostTag": "</mark>",
+            "cropMarker": "...",
+        }))
+        .await;
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["highlightPreTag"], "<mark>");
+    assert_eq!(response["highlightPostTag"], "</mark>");
+    assert_eq!(response["cropMarker"], "...");
+
+    index
+        .search(
+            json!({
+                "q": "train",
+                "attributesToHighlight": ["title"],
+                "attributesToCrop": ["title"],
+                "cropLength": 3,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["title"],
+                    "...to <mark>Train</mark> Your..."
+                );
+            },
+        )
+        .await;
+
+    // The parameters of the query take precedence over the settings of the index.
+    index
+        .search(
+            json!({
+                "q": "train",
+                "attributesToHighlight": ["title"],
+                "highlightPreTag": "<b>",
+                "highlightPostTag": "</b>",
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["title"],
+                    "How to <b>Train</b> Your Dragon: The Hidden World"
+                );
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn formatted_with_crop_on_best_matches() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([{
+        "id": 1,
+        "body": "Potter is a wizard. The boy Harry Potter lives with his aunt.",
+    }]);
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    // The snippet is the window containing the most query words, not the first match.
+    index
+        .search(
+            json!({ "q": "harry potter", "attributesToCrop": ["body"], "cropLength": 2 }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["hits"][0]["_formatted"]["body"], "…Harry Potter…");
+            },
+        )
+        .await;
+
+    index
+        .search(
+            json!({
+                "q": "harry potter",
+                "attributesToCrop": ["body"],
+                "cropLength": 2,
+                "cropSnippets": 2,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["body"],
+                    "Potter is…Harry Potter…"
+                );
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn formatted_ignores_stop_words() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index.update_settings(json!({ "stopWords": ["the"] })).await;
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    index
+        .search(
+            json!({
+                "q": "the dragon",
+                "attributesToHighlight": ["title"],
+                "matches": true,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["title"],
+                    "How to Train Your <em>Dragon</em>: The Hidden World"
+                );
+                assert_eq!(
+                    response["hits"][0]["_matchesInfo"]["title"],
+                    json!([{ "start": 18, "length": 6 }])
+                );
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn formatted_highlights_synonyms() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "synonyms": { "superhero": ["marvel"] } }))
+        .await;
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    index
+        .search(
+            json!({
+                "q": "superhero",
+                "attributesToHighlight": ["title"],
+                "matches": true,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["hits"][0]["id"], "299537");
+                assert_eq!(
+                    response["hits"][0]["_formatted"]["title"],
+                    "Captain <em>Marvel</em>"
+                );
+                assert_eq!(
+                    response["hits"][0]["_matchesInfo"]["title"],
+                    json!([{ "start": 8, "length": 6 }])
+                );
+            },
+        )
+        .await;
+}
@@ -1,7 +1,12 @@
 // This modules contains all the test concerning search. Each particular feture of the search
 // should be tested in its own module to isolate tests and keep the tests readable.
 
+mod cache;
 mod errors;
+mod facet_search;
+mod formatted;
+mod pagination;
+mod ranking_score;
 
 use crate::common::Server;
 use once_cell::sync::Lazy;
@@ -248,6 +253,177 @@ async fn search_facet_distribution() {
         .await;
 }
 
+#[actix_rt::test]
+async fn search_exhaustive_facet_distribution() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["color"]}))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "color": "red" },
+        { "id": 2, "color": ["red", "blue", "red"] },
+        { "id": 3, "color": "green" },
+        { "id": 4 },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    index
+        .search(
+            json!({
+                "facetsDistribution": ["color"],
+                "exhaustiveFacetsCount": true,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["facetsDistribution"],
+                    json!({ "color": { "blue": 1, "green": 1, "red": 2 } })
+                );
+                assert_eq!(response["exhaustiveFacetsCount"], true);
+            },
+        )
+        .await;
+
+    index
+        .search(
+            json!({
+                "facetsDistribution": ["*"],
+                "exhaustiveFacetsCount": true,
+                "maxValuesPerFacet": 1,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["facetsDistribution"],
+                    json!({ "color": { "blue": 1 } })
+                );
+                assert_eq!(response["exhaustiveFacetsCount"], false);
+            },
+        )
+        .await;
+
+    index
+        .search(
+            json!({
+                "facetsDistribution": ["id"],
+                "exhaustiveFacetsCount": true,
+            }),
+            |response, code| {
+                assert_eq!(code, 400, "{}", response);
+                assert_eq!(response["code"], "bad_request");
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn search_exhaustive_facet_distribution_normalizes_values() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["brand", "size"]}))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "brand": "Nike", "size": 1.0 },
+        { "id": 2, "brand": "nike", "size": 1 },
+        { "id": 3, "brand": " NIKE ", "size": 2.5 },
+        { "id": 4, "brand": "Adidas" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    index
+        .search(
+            json!({
+                "facetsDistribution": ["brand", "size"],
+                "exhaustiveFacetsCount": true,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                let distribution = &response["facetsDistribution"];
+                // The spellings of a brand are counted together, under one of them.
+                let brands = distribution["brand"].as_object().unwrap();
+                assert_eq!(brands.len(), 2);
+                assert_eq!(brands["Adidas"], 1);
+                assert!(brands.values().any(|count| count == 3));
+                assert_eq!(distribution["size"], json!({ "1": 2, "2.5": 1 }));
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn search_facet_stats() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["price", "color"]}))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "price": 10, "color": "red" },
+        { "id": 2, "price": 20, "color": "blue" },
+        { "id": 3, "price": [5, 30] },
+        { "id": 4, "color": "red" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    index
+        .search(
+            json!({ "facetsDistribution": ["*"], "showFacetStats": true }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["facetStats"],
+                    json!({ "price": { "min": 5.0, "max": 30.0, "sum": 65.0, "avg": 16.25 } })
+                );
+            },
+        )
+        .await;
+
+    index
+        .search(
+            json!({
+                "filter": "price = 20",
+                "facetsDistribution": ["price"],
+                "showFacetStats": true,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(
+                    response["facetStats"],
+                    json!({ "price": { "min": 20.0, "max": 20.0, "sum": 20.0, "avg": 20.0 } })
+                );
+            },
+        )
+        .await;
+
+    index
+        .search(json!({ "facetsDistribution": ["*"] }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert!(response.get("facetStats").is_none());
+        })
+        .await;
+
+    index
+        .search(
+            json!({ "q": "", "showFacetStats": true }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert!(response.get("facetStats").is_none());
+            },
+        )
+        .await;
+}
+
 #[actix_rt::test]
 async fn displayed_attributes() {
     let server = Server::new().await;
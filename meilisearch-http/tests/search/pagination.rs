@@ -0,0 +1,104 @@
+use serde_json::json;
+
+use super::DOCUMENTS;
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn search_with_page_and_hits_per_page() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index
+        .search(json!({ "page": 2, "hitsPerPage": 2 }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+            assert_eq!(response["page"], 2);
+            assert_eq!(response["hitsPerPage"], 2);
+            assert_eq!(response["totalHits"], 5);
+            assert_eq!(response["totalPages"], 3);
+            assert_eq!(response["exhaustiveNbHits"], true);
+        })
+        .await;
+
+    index
+        .search(json!({ "page": 3, "hitsPerPage": 2 }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+        })
+        .await;
+
+    index
+        .search(json!({ "page": 4, "hitsPerPage": 2 }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"], json!([]));
+            assert_eq!(response["totalHits"], 5);
+        })
+        .await;
+}
+
+#[actix_rt::test]
+async fn search_with_page_zero_returns_no_hits() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index
+        .search(json!({ "page": 0 }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"], json!([]));
+            assert_eq!(response["hitsPerPage"], 20);
+            assert_eq!(response["totalHits"], 5);
+            assert_eq!(response["totalPages"], 1);
+        })
+        .await;
+}
+
+#[actix_rt::test]
+async fn search_without_page_is_not_paginated_by_pages() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index
+        .search(json!({ "limit": 2 }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+            assert!(response.get("totalHits").is_none());
+            assert!(response.get("totalPages").is_none());
+        })
+        .await;
+}
+
+#[actix_rt::test]
+async fn max_total_hits_bounds_the_total_hits() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index.update_settings(json!({ "maxTotalHits": 3 })).await;
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["maxTotalHits"], 3);
+
+    index
+        .search(json!({ "page": 2, "hitsPerPage": 2 }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+            assert_eq!(response["totalHits"], 3);
+            assert_eq!(response["totalPages"], 2);
+        })
+        .await;
+}
@@ -0,0 +1,185 @@
+use serde_json::json;
+
+use super::DOCUMENTS;
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn ranking_score_is_not_shown_by_default() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index
+        .search(json!({ "q": "glass" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert!(response["hits"][0].get("_rankingScore").is_none());
+            assert!(response["hits"][0].get("_rankingScoreDetails").is_none());
+        })
+        .await;
+}
+
+#[actix_rt::test]
+async fn ranking_score_follows_the_order_of_the_hits() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "title": "Harry Potter", "overview": "The boy who lived" },
+        { "id": 2, "title": "The Wizard", "overview": "Potter and Harry" },
+        { "id": 3, "title": "Harry", "overview": "A boy" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    index
+        .search(
+            json!({ "q": "harry potter", "showRankingScore": true }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                let hits = response["hits"].as_array().unwrap();
+                let ids: Vec<_> = hits.iter().map(|hit| hit["id"].clone()).collect();
+                assert_eq!(ids, [json!(1), json!(2), json!(3)]);
+
+                let scores: Vec<_> = hits
+                    .iter()
+                    .map(|hit| hit["_rankingScore"].as_f64().unwrap())
+                    .collect();
+                assert!(scores[0] <= 1.0, "{:?}", scores);
+                assert!(scores[0] > scores[1], "{:?}", scores);
+                assert!(scores[1] > scores[2], "{:?}", scores);
+                assert!(scores[2] > 0.0, "{:?}", scores);
+                assert!(hits[0].get("_rankingScoreDetails").is_none());
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn ranking_score_does_not_depend_on_the_page() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "title": "Harry Potter and the wizard", "overview": "A boy" },
+        { "id": 2, "title": "The wizard", "overview": "Harry the potter" },
+        { "id": 3, "title": "Hary Poter", "overview": "The wizzard" },
+        { "id": 4, "title": "Wizards", "overview": "Harry and the potters" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_task(0).await;
+
+    let (response, code) = index
+        .search_post(json!({
+            "q": "harry potter wizard",
+            "showRankingScore": true,
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let hits = response["hits"].as_array().unwrap().clone();
+    assert!(hits.len() > 1, "{}", response);
+
+    // Each hit has the same score on its own page as on the first page.
+    for (offset, hit) in hits.iter().enumerate() {
+        let (response, code) = index
+            .search_post(json!({
+                "q": "harry potter wizard",
+                "offset": offset,
+                "limit": 1,
+                "showRankingScore": true,
+            }))
+            .await;
+        assert_eq!(code, 200, "{}", response);
+        assert_eq!(response["hits"][0]["id"], hit["id"]);
+        assert_eq!(response["hits"][0]["_rankingScore"], hit["_rankingScore"]);
+    }
+}
+
+#[actix_rt::test]
+async fn ranking_score_details() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "searchableAttributes": ["title", "id"] }))
+        .await;
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    index
+        .search(
+            json!({
+                "q": "glass",
+                "showRankingScore": true,
+                "showRankingScoreDetails": true,
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["hits"][0]["_rankingScore"], 1.0);
+                assert_eq!(
+                    response["hits"][0]["_rankingScoreDetails"],
+                    json!({
+                        "words": {
+                            "order": 0,
+                            "matchingWords": 1,
+                            "maxMatchingWords": 1,
+                            "score": 1.0,
+                        },
+                        "typo": {
+                            "order": 1,
+                            "typoCount": 0,
+                            "maxTypoCount": 1,
+                            "score": 1.0,
+                        },
+                        "proximity": { "order": 2, "score": 1.0 },
+                        "attribute": { "order": 3, "score": 1.0 },
+                        "exactness": {
+                            "order": 4,
+                            "matchType": "exactMatch",
+                            "matchingWords": 1,
+                            "maxMatchingWords": 1,
+                            "score": 1.0,
+                        },
+                    })
+                );
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn ranking_score_details_with_sort() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "sortableAttributes": ["price"] }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "price": 10 },
+        { "id": 2, "price": 5 },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_task(1).await;
+
+    // Without query words, only the sort ranks the documents.
+    index
+        .search(
+            json!({ "sort": ["price:asc"], "showRankingScoreDetails": true }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["hits"][0]["id"], 2);
+                assert_eq!(
+                    response["hits"][0]["_rankingScoreDetails"],
+                    json!({ "price:asc": { "order": 0, "value": 5 } })
+                );
+                assert!(response["hits"][0].get("_rankingScore").is_none());
+            },
+        )
+        .await;
+}
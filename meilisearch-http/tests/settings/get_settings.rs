@@ -24,6 +24,10 @@ static DEFAULT_SETTINGS_VALUES: Lazy<HashMap<&'static str, Value>> = Lazy::new(|
     );
     map.insert("stop_words", json!([]));
     map.insert("synonyms", json!({}));
+    map.insert("max_total_hits", json!(1000));
+    map.insert("highlight_pre_tag", json!("<em>"));
+    map.insert("highlight_post_tag", json!("</em>"));
+    map.insert("crop_marker", json!("…"));
     map
 });
 
@@ -43,7 +47,7 @@ async fn get_settings() {
     let (response, code) = index.settings().await;
     assert_eq!(code, 200);
     let settings = response.as_object().unwrap();
-    assert_eq!(settings.keys().len(), 8);
+    assert_eq!(settings.keys().len(), 12);
     assert_eq!(settings["displayedAttributes"], json!(["*"]));
     assert_eq!(settings["searchableAttributes"], json!(["*"]));
     assert_eq!(settings["filterableAttributes"], json!([]));
@@ -61,6 +65,10 @@ async fn get_settings() {
         ])
     );
     assert_eq!(settings["stopWords"], json!([]));
+    assert_eq!(settings["maxTotalHits"], json!(1000));
+    assert_eq!(settings["highlightPreTag"], json!("<em>"));
+    assert_eq!(settings["highlightPostTag"], json!("</em>"));
+    assert_eq!(settings["cropMarker"], json!("…"));
 }
 
 #[actix_rt::test]
@@ -264,7 +272,11 @@ test_setting_routes!(
     distinct_attribute,
     stop_words,
     ranking_rules,
-    synonyms
+    synonyms,
+    max_total_hits,
+    highlight_pre_tag,
+    highlight_post_tag,
+    crop_marker
 );
 
 #[actix_rt::test]
@@ -1,5 +1,13 @@
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::common::server::default_settings;
 use crate::common::Server;
-use chrono::{DateTime, Utc};
+use actix_web::body::MessageBody;
+use actix_web::test;
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::future::poll_fn;
+use meilisearch_http::{analytics, create_app, Opt};
 use serde_json::json;
 
 #[actix_rt::test]
@@ -131,3 +139,422 @@ async fn test_summarized_task_view() {
     let (response, _) = index.delete().await;
     assert_valid_summarized_task!(response, "indexDeletion", "test");
 }
+
+#[actix_rt::test]
+async fn error_cancel_unexisting_task() {
+    let server = Server::new().await;
+    let (response, code) = server.service.delete("/tasks/1").await;
+
+    let expected_response = json!({
+        "message": "Task `1` not found.",
+        "code": "task_not_found",
+        "type": "invalid_request",
+        "link": "https://docs.meilisearch.com/errors#task_not_found"
+    });
+
+    assert_eq!(response, expected_response);
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn error_cancel_finished_task() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    let (response, code) = server.service.delete("/tasks/0").await;
+
+    let expected_response = json!({
+        "message": "Task `0` is already finished and can't be canceled.",
+        "code": "task_already_finished",
+        "type": "invalid_request",
+        "link": "https://docs.meilisearch.com/errors#task_already_finished"
+    });
+
+    assert_eq!(response, expected_response);
+    assert_eq!(code, 409);
+}
+
+#[actix_rt::test]
+async fn cancel_index_tasks_without_pending_tasks() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    let (response, code) = server.service.delete("/indexes/test/tasks").await;
+
+    assert_eq!(code, 200);
+    assert_eq!(response["results"], json!([]));
+}
+
+#[actix_rt::test]
+async fn retry_failed_document_addition() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    // the primary key can't be inferred from the documents.
+    index.add_documents(json!([{ "name": "foo" }]), None).await;
+    let response = index.wait_task(1).await;
+    assert_eq!(response["status"], "failed");
+
+    index.update(Some("name")).await;
+    index.wait_task(2).await;
+
+    let (response, code) = server.service.post("/tasks/1/retry", json!({})).await;
+    assert_eq!(code, 202, "{}", response);
+    assert_eq!(response["uid"], 3);
+    assert_eq!(response["type"], "documentAddition");
+
+    let response = index.wait_task(3).await;
+    assert_eq!(response["status"], "succeeded");
+    assert_eq!(response["details"]["retryOf"], 1);
+    assert_eq!(response["details"]["indexedDocuments"], 1);
+
+    // the update file is deleted once the retry succeeded.
+    let (response, code) = server.service.post("/tasks/1/retry", json!({})).await;
+    assert_eq!(code, 410, "{}", response);
+    assert_eq!(response["code"], "task_payload_deleted");
+}
+
+#[actix_rt::test]
+async fn retry_failed_document_addition_after_cancelled_retry() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    // the primary key can't be inferred from the documents.
+    index.add_documents(json!([{ "name": "foo" }]), None).await;
+    let response = index.wait_task(1).await;
+    assert_eq!(response["status"], "failed");
+
+    // the retry stays enqueued while the processing is paused.
+    let (response, code) = server.service.post("/tasks/pause", json!({})).await;
+    assert_eq!(code, 200, "{}", response);
+    let (response, code) = server.service.post("/tasks/1/retry", json!({})).await;
+    assert_eq!(code, 202, "{}", response);
+    assert_eq!(response["uid"], 2);
+
+    // cancelling the retry keeps the update file of the failed task.
+    let (response, code) = server.service.delete("/tasks/2").await;
+    assert_eq!(code, 200, "{}", response);
+    let (response, code) = server.service.post("/tasks/1/retry", json!({})).await;
+    assert_eq!(code, 202, "{}", response);
+    assert_eq!(response["uid"], 3);
+
+    let (response, code) = server.service.post("/tasks/resume", json!({})).await;
+    assert_eq!(code, 200, "{}", response);
+}
+
+#[actix_rt::test]
+async fn retry_failed_document_addition_after_dump_import() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = default_settings(temp.path());
+    let dumps_dir = options.dumps_dir.clone();
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    // the primary key can't be inferred from the documents.
+    index.add_documents(json!([{ "name": "foo" }]), None).await;
+    let response = index.wait_task(1).await;
+    assert_eq!(response["status"], "failed");
+
+    index.update(Some("name")).await;
+    index.wait_task(2).await;
+
+    let (response, code) = server.service.post("/dumps", json!({})).await;
+    assert_eq!(code, 202, "{}", response);
+    let uid = response["uid"].as_str().unwrap().to_string();
+    loop {
+        let (response, _) = server.get_dump_status(&uid).await;
+        match response["status"].as_str() {
+            Some("in_progress") => actix_rt::time::sleep(Duration::from_millis(10)).await,
+            status => {
+                assert_eq!(status, Some("done"), "{}", response);
+                break;
+            }
+        }
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        import_dump: Some(dumps_dir.join(&uid).with_extension("dump")),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    // the payload of the failed task is part of the dump.
+    let (response, code) = server.service.post("/tasks/1/retry", json!({})).await;
+    assert_eq!(code, 202, "{}", response);
+
+    let response = server.index("test").wait_task(3).await;
+    assert_eq!(response["status"], "succeeded", "{}", response);
+    assert_eq!(response["details"]["retryOf"], 1);
+}
+
+#[actix_rt::test]
+async fn error_retry_not_failed_task() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    let (response, code) = server.service.post("/tasks/0/retry", json!({})).await;
+
+    let expected_response = json!({
+        "message": "Task `0` can't be retried. Only failed document additions can be retried.",
+        "code": "task_not_retryable",
+        "type": "invalid_request",
+        "link": "https://docs.meilisearch.com/errors#task_not_retryable"
+    });
+
+    assert_eq!(response, expected_response);
+    assert_eq!(code, 409);
+
+    let (response, code) = server.service.post("/tasks/1/retry", json!({})).await;
+    assert_eq!(code, 404);
+    assert_eq!(response["code"], "task_not_found");
+}
+
+#[actix_rt::test]
+async fn pause_and_resume_task_processing() {
+    let server = Server::new().await;
+
+    let (response, code) = server.service.post("/tasks/pause", json!({})).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["isIndexingPaused"], true);
+
+    let index = server.index("test");
+    let (_response, code) = index.create(None).await;
+    assert_eq!(code, 202);
+
+    actix_rt::time::sleep(Duration::from_millis(200)).await;
+    let (response, _code) = index.get_task(0).await;
+    assert_eq!(response["status"], "enqueued");
+
+    let (response, _code) = server.service.get("/health").await;
+    assert_eq!(response["status"], "available");
+    assert_eq!(response["isIndexingPaused"], true);
+    let (response, _code) = server.stats().await;
+    assert_eq!(response["isIndexingPaused"], true);
+
+    let (response, code) = server.service.post("/tasks/resume", json!({})).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["isIndexingPaused"], false);
+
+    let response = index.wait_task(0).await;
+    assert_eq!(response["status"], "succeeded");
+    let (response, _code) = server.service.get("/health").await;
+    assert_eq!(response["isIndexingPaused"], false);
+}
+
+#[actix_rt::test]
+async fn scheduled_tasks_are_processed_once_due() {
+    let server = Server::new().await;
+
+    let not_before =
+        (Utc::now() + chrono::Duration::hours(1)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let (response, code) = server
+        .service
+        .post(
+            format!("/indexes?notBefore={}", not_before),
+            json!({ "uid": "test" }),
+        )
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    assert_eq!(response["status"], "scheduled");
+
+    let not_before =
+        (Utc::now() + chrono::Duration::seconds(2)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let (response, code) = server
+        .service
+        .post(
+            format!("/indexes?notBefore={}", not_before),
+            json!({ "uid": "other" }),
+        )
+        .await;
+    assert_eq!(code, 202, "{}", response);
+
+    let response = server.index("other").wait_task(1).await;
+    assert_eq!(response["status"], "succeeded");
+
+    let (response, code) = server.service.get("/tasks/0").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "scheduled");
+    assert!(response["notBefore"].is_string());
+    assert!(response["startedAt"].is_null());
+
+    let (response, code) = server.service.get("/tasks?status=scheduled").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+    assert_eq!(response["results"][0]["uid"], 0);
+}
+
+#[actix_rt::test]
+async fn prune_finished_tasks() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+    index
+        .add_documents(serde_json::json!([{ "id": 1, "content": "foobar" }]), None)
+        .await;
+    index.wait_task(1).await;
+
+    let (response, code) = server
+        .service
+        .post("/tasks/prune", json!({ "maxCount": 1 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["deletedTasks"], 1);
+
+    let (response, code) = server.service.get("/tasks").await;
+    assert_eq!(code, 200);
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["uid"], 1);
+}
+
+#[actix_rt::test]
+async fn list_tasks_filtered_by_status_and_type() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+    index
+        .add_documents(serde_json::json!([{ "id": 1, "content": "foobar" }]), None)
+        .await;
+    index.wait_task(1).await;
+
+    let (response, code) = server
+        .service
+        .get("/tasks?status=succeeded&type=documentAddition")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["uid"], 1);
+
+    let (response, code) = server.service.get("/tasks?status=failed").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"], json!([]));
+}
+
+#[actix_rt::test]
+async fn list_tasks_with_cursor() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+    index
+        .add_documents(serde_json::json!([{ "id": 1, "content": "foobar" }]), None)
+        .await;
+    index.wait_task(1).await;
+
+    let (response, code) = server.service.get("/tasks?limit=1").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+    assert_eq!(response["results"][0]["uid"], 1);
+    assert_eq!(response["limit"], 1);
+    assert_eq!(response["from"], 1);
+    assert_eq!(response["next"], 0);
+
+    let (response, code) = server.service.get("/tasks?limit=1&from=0").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"][0]["uid"], 0);
+    assert_eq!(response["next"], json!(null));
+}
+
+#[actix_rt::test]
+async fn list_tasks_without_limit_returns_all_tasks() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    for i in 0..25 {
+        index
+            .add_documents(serde_json::json!([{ "id": i, "content": "foobar" }]), None)
+            .await;
+    }
+    index.wait_task(24).await;
+
+    // Unknown parameters are ignored.
+    let (response, code) = server.service.get("/tasks?unknown=true").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"].as_array().unwrap().len(), 25);
+    assert!(response.get("limit").is_none());
+    assert!(response.get("next").is_none());
+}
+
+#[actix_rt::test]
+async fn error_list_tasks_invalid_status() {
+    let server = Server::new().await;
+    let (response, code) = server.service.get("/tasks?status=done").await;
+
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["code"], "invalid_task_status");
+}
+
+#[actix_rt::test]
+async fn stream_task_events() {
+    let server = Server::new().await;
+    let app = test::init_service(create_app!(
+        &server.service.meilisearch,
+        &server.service.auth,
+        true,
+        &server.service.options,
+        analytics::MockAnalytics::new(&server.service.options).0
+    ))
+    .await;
+
+    let req = test::TestRequest::get().uri("/tasks/stream").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/event-stream");
+    let mut body = Box::pin(res.into_body());
+
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    let mut received = String::new();
+    while !received.contains("event: succeeded") {
+        let chunk = actix_rt::time::timeout(
+            Duration::from_secs(5),
+            poll_fn(|cx| body.as_mut().poll_next(cx)),
+        )
+        .await
+        .expect("the stream should have sent the task events")
+        .unwrap()
+        .unwrap();
+        received.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+
+    let events: Vec<_> = received
+        .lines()
+        .filter_map(|line| line.strip_prefix("event: "))
+        .collect();
+    assert_eq!(events, ["created", "batched", "processing", "succeeded"]);
+
+    let last_data = received
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .last()
+        .unwrap();
+    let task: serde_json::Value = serde_json::from_str(last_data).unwrap();
+    assert_eq!(task["uid"], 0);
+    assert_eq!(task["status"], "succeeded");
+}
+
+#[actix_rt::test]
+async fn error_stream_unexisting_index_tasks() {
+    let server = Server::new().await;
+    let (response, code) = server.service.get("/indexes/test/tasks/stream").await;
+
+    assert_eq!(code, 404, "{}", response);
+    assert_eq!(response["code"], "index_not_found");
+}
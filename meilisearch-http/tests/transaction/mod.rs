@@ -0,0 +1,96 @@
+use serde_json::json;
+
+use crate::common::{GetAllDocumentsOptions, Server};
+
+#[actix_rt::test]
+async fn transaction_applies_all_operations() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(
+            json!([{ "id": 1, "title": "a" }, { "id": 2, "title": "b" }]),
+            None,
+        )
+        .await;
+    index.wait_task(0).await;
+
+    let (response, code) = index
+        .transaction(json!([
+            { "type": "documentAddition", "documents": [{ "id": 3, "title": "c" }] },
+            { "type": "documentDeletion", "ids": [1] },
+            { "type": "settingsUpdate", "settings": { "filterableAttributes": ["title"] } },
+        ]))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    assert_eq!(response["type"], "transaction");
+
+    let response = index.wait_task(1).await;
+    assert_eq!(response["status"], "succeeded", "{}", response);
+    assert_eq!(response["type"], "transaction");
+    let operations = &response["details"]["operations"];
+    assert_eq!(operations[0]["type"], "documentAddition");
+    assert_eq!(operations[0]["receivedDocuments"], 1);
+    assert_eq!(operations[0]["indexedDocuments"], 1);
+    assert_eq!(operations[1]["type"], "documentDeletion");
+    assert_eq!(operations[1]["receivedDocumentIds"], 1);
+    assert_eq!(operations[1]["deletedDocuments"], 1);
+    assert_eq!(operations[2]["type"], "settingsUpdate");
+    assert_eq!(operations[2]["filterableAttributes"], json!(["title"]));
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response,
+        json!([{ "id": 2, "title": "b" }, { "id": 3, "title": "c" }])
+    );
+
+    let (response, _code) = index.settings().await;
+    assert_eq!(response["filterableAttributes"], json!(["title"]));
+}
+
+#[actix_rt::test]
+async fn failed_transaction_applies_nothing() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "title": "a" }]), None)
+        .await;
+    index.wait_task(0).await;
+
+    let (_response, code) = index
+        .transaction(json!([
+            { "type": "documentDeletion", "ids": [1] },
+            { "type": "documentAddition", "documents": [{ "id": "invalid id", "title": "b" }] },
+        ]))
+        .await;
+    assert_eq!(code, 202);
+
+    let response = index.wait_task(1).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+    assert_eq!(response["details"]["operations"][0]["deletedDocuments"], 0);
+    assert_eq!(response["details"]["operations"][1]["indexedDocuments"], 0);
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!([{ "id": 1, "title": "a" }]));
+}
+
+#[actix_rt::test]
+async fn error_empty_transaction() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let (response, code) = index.transaction(json!([])).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["code"], "invalid_transaction");
+
+    let (response, code) = index
+        .transaction(json!([{ "type": "unknownOperation" }]))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["code"], "bad_request");
+}
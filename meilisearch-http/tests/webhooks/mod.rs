@@ -0,0 +1,171 @@
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+
+use crate::common::Server;
+
+/// The requests received by the webhook stand-in.
+#[derive(Default)]
+struct Received {
+    attempts: AtomicUsize,
+    tasks: Mutex<Vec<Value>>,
+    tokens: Mutex<Vec<String>>,
+}
+
+async fn receive(
+    received: web::Data<Received>,
+    req: HttpRequest,
+    body: web::Json<Value>,
+) -> HttpResponse {
+    // The first delivery fails, to check that it is retried.
+    if received.attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    if let Some(token) = req.headers().get("X-Token") {
+        let token = token.to_str().unwrap().to_string();
+        received.tokens.lock().unwrap().push(token);
+    }
+    received.tasks.lock().unwrap().push(body.into_inner());
+
+    HttpResponse::Ok().finish()
+}
+
+/// Starts a local http server standing in for a webhook, returns its url and the requests it
+/// received.
+fn start_webhook_stand_in() -> (String, web::Data<Received>) {
+    let received = web::Data::new(Received::default());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+    let data = received.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(data.clone())
+            .route("/hook", web::post().to(receive))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    actix_rt::spawn(server);
+
+    (url, received)
+}
+
+#[actix_rt::test]
+async fn finished_tasks_are_sent_to_webhooks() {
+    let (url, received) = start_webhook_stand_in();
+    let server = Server::new().await;
+
+    let (_response, code) = server
+        .service
+        .post(
+            "/webhooks",
+            json!({ "url": url, "headers": { "X-Token": "secret" } }),
+        )
+        .await;
+    assert_eq!(code, 201);
+
+    let index = server.index("test");
+    index.create(None).await;
+    index.wait_task(0).await;
+
+    let mut tasks = Vec::new();
+    for _ in 0..50 {
+        tasks = received.tasks.lock().unwrap().clone();
+        if !tasks.is_empty() {
+            break;
+        }
+        actix_rt::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["uid"], 0);
+    assert_eq!(tasks[0]["indexUid"], "test");
+    assert_eq!(tasks[0]["status"], "succeeded");
+    assert_eq!(tasks[0]["type"], "indexCreation");
+    assert_eq!(received.attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(*received.tokens.lock().unwrap(), vec!["secret".to_string()]);
+}
+
+#[actix_rt::test]
+async fn register_list_and_delete_webhooks() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .post("/webhooks", json!({ "url": "http://localhost:8080/hook" }))
+        .await;
+    assert_eq!(code, 201);
+    assert_eq!(response["url"], "http://localhost:8080/hook");
+    assert_eq!(response["headers"], json!({}));
+    let uid = response["uid"].as_str().unwrap().to_string();
+
+    let (response, code) = server.service.get("/webhooks").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+    assert_eq!(response["results"][0]["uid"], uid);
+
+    let (_response, code) = server.service.delete(format!("/webhooks/{}", uid)).await;
+    assert_eq!(code, 204);
+
+    let (response, _code) = server.service.get("/webhooks").await;
+    assert_eq!(response["results"], json!([]));
+
+    let (response, code) = server.service.delete(format!("/webhooks/{}", uid)).await;
+    assert_eq!(code, 404);
+    assert_eq!(response["code"], "webhook_not_found");
+}
+
+#[actix_rt::test]
+async fn webhook_header_values_are_masked() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .post(
+            "/webhooks",
+            json!({
+                "url": "http://localhost:8080/hook",
+                "headers": { "Authorization": "Bearer secret" },
+            }),
+        )
+        .await;
+    assert_eq!(code, 201);
+    assert_eq!(response["headers"], json!({ "Authorization": "***" }));
+
+    let (response, code) = server.service.get("/webhooks").await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["results"][0]["headers"],
+        json!({ "Authorization": "***" })
+    );
+    assert!(!response.to_string().contains("secret"));
+}
+
+#[actix_rt::test]
+async fn error_register_invalid_webhook() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .post("/webhooks", json!({ "url": "localhost:8080" }))
+        .await;
+    assert_eq!(code, 400);
+    assert_eq!(response["code"], "invalid_webhook_url");
+
+    let (response, code) = server
+        .service
+        .post(
+            "/webhooks",
+            json!({ "url": "http://localhost:8080", "headers": { "Invalid Name": "value" } }),
+        )
+        .await;
+    assert_eq!(code, 400);
+    assert_eq!(response["code"], "invalid_webhook_header");
+}
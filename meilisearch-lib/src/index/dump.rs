@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::document_formats::{read_ndjson, DocumentFormatError};
 use crate::index::update_handler::UpdateHandler;
-use crate::index::updates::apply_settings_to_builder;
+use crate::index::updates::{apply_meilisearch_settings, apply_settings_to_builder};
 
 use super::error::Result;
 use super::{index::Index, Settings, Unchecked};
@@ -123,6 +123,8 @@ impl Index {
 
         builder.execute(|_| ())?;
 
+        apply_meilisearch_settings(&index, &mut txn, &settings)?;
+
         let document_file_path = src.as_ref().join(DATA_FILE_NAME);
         let reader = BufReader::new(File::open(&document_file_path)?);
 
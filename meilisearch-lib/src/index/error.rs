@@ -4,6 +4,7 @@ use meilisearch_error::{internal_error, Code, ErrorCode};
 use serde_json::Value;
 
 use crate::error::MilliError;
+use crate::update_file_store::UpdateFileStoreError;
 
 pub type Result<T> = std::result::Result<T, IndexError>;
 
@@ -23,7 +24,9 @@ internal_error!(
     IndexError: std::io::Error,
     heed::Error,
     fst::Error,
-    serde_json::Error
+    serde_json::Error,
+    UpdateFileStoreError,
+    milli::documents::Error
 );
 
 impl ErrorCode for IndexError {
@@ -41,12 +44,17 @@ impl ErrorCode for IndexError {
 pub enum FacetError {
     #[error("Invalid syntax for the filter parameter: `expected {}, found: {1}`.", .0.join(", "))]
     InvalidExpression(&'static [&'static str], Value),
+    #[error(
+        "Attribute `{0}` is not filterable. The facet search only works on filterable attributes."
+    )]
+    NotFilterable(String),
 }
 
 impl ErrorCode for FacetError {
     fn error_code(&self) -> Code {
         match self {
             FacetError::InvalidExpression(_, _) => Code::Filter,
+            FacetError::NotFilterable(_) => Code::InvalidFacetSearchFacetName,
         }
     }
 }
@@ -0,0 +1,203 @@
+use std::cmp::Reverse;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::error::{FacetError, Result};
+use super::index::Index;
+use super::search::{parse_filter, FacetValue};
+
+pub const DEFAULT_FACET_SEARCH_LIMIT: usize = 100;
+const fn default_facet_search_limit() -> usize {
+    DEFAULT_FACET_SEARCH_LIMIT
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FacetSearchQuery {
+    pub facet_name: String,
+    /// The facet values are matched against this query as a prefix, allowing typos. All the
+    /// values match when it is missing.
+    pub facet_query: Option<String>,
+    /// The search `q` and `filter` restrict the facet values to the ones of their candidates.
+    pub q: Option<String>,
+    pub filter: Option<Value>,
+    #[serde(default = "default_facet_search_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FacetHit {
+    pub value: String,
+    pub count: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FacetSearchResult {
+    pub facet_hits: Vec<FacetHit>,
+    pub facet_query: Option<String>,
+    pub processing_time_ms: u128,
+}
+
+impl Index {
+    pub fn perform_facet_search(&self, query: FacetSearchQuery) -> Result<FacetSearchResult> {
+        let before_search = Instant::now();
+        let rtxn = self.read_txn()?;
+
+        if !self.filterable_fields(&rtxn)?.contains(&query.facet_name) {
+            return Err(FacetError::NotFilterable(query.facet_name).into());
+        }
+
+        let mut search = self.search(&rtxn);
+
+        if let Some(ref q) = query.q {
+            search.query(q);
+        }
+
+        if let Some(ref filter) = query.filter {
+            if let Some(facets) = parse_filter(filter)? {
+                search.filter(facets);
+            }
+        }
+
+        // Only the candidates of the search are needed.
+        search.limit(0);
+        let milli::SearchResult { candidates, .. } = search.execute()?;
+
+        let facet_query: Option<Vec<char>> = query
+            .facet_query
+            .as_deref()
+            .map(|facet_query| normalize(facet_query).chars().collect());
+
+        // The values are matched in their normalized form, as they are indexed, so the spellings
+        // of a value are counted together.
+        let mut facet_hits = Vec::new();
+        if let Some(fid) = self.fields_ids_map(&rtxn)?.id(&query.facet_name) {
+            for value in self.facet_values(&rtxn, fid)? {
+                let FacetValue {
+                    normalized,
+                    original,
+                    docids,
+                } = value?;
+                if let Some(ref facet_query) = facet_query {
+                    if !matches_facet_query(facet_query, &normalized) {
+                        continue;
+                    }
+                }
+
+                let count = (&docids & &candidates).len();
+                if count > 0 {
+                    facet_hits.push(FacetHit {
+                        value: original,
+                        count,
+                    });
+                }
+            }
+        }
+
+        // The most frequent values come first, the sort is stable so the values with the same
+        // count stay in the order of the facet databases.
+        facet_hits.sort_by_key(|hit| Reverse(hit.count));
+        facet_hits.truncate(query.limit);
+
+        Ok(FacetSearchResult {
+            facet_hits,
+            facet_query: query.facet_query,
+            processing_time_ms: before_search.elapsed().as_millis(),
+        })
+    }
+}
+
+fn normalize(s: &str) -> String {
+    s.trim().to_lowercase()
+}
+
+/// The number of typos allowed in a facet query, depending on its length.
+pub(super) fn allowed_typos(facet_query: &[char]) -> usize {
+    match facet_query.len() {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    }
+}
+
+/// A facet value matches the facet query if the query is a prefix, with typos, of the value
+/// starting at any of its words.
+fn matches_facet_query(facet_query: &[char], value: &str) -> bool {
+    let allowed_typos = allowed_typos(facet_query);
+    let value: Vec<char> = value.chars().collect();
+
+    facet_query.is_empty()
+        || (0..value.len())
+            .filter(|&start| start == 0 || value[start - 1].is_whitespace())
+            .any(|start| prefix_edit_distance(facet_query, &value[start..]) <= allowed_typos)
+}
+
+/// Returns the minimum number of edits needed to turn `query` into a prefix of `text`.
+pub(super) fn prefix_edit_distance(query: &[char], text: &[char]) -> usize {
+    edit_distances(query, text)
+        .into_iter()
+        .min()
+        .unwrap_or_default()
+}
+
+/// Returns the minimum number of edits needed to turn `query` into `text`.
+pub(super) fn edit_distance(query: &[char], text: &[char]) -> usize {
+    edit_distances(query, text)[text.len()]
+}
+
+/// Returns the edit distances between `query` and each prefix of `text`, by prefix length.
+fn edit_distances(query: &[char], text: &[char]) -> Vec<usize> {
+    // `row[j]` is the edit distance between the query prefix processed so far and the `j` first
+    // chars of `text`.
+    let mut row: Vec<usize> = (0..=text.len()).collect();
+
+    for (i, query_char) in query.iter().enumerate() {
+        let mut previous_diagonal = row[0];
+        row[0] = i + 1;
+        for (j, text_char) in text.iter().enumerate() {
+            let substitution = previous_diagonal + (query_char != text_char) as usize;
+            previous_diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_prefix_edit_distance() {
+        assert_eq!(prefix_edit_distance(&chars("app"), &chars("apple")), 0);
+        assert_eq!(prefix_edit_distance(&chars("apl"), &chars("apple")), 1);
+        assert_eq!(prefix_edit_distance(&chars("apple"), &chars("app")), 2);
+        assert_eq!(prefix_edit_distance(&chars(""), &chars("apple")), 0);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance(&chars("apple"), &chars("apple")), 0);
+        assert_eq!(edit_distance(&chars("app"), &chars("apple")), 2);
+        assert_eq!(edit_distance(&chars("aple"), &chars("apple")), 1);
+        assert_eq!(edit_distance(&chars(""), &chars("apple")), 5);
+    }
+
+    #[test]
+    fn test_matches_facet_query() {
+        assert!(matches_facet_query(&chars("sam"), "samsung"));
+        assert!(matches_facet_query(&chars("samsumg"), "samsung"));
+        assert!(!matches_facet_query(&chars("sma"), "samsung"));
+        assert!(matches_facet_query(&chars("harp"), "penguin harper"));
+        assert!(!matches_facet_query(&chars("guin"), "penguin harper"));
+        assert!(matches_facet_query(&chars(""), ""));
+    }
+}
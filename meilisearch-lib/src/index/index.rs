@@ -6,6 +6,7 @@ use std::path::Path;
 use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
+use heed::types::{SerdeJson, Str};
 use heed::{EnvOpenOptions, RoTxn};
 use milli::update::Setting;
 use milli::{obkv_to_json, FieldDistribution, FieldId};
@@ -17,11 +18,24 @@ use crate::EnvSizer;
 
 use super::error::IndexError;
 use super::error::Result;
+use super::search_cache::SearchCache;
 use super::update_handler::UpdateHandler;
 use super::{Checked, Settings};
 
 pub type Document = Map<String, Value>;
 
+/// The key of the `maxTotalHits` setting in the main database of the index. Milli doesn't know
+/// about this setting, it is stored along the milli settings.
+pub(crate) const MAX_TOTAL_HITS_KEY: &str = "max-total-hits";
+pub const DEFAULT_MAX_TOTAL_HITS: usize = 1000;
+/// The keys of the default highlight tags and crop marker of the search formatting.
+pub(crate) const HIGHLIGHT_PRE_TAG_KEY: &str = "highlight-pre-tag";
+pub(crate) const HIGHLIGHT_POST_TAG_KEY: &str = "highlight-post-tag";
+pub(crate) const CROP_MARKER_KEY: &str = "crop-marker";
+pub const DEFAULT_HIGHLIGHT_PRE_TAG: &str = "<em>";
+pub const DEFAULT_HIGHLIGHT_POST_TAG: &str = "</em>";
+pub const DEFAULT_CROP_MARKER: &str = "…";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexMeta {
@@ -69,6 +83,8 @@ pub struct Index {
     pub inner: Arc<milli::Index>,
     #[derivative(Debug = "ignore")]
     pub update_handler: Arc<UpdateHandler>,
+    #[derivative(Debug = "ignore")]
+    pub search_cache: Arc<SearchCache>,
 }
 
 impl Deref for Index {
@@ -85,6 +101,7 @@ impl Index {
         size: usize,
         uuid: Uuid,
         update_handler: Arc<UpdateHandler>,
+        search_cache: Arc<SearchCache>,
     ) -> Result<Self> {
         log::debug!("opening index in {}", path.as_ref().display());
         create_dir_all(&path)?;
@@ -95,11 +112,13 @@ impl Index {
             inner,
             uuid,
             update_handler,
+            search_cache,
         })
     }
 
     /// Asynchronously close the underlying index
     pub fn close(self) {
+        self.search_cache.invalidate(self.uuid);
         self.inner.as_ref().clone().prepare_for_closing();
     }
 
@@ -126,6 +145,37 @@ impl Index {
         self.uuid
     }
 
+    /// The maximum number of hits a search paginated by pages can return.
+    pub fn max_total_hits(&self, txn: &RoTxn) -> Result<usize> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<usize>>(txn, MAX_TOTAL_HITS_KEY)?
+            .unwrap_or(DEFAULT_MAX_TOTAL_HITS))
+    }
+
+    /// The tag inserted before the highlighted words, when the search query doesn't specify one.
+    pub fn highlight_pre_tag(&self, txn: &RoTxn) -> Result<String> {
+        self.string_setting(txn, HIGHLIGHT_PRE_TAG_KEY, DEFAULT_HIGHLIGHT_PRE_TAG)
+    }
+
+    /// The tag inserted after the highlighted words, when the search query doesn't specify one.
+    pub fn highlight_post_tag(&self, txn: &RoTxn) -> Result<String> {
+        self.string_setting(txn, HIGHLIGHT_POST_TAG_KEY, DEFAULT_HIGHLIGHT_POST_TAG)
+    }
+
+    /// The marker inserted where a cropped text is truncated, when the search query doesn't
+    /// specify one.
+    pub fn crop_marker(&self, txn: &RoTxn) -> Result<String> {
+        self.string_setting(txn, CROP_MARKER_KEY, DEFAULT_CROP_MARKER)
+    }
+
+    fn string_setting(&self, txn: &RoTxn, key: &str, default: &str) -> Result<String> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<String>>(txn, key)?
+            .unwrap_or_else(|| default.to_string()))
+    }
+
     pub fn settings_txn(&self, txn: &RoTxn) -> Result<Settings<Checked>> {
         let displayed_attributes = self
             .displayed_fields(txn)?
@@ -167,6 +217,11 @@ impl Index {
             })
             .collect();
 
+        let max_total_hits = self.max_total_hits(txn)?;
+        let highlight_pre_tag = self.highlight_pre_tag(txn)?;
+        let highlight_post_tag = self.highlight_post_tag(txn)?;
+        let crop_marker = self.crop_marker(txn)?;
+
         Ok(Settings {
             displayed_attributes: match displayed_attributes {
                 Some(attrs) => Setting::Set(attrs),
@@ -185,6 +240,10 @@ impl Index {
                 None => Setting::Reset,
             },
             synonyms: Setting::Set(synonyms),
+            max_total_hits: Setting::Set(max_total_hits),
+            highlight_pre_tag: Setting::Set(highlight_pre_tag),
+            highlight_post_tag: Setting::Set(highlight_post_tag),
+            crop_marker: Setting::Set(crop_marker),
             _kind: PhantomData,
         })
     }
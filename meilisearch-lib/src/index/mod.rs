@@ -1,9 +1,16 @@
-pub use search::{default_crop_length, SearchQuery, SearchResult, DEFAULT_SEARCH_LIMIT};
+pub use facet_search::{FacetSearchQuery, FacetSearchResult};
+pub use search::{
+    default_crop_length, default_crop_snippets, default_max_values_per_facet, SearchHit,
+    SearchQuery, SearchResult, DEFAULT_SEARCH_LIMIT,
+};
 pub use updates::{apply_settings_to_builder, Checked, Facets, Settings, Unchecked};
 
 mod dump;
 pub mod error;
+mod facet_search;
+mod ranking_score;
 mod search;
+pub mod search_cache;
 pub mod update_handler;
 pub mod updates;
 
@@ -26,15 +33,22 @@ pub mod test {
     use std::path::PathBuf;
     use std::sync::Arc;
 
-    use milli::update::{DocumentAdditionResult, DocumentDeletionResult, IndexDocumentsMethod};
+    use milli::update::{
+        DocumentAdditionResult, DocumentDeletionResult, IndexDocumentsMethod, UpdateIndexingStep,
+    };
     use nelson::Mocker;
     use serde_json::{Map, Value};
     use uuid::Uuid;
 
     use super::error::Result;
     use super::index::Index;
+    use super::search_cache::SearchCache;
     use super::update_handler::UpdateHandler;
-    use super::{Checked, IndexMeta, IndexStats, SearchQuery, SearchResult, Settings};
+    use super::{
+        Checked, FacetSearchQuery, FacetSearchResult, IndexMeta, IndexStats, SearchQuery,
+        SearchResult, Settings,
+    };
+    use crate::tasks::task::{TaskResult, TransactionOperation};
     use crate::update_file_store::UpdateFileStore;
 
     #[derive(Clone)]
@@ -53,8 +67,9 @@ pub mod test {
             size: usize,
             uuid: Uuid,
             update_handler: Arc<UpdateHandler>,
+            search_cache: Arc<SearchCache>,
         ) -> Result<Self> {
-            let index = Index::open(path, size, uuid, update_handler)?;
+            let index = Index::open(path, size, uuid, update_handler, search_cache)?;
             Ok(Self::Real(index))
         }
 
@@ -147,6 +162,13 @@ pub mod test {
             }
         }
 
+        pub fn perform_facet_search(&self, query: FacetSearchQuery) -> Result<FacetSearchResult> {
+            match self {
+                MockIndex::Real(index) => index.perform_facet_search(query),
+                MockIndex::Mock(m) => unsafe { m.get("perform_facet_search").call(query) },
+            }
+        }
+
         pub fn dump(&self, path: impl AsRef<Path>) -> Result<()> {
             match self {
                 MockIndex::Real(index) => index.dump(path),
@@ -160,10 +182,11 @@ pub mod test {
             content_uuid: Uuid,
             primary_key: Option<String>,
             file_store: UpdateFileStore,
+            progress: impl Fn(UpdateIndexingStep) + Sync,
         ) -> Result<DocumentAdditionResult> {
             match self {
                 MockIndex::Real(index) => {
-                    index.update_documents(method, content_uuid, primary_key, file_store)
+                    index.update_documents(method, content_uuid, primary_key, file_store, progress)
                 }
                 MockIndex::Mock(mocker) => unsafe {
                     mocker.get("update_documents").call((
@@ -176,6 +199,46 @@ pub mod test {
             }
         }
 
+        pub fn update_documents_batch(
+            &self,
+            method: IndexDocumentsMethod,
+            content_uuids: &[Uuid],
+            primary_key: Option<String>,
+            file_store: UpdateFileStore,
+            progress: impl Fn(usize, UpdateIndexingStep) + Sync,
+        ) -> Result<Vec<DocumentAdditionResult>> {
+            match self {
+                MockIndex::Real(index) => index.update_documents_batch(
+                    method,
+                    content_uuids,
+                    primary_key,
+                    file_store,
+                    progress,
+                ),
+                MockIndex::Mock(mocker) => unsafe {
+                    mocker.get("update_documents_batch").call((
+                        method,
+                        content_uuids,
+                        primary_key,
+                        file_store,
+                    ))
+                },
+            }
+        }
+
+        pub fn apply_transaction(
+            &self,
+            operations: &[TransactionOperation],
+            file_store: UpdateFileStore,
+        ) -> Result<Vec<TaskResult>> {
+            match self {
+                MockIndex::Real(index) => index.apply_transaction(operations, file_store),
+                MockIndex::Mock(m) => unsafe {
+                    m.get("apply_transaction").call((operations, file_store))
+                },
+            }
+        }
+
         pub fn update_settings(&self, settings: &Settings<Checked>) -> Result<()> {
             match self {
                 MockIndex::Real(index) => index.update_settings(settings),
@@ -197,6 +260,16 @@ pub mod test {
             }
         }
 
+        pub fn delete_documents_batch(
+            &self,
+            batch: &[Vec<String>],
+        ) -> Result<Vec<DocumentDeletionResult>> {
+            match self {
+                MockIndex::Real(index) => index.delete_documents_batch(batch),
+                MockIndex::Mock(m) => unsafe { m.get("delete_documents_batch").call(batch) },
+            }
+        }
+
         pub fn clear_documents(&self) -> Result<()> {
             match self {
                 MockIndex::Real(index) => index.clear_documents(),
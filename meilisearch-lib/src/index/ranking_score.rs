@@ -0,0 +1,544 @@
+use indexmap::IndexMap;
+use milli::tokenizer::Analyzer;
+use milli::{AscDesc, Criterion, FieldId, FieldsIdsMap, Member};
+use serde_json::{json, Map, Value};
+
+use super::facet_search::{allowed_typos, edit_distance, prefix_edit_distance};
+
+/// The details of the ranking score of a hit, by ranking rule, in the order of the rules.
+pub type ScoreDetails = IndexMap<String, Value>;
+
+/// The proximity of two query words found in different attributes. In the same attribute, the
+/// proximity is the distance between the words, bounded by `MAX_PROXIMITY - 1`.
+const MAX_PROXIMITY: usize = 8;
+
+/// The gap between the positions of the values of an array, so that the words of two different
+/// values are never considered close.
+const VALUES_POSITION_GAP: usize = MAX_PROXIMITY;
+
+/// The rank of a document for a ranking rule, from 1 to `max_rank`. The best documents have the
+/// highest rank.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rank {
+    rank: usize,
+    max_rank: usize,
+}
+
+impl Rank {
+    /// Ranks a document from its cost for a rule, 0 being the cost of the best documents.
+    fn from_cost(cost: usize, max_cost: usize) -> Self {
+        Self {
+            rank: max_cost - cost.min(max_cost) + 1,
+            max_rank: max_cost + 1,
+        }
+    }
+
+    fn score(self) -> f64 {
+        self.rank as f64 / self.max_rank as f64
+    }
+}
+
+/// Combines the ranks of a document into a score between 0 and 1. The scores of two documents are
+/// ordered as the ranking rules order them: a rank only matters when all the previous ranks of
+/// the documents are equal.
+fn global_score(ranks: impl IntoIterator<Item = Rank>) -> f64 {
+    let mut score = 1.0;
+    let mut weight = 1.0;
+    for Rank { rank, max_rank } in ranks {
+        weight /= max_rank as f64;
+        score -= (max_rank - rank) as f64 * weight;
+    }
+
+    score
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MatchType {
+    NoExactMatch,
+    MatchesStart,
+    ExactMatch,
+}
+
+impl MatchType {
+    fn name(self) -> &'static str {
+        match self {
+            MatchType::NoExactMatch => "noExactMatch",
+            MatchType::MatchesStart => "matchesStart",
+            MatchType::ExactMatch => "exactMatch",
+        }
+    }
+}
+
+struct QueryWord {
+    text: String,
+    chars: Vec<char>,
+    /// The last word of the query also matches the words it is a prefix of
+    is_prefix: bool,
+}
+
+/// An occurrence of a query word in a document.
+struct Occurrence {
+    /// The index of the attribute in the searchable attributes
+    attribute: usize,
+    position: usize,
+    typos: usize,
+    /// The word of the document is the query word itself, neither misspelled nor completed
+    exact: bool,
+}
+
+/// The words of an attribute of a document, by value of the attribute. The stop words are kept
+/// as `None`, to count them in the positions.
+type AttributeWords = Vec<Vec<Option<String>>>;
+
+/// Computes an approximate ranking score of the hits of a search, by evaluating the ranking rules
+/// of the index on each hit. The query words are matched with the typos allowed by the search,
+/// but the synonyms are ignored, so a score can disagree with the order of the search.
+pub(super) struct Ranker<'a, A> {
+    analyzer: &'a Analyzer<'a, A>,
+    query_words: Vec<QueryWord>,
+    rules: Vec<Criterion>,
+    /// The `sort` of the search, evaluated in place of the `sort` ranking rule
+    sort: Vec<(String, AscDesc)>,
+    /// The searchable attributes, by order of importance
+    searchable_ids: Vec<FieldId>,
+}
+
+impl<'a, A: AsRef<[u8]>> Ranker<'a, A> {
+    pub fn new(
+        analyzer: &'a Analyzer<'a, A>,
+        query: Option<&str>,
+        rules: Vec<Criterion>,
+        sort: Vec<(String, AscDesc)>,
+        searchable_ids: Vec<FieldId>,
+    ) -> Self {
+        let mut query_words = Vec::new();
+        if let Some(query) = query {
+            let analyzed = analyzer.analyze(query);
+            query_words.extend(
+                analyzed
+                    .tokens()
+                    .filter(|token| token.is_word())
+                    .map(|token| QueryWord {
+                        text: token.text().to_string(),
+                        chars: token.text().chars().collect(),
+                        is_prefix: false,
+                    }),
+            );
+
+            // As in the search, the last word is a prefix unless the query ends with a space.
+            if let Some(last) = query_words.last_mut() {
+                last.is_prefix = !query.ends_with(char::is_whitespace);
+            }
+        }
+
+        Self {
+            analyzer,
+            query_words,
+            rules,
+            sort,
+            searchable_ids,
+        }
+    }
+
+    /// Returns the ranking score of a document, along with its details.
+    pub fn score(
+        &self,
+        fields_ids_map: &FieldsIdsMap,
+        obkv: obkv::KvReaderU16,
+    ) -> (f64, ScoreDetails) {
+        // The fields of the stored documents are always valid JSON.
+        let field_value = |fid: FieldId| -> Option<Value> {
+            obkv.get(fid)
+                .and_then(|value| serde_json::from_slice(value).ok())
+        };
+
+        let attributes: Vec<_> = self
+            .searchable_ids
+            .iter()
+            .map(|&fid| {
+                let mut words = AttributeWords::new();
+                if let Some(value) = field_value(fid) {
+                    self.insert_words(&value, &mut words);
+                }
+                words
+            })
+            .collect();
+
+        self.rank_document(&attributes, |name| {
+            fields_ids_map.id(name).and_then(field_value)
+        })
+    }
+
+    fn insert_words(&self, value: &Value, words: &mut AttributeWords) {
+        match value {
+            Value::String(s) => {
+                let analyzed = self.analyzer.analyze(s);
+                let value_words = analyzed
+                    .tokens()
+                    .filter(|token| token.is_word() || token.is_stopword())
+                    .map(|token| token.is_word().then(|| token.text().to_string()))
+                    .collect();
+                words.push(value_words);
+            }
+            Value::Number(number) => self.insert_words(&Value::String(number.to_string()), words),
+            Value::Array(values) => values
+                .iter()
+                .for_each(|value| self.insert_words(value, words)),
+            Value::Object(values) => values
+                .values()
+                .for_each(|value| self.insert_words(value, words)),
+            _ => (),
+        }
+    }
+
+    /// Ranks a document from the words of its searchable attributes, `field_value` returns the
+    /// value of its fields used by the sort and custom ranking rules.
+    fn rank_document(
+        &self,
+        attributes: &[AttributeWords],
+        field_value: impl Fn(&str) -> Option<Value>,
+    ) -> (f64, ScoreDetails) {
+        let occurrences: Vec<_> = self
+            .query_words
+            .iter()
+            .map(|word| find_occurrences(word, attributes))
+            .collect();
+
+        // The words rule drops the last words of the query until the document contains all the
+        // remaining ones, only these words are considered by the next rules.
+        let matching_words = occurrences
+            .iter()
+            .take_while(|occurrences| !occurrences.is_empty())
+            .count();
+        let matched = &occurrences[..matching_words];
+        let max_matching_words = self.query_words.len();
+
+        let mut rules: Vec<(String, Option<Rank>, Value)> = Vec::new();
+        for rule in &self.rules {
+            match rule {
+                // Without query words, only the sort and custom rules rank the documents.
+                Criterion::Words
+                | Criterion::Typo
+                | Criterion::Proximity
+                | Criterion::Attribute
+                | Criterion::Exactness
+                    if self.query_words.is_empty() => {}
+                Criterion::Words => {
+                    let rank =
+                        Rank::from_cost(max_matching_words - matching_words, max_matching_words);
+                    let details = json!({
+                        "matchingWords": matching_words,
+                        "maxMatchingWords": max_matching_words,
+                    });
+                    rules.push(("words".to_string(), Some(rank), details));
+                }
+                Criterion::Typo => {
+                    let typo_count: usize = matched
+                        .iter()
+                        .map(|occurrences| occurrences.iter().map(|o| o.typos).min())
+                        .map(Option::unwrap_or_default)
+                        .sum();
+                    let max_typo_count: usize = self.query_words[..matching_words]
+                        .iter()
+                        .map(|word| allowed_typos(&word.chars))
+                        .sum();
+                    let rank = Rank::from_cost(typo_count, max_typo_count);
+                    let details = json!({
+                        "typoCount": typo_count,
+                        "maxTypoCount": max_typo_count,
+                    });
+                    rules.push(("typo".to_string(), Some(rank), details));
+                }
+                Criterion::Proximity => {
+                    let cost: usize = matched
+                        .windows(2)
+                        .map(|pair| proximity(&pair[0], &pair[1]) - 1)
+                        .sum();
+                    let max_cost = matching_words.saturating_sub(1) * (MAX_PROXIMITY - 1);
+                    let rank = Rank::from_cost(cost, max_cost);
+                    rules.push(("proximity".to_string(), Some(rank), json!({})));
+                }
+                Criterion::Attribute => {
+                    // The cost of a word is the index of the most important attribute it is in.
+                    let cost: usize = matched
+                        .iter()
+                        .map(|occurrences| occurrences.iter().map(|o| o.attribute).min())
+                        .map(Option::unwrap_or_default)
+                        .sum();
+                    let max_cost = matching_words * attributes.len().saturating_sub(1);
+                    let rank = Rank::from_cost(cost, max_cost);
+                    rules.push(("attribute".to_string(), Some(rank), json!({})));
+                }
+                Criterion::Exactness => {
+                    let exact_words = matched
+                        .iter()
+                        .filter(|occurrences| occurrences.iter().any(|o| o.exact))
+                        .count();
+                    let match_type = self.match_type(attributes);
+                    // The documents are ranked by match type, then by number of exact words.
+                    let rank = Rank {
+                        rank: match_type as usize * (max_matching_words + 1) + exact_words + 1,
+                        max_rank: (MatchType::ExactMatch as usize + 1) * (max_matching_words + 1),
+                    };
+                    let details = json!({
+                        "matchType": match_type.name(),
+                        "matchingWords": exact_words,
+                        "maxMatchingWords": max_matching_words,
+                    });
+                    rules.push(("exactness".to_string(), Some(rank), details));
+                }
+                Criterion::Sort => {
+                    for (name, asc_desc) in &self.sort {
+                        let member = match asc_desc {
+                            AscDesc::Asc(member) | AscDesc::Desc(member) => member,
+                        };
+                        let details = json!({ "value": member_value(member, &field_value) });
+                        rules.push((name.clone(), None, details));
+                    }
+                }
+                Criterion::Asc(field) => {
+                    let details = json!({ "value": field_value(field) });
+                    rules.push((format!("{}:asc", field), None, details));
+                }
+                Criterion::Desc(field) => {
+                    let details = json!({ "value": field_value(field) });
+                    rules.push((format!("{}:desc", field), None, details));
+                }
+            }
+        }
+
+        let score = global_score(rules.iter().filter_map(|(_, rank, _)| *rank));
+        let details = rules
+            .into_iter()
+            .enumerate()
+            .map(|(order, (name, rank, details))| {
+                let mut rule = Map::new();
+                rule.insert("order".to_string(), json!(order));
+                if let Value::Object(details) = details {
+                    rule.extend(details);
+                }
+                if let Some(rank) = rank {
+                    rule.insert("score".to_string(), json!(rank.score()));
+                }
+                (name, Value::Object(rule))
+            })
+            .collect();
+
+        (score, details)
+    }
+
+    /// Returns whether a value of a searchable attribute is exactly the query, or starts with it.
+    fn match_type(&self, attributes: &[AttributeWords]) -> MatchType {
+        let query_len = self.query_words.len();
+        let mut match_type = MatchType::NoExactMatch;
+
+        for value in attributes.iter().flatten() {
+            let words: Vec<&str> = value.iter().flatten().map(String::as_str).collect();
+            let starts_with_query = words.len() >= query_len
+                && words[..query_len]
+                    .iter()
+                    .copied()
+                    .eq(self.query_words.iter().map(|word| word.text.as_str()));
+
+            if starts_with_query && words.len() == query_len {
+                return MatchType::ExactMatch;
+            } else if starts_with_query {
+                match_type = MatchType::MatchesStart;
+            }
+        }
+
+        match_type
+    }
+}
+
+fn find_occurrences(word: &QueryWord, attributes: &[AttributeWords]) -> Vec<Occurrence> {
+    let allowed_typos = allowed_typos(&word.chars);
+    let mut occurrences = Vec::new();
+
+    for (attribute, values) in attributes.iter().enumerate() {
+        let mut position = 0;
+        for value in values {
+            for document_word in value {
+                if let Some(document_word) = document_word {
+                    let chars: Vec<char> = document_word.chars().collect();
+                    let typos = if word.is_prefix {
+                        prefix_edit_distance(&word.chars, &chars)
+                    } else {
+                        edit_distance(&word.chars, &chars)
+                    };
+
+                    if typos <= allowed_typos {
+                        occurrences.push(Occurrence {
+                            attribute,
+                            position,
+                            typos,
+                            exact: *document_word == word.text,
+                        });
+                    }
+                }
+                position += 1;
+            }
+            position += VALUES_POSITION_GAP;
+        }
+    }
+
+    occurrences
+}
+
+/// Returns the proximity of two consecutive query words in a document, from 1 when the second
+/// word follows the first one, to `MAX_PROXIMITY`.
+fn proximity(first: &[Occurrence], second: &[Occurrence]) -> usize {
+    first
+        .iter()
+        .flat_map(|a| {
+            second
+                .iter()
+                .filter(move |b| a.attribute == b.attribute)
+                .map(move |b| {
+                    // The words in the reverse order are a bit farther than in the query order.
+                    let distance = if a.position < b.position {
+                        b.position - a.position
+                    } else {
+                        a.position - b.position + 1
+                    };
+                    distance.min(MAX_PROXIMITY - 1)
+                })
+        })
+        .min()
+        .unwrap_or(MAX_PROXIMITY)
+}
+
+/// Returns the value a document is sorted by: the value of a field, or the distance in meters to
+/// a geo point.
+fn member_value(member: &Member, field_value: impl Fn(&str) -> Option<Value>) -> Option<Value> {
+    match member {
+        Member::Field(name) => field_value(name),
+        Member::Geo(base) => {
+            let geo_point = field_value("_geo")?;
+            let (lat, lng) = geo_point["lat"].as_f64().zip(geo_point["lng"].as_f64())?;
+            let distance = milli::distance_between_two_points(base, &[lat, lng]);
+            Some(json!(distance.round() as usize))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use milli::tokenizer::AnalyzerConfig;
+
+    use super::*;
+
+    fn words(values: &[&str]) -> AttributeWords {
+        values
+            .iter()
+            .map(|value| {
+                value
+                    .split_whitespace()
+                    .map(|w| Some(w.to_string()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn rules() -> Vec<Criterion> {
+        vec![
+            Criterion::Words,
+            Criterion::Typo,
+            Criterion::Proximity,
+            Criterion::Attribute,
+            Criterion::Sort,
+            Criterion::Exactness,
+        ]
+    }
+
+    #[test]
+    fn global_score_follows_the_order_of_the_rules() {
+        let perfect = [Rank::from_cost(0, 2), Rank::from_cost(0, 4)];
+        assert_eq!(global_score(perfect), 1.0);
+        assert_eq!(global_score([]), 1.0);
+
+        // Only the first rank matters when the first ranks differ.
+        let first = global_score([Rank::from_cost(0, 2), Rank::from_cost(4, 4)]);
+        let second = global_score([Rank::from_cost(1, 2), Rank::from_cost(0, 4)]);
+        assert!(first > second);
+        assert!(second > 0.0);
+    }
+
+    #[test]
+    fn rank_documents() {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let ranker = Ranker::new(
+            &analyzer,
+            Some("harry potter"),
+            rules(),
+            Vec::new(),
+            vec![0, 1],
+        );
+
+        let (exact, details) =
+            ranker.rank_document(&[words(&["harry potter"]), words(&[])], |_| None);
+        assert_eq!(exact, 1.0);
+        assert_eq!(
+            details.keys().collect::<Vec<_>>(),
+            ["words", "typo", "proximity", "attribute", "exactness"]
+        );
+        assert_eq!(details["exactness"]["matchType"], "exactMatch");
+
+        let (typo, details) =
+            ranker.rank_document(&[words(&["harry poter"]), words(&[])], |_| None);
+        assert!(typo < exact);
+        assert_eq!(details["typo"]["typoCount"], 1);
+        assert_eq!(details["exactness"]["matchType"], "noExactMatch");
+
+        // The last word of the query is a prefix.
+        let (prefix, details) =
+            ranker.rank_document(&[words(&["harry potterhead"]), words(&[])], |_| None);
+        assert_eq!(details["typo"]["typoCount"], 0);
+        assert_eq!(details["exactness"]["matchingWords"], 1);
+        assert!(prefix < exact);
+
+        let (far, details) =
+            ranker.rank_document(&[words(&["harry"]), words(&["potter and harry"])], |_| None);
+        assert_eq!(details["words"]["matchingWords"], 2);
+        assert!(details["proximity"]["score"].as_f64().unwrap() < 1.0);
+        assert!(details["attribute"]["score"].as_f64().unwrap() < 1.0);
+        assert!(far < prefix);
+
+        let (partial, details) = ranker.rank_document(&[words(&["harry"]), words(&[])], |_| None);
+        assert_eq!(details["words"]["matchingWords"], 1);
+        assert_eq!(details["words"]["maxMatchingWords"], 2);
+        assert!(partial < far);
+    }
+
+    #[test]
+    fn rank_documents_without_query() {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let mut rules = rules();
+        rules.push(Criterion::Desc("release_date".to_string()));
+        let sort = vec![(
+            "price:asc".to_string(),
+            AscDesc::Asc(Member::Field("price".to_string())),
+        )];
+        let ranker = Ranker::new(&analyzer, None, rules, sort, vec![0]);
+
+        let (score, details) =
+            ranker.rank_document(&[words(&["harry potter"])], |name| match name {
+                "price" => Some(json!(12)),
+                _ => None,
+            });
+        assert_eq!(score, 1.0);
+        assert_eq!(
+            Value::Object(details.into_iter().collect()),
+            json!({
+                "price:asc": { "order": 0, "value": 12 },
+                "release_date:desc": { "order": 1, "value": null },
+            })
+        );
+    }
+}
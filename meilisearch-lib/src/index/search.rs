@@ -1,12 +1,15 @@
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::str::FromStr;
 use std::time::Instant;
 
 use either::Either;
+use heed::RoTxn;
 use indexmap::IndexMap;
 use milli::tokenizer::{Analyzer, AnalyzerConfig, Token};
 use milli::{AscDesc, FieldId, FieldsIdsMap, Filter, MatchingWords, SortError};
 use regex::Regex;
+use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
@@ -14,6 +17,7 @@ use crate::index::error::FacetError;
 
 use super::error::{IndexError, Result};
 use super::index::Index;
+use super::ranking_score::{Ranker, ScoreDetails};
 
 pub type Document = IndexMap<String, Value>;
 type MatchesInfo = BTreeMap<String, Vec<MatchInfo>>;
@@ -29,11 +33,22 @@ const fn default_search_limit() -> usize {
     DEFAULT_SEARCH_LIMIT
 }
 
-pub const DEFAULT_CROP_LENGTH: usize = 200;
+pub const DEFAULT_MAX_VALUES_PER_FACET: usize = 100;
+pub const fn default_max_values_per_facet() -> usize {
+    DEFAULT_MAX_VALUES_PER_FACET
+}
+
+/// The number of words kept around the matches when cropping.
+pub const DEFAULT_CROP_LENGTH: usize = 10;
 pub const fn default_crop_length() -> usize {
     DEFAULT_CROP_LENGTH
 }
 
+pub const DEFAULT_CROP_SNIPPETS: usize = 1;
+pub const fn default_crop_snippets() -> usize {
+    DEFAULT_CROP_SNIPPETS
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct SearchQuery {
@@ -41,17 +56,76 @@ pub struct SearchQuery {
     pub offset: Option<usize>,
     #[serde(default = "default_search_limit")]
     pub limit: usize,
+    /// The page to return, starting at 1. When `page` or `hits_per_page` is given, `offset` and
+    /// `limit` are ignored and the hits are counted exhaustively.
+    pub page: Option<usize>,
+    pub hits_per_page: Option<usize>,
     pub attributes_to_retrieve: Option<BTreeSet<String>>,
     pub attributes_to_crop: Option<Vec<String>>,
+    /// The number of words of a cropped snippet
     #[serde(default = "default_crop_length")]
     pub crop_length: usize,
+    /// The maximum number of snippets returned by cropped attribute
+    #[serde(default = "default_crop_snippets")]
+    pub crop_snippets: usize,
     pub attributes_to_highlight: Option<HashSet<String>>,
+    /// The tags surrounding the highlighted words, default to the settings of the index
+    pub highlight_pre_tag: Option<String>,
+    pub highlight_post_tag: Option<String>,
+    /// The marker inserted where a cropped text is truncated, default to the settings of the
+    /// index
+    pub crop_marker: Option<String>,
     // Default to false
     #[serde(default = "Default::default")]
     pub matches: bool,
+    /// Adds to each hit an approximation of its ranking score, between 0 and 1. It can disagree
+    /// with the order of the hits
+    #[serde(default = "Default::default")]
+    pub show_ranking_score: bool,
+    /// Adds to each hit the details of its ranking score, by ranking rule
+    #[serde(default = "Default::default")]
+    pub show_ranking_score_details: bool,
     pub filter: Option<Value>,
     pub sort: Option<Vec<String>>,
     pub facets_distribution: Option<Vec<String>>,
+    /// Count the facet values over all the candidates
+    #[serde(default = "Default::default")]
+    pub exhaustive_facets_count: bool,
+    /// The maximum number of values counted by facet, when counting exhaustively
+    #[serde(default = "default_max_values_per_facet")]
+    pub max_values_per_facet: usize,
+    /// Computes the statistics of the numeric values of the distributed facets
+    #[serde(default = "Default::default")]
+    pub show_facet_stats: bool,
+}
+
+impl SearchQuery {
+    /// Returns the requested page and number of hits per page, if the query is paginated by
+    /// pages.
+    fn page_and_hits_per_page(&self) -> Option<(usize, usize)> {
+        if self.page.is_none() && self.hits_per_page.is_none() {
+            return None;
+        }
+
+        Some((
+            self.page.unwrap_or(1),
+            self.hits_per_page.unwrap_or(DEFAULT_SEARCH_LIMIT),
+        ))
+    }
+
+    /// Returns a representation of the query that is the same for all the queries returning the
+    /// same results, used to cache the results.
+    fn cache_key(&self) -> String {
+        let mut query = self.clone();
+        query.offset = Some(query.offset.unwrap_or_default());
+        // The iteration order of a `HashSet` is random, the attributes are sorted apart.
+        let attributes_to_highlight: Option<BTreeSet<_>> = query
+            .attributes_to_highlight
+            .take()
+            .map(|attributes| attributes.into_iter().collect());
+
+        format!("{:?} {:?}", query, attributes_to_highlight)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -62,6 +136,13 @@ pub struct SearchHit {
     pub formatted: Document,
     #[serde(rename = "_matchesInfo", skip_serializing_if = "Option::is_none")]
     pub matches_info: Option<MatchesInfo>,
+    #[serde(rename = "_rankingScore", skip_serializing_if = "Option::is_none")]
+    pub ranking_score: Option<f64>,
+    #[serde(
+        rename = "_rankingScoreDetails",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ranking_score_details: Option<ScoreDetails>,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -74,10 +155,67 @@ pub struct SearchResult {
     pub limit: usize,
     pub offset: usize,
     pub processing_time_ms: u128,
+    #[serde(flatten)]
+    pub pagination: Option<Pagination>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub facets_distribution: Option<BTreeMap<String, BTreeMap<String, u64>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exhaustive_facets_count: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facet_stats: Option<BTreeMap<String, FacetStats>>,
+}
+
+/// The statistics of the numeric values of a facet among the candidates of a search. A value is
+/// accounted once per document having it.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FacetStats {
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    pub avg: f64,
+}
+
+/// The pagination of a search result paginated by pages.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    pub page: usize,
+    pub hits_per_page: usize,
+    /// The exact number of hits, bounded by the `maxTotalHits` setting of the index
+    pub total_hits: usize,
+    pub total_pages: usize,
+}
+
+impl Pagination {
+    fn new(page: usize, hits_per_page: usize, total_hits: usize) -> Self {
+        let total_pages = if hits_per_page == 0 {
+            0
+        } else {
+            (total_hits + hits_per_page - 1) / hits_per_page
+        };
+
+        Self {
+            page,
+            hits_per_page,
+            total_hits,
+            total_pages,
+        }
+    }
+
+    /// The offset of the first hit of the page. The page 0 contains no hit.
+    fn offset(&self) -> usize {
+        self.page
+            .saturating_sub(1)
+            .saturating_mul(self.hits_per_page)
+    }
+
+    fn limit(&self) -> usize {
+        if self.page == 0 {
+            0
+        } else {
+            self.hits_per_page
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -87,7 +225,30 @@ struct FormatOptions {
 }
 
 impl Index {
+    /// Performs the search, or returns its result from the search cache when it is enabled.
     pub fn perform_search(&self, query: SearchQuery) -> Result<SearchResult> {
+        if !self.search_cache.is_enabled() {
+            return self.execute_search(query);
+        }
+
+        let before_search = Instant::now();
+        let key = query.cache_key();
+        if let Some(mut result) = self.search_cache.get(self.uuid, &key) {
+            result.processing_time_ms = before_search.elapsed().as_millis();
+            return Ok(result);
+        }
+
+        // The generation must be retrieved before the search reads the index, so that a result
+        // computed on data overwritten in the meantime is not cached.
+        let generation = self.search_cache.generation(self.uuid);
+        let result = self.execute_search(query)?;
+        self.search_cache
+            .insert(self.uuid, key, generation, &result);
+
+        Ok(result)
+    }
+
+    fn execute_search(&self, query: SearchQuery) -> Result<SearchResult> {
         let before_search = Instant::now();
         let rtxn = self.read_txn()?;
 
@@ -97,8 +258,19 @@ impl Index {
             search.query(query);
         }
 
-        search.limit(query.limit);
-        search.offset(query.offset.unwrap_or_default());
+        // When paginating by pages, the search returns the ranked ids of all the hits, up to
+        // `maxTotalHits`, to count them exactly: the candidates returned by milli are only an
+        // estimation of the number of hits.
+        match query.page_and_hits_per_page() {
+            Some(_) => {
+                search.limit(self.max_total_hits(&rtxn)?);
+                search.offset(0);
+            }
+            None => {
+                search.limit(query.limit);
+                search.offset(query.offset.unwrap_or_default());
+            }
+        }
 
         if let Some(ref filter) = query.filter {
             if let Some(facets) = parse_filter(filter)? {
@@ -106,15 +278,18 @@ impl Index {
             }
         }
 
+        let mut sort_criteria = Vec::new();
         if let Some(ref sort) = query.sort {
-            let sort = match sort.iter().map(|s| AscDesc::from_str(s)).collect() {
+            let criteria: Vec<_> = match sort.iter().map(|s| AscDesc::from_str(s)).collect() {
                 Ok(sorts) => sorts,
                 Err(asc_desc_error) => {
                     return Err(IndexError::Milli(SortError::from(asc_desc_error).into()))
                 }
             };
 
-            search.sort_criteria(sort);
+            // The sort is kept along with its criteria to report the ranking score details.
+            sort_criteria = sort.iter().cloned().zip(criteria.clone()).collect();
+            search.sort_criteria(criteria);
         }
 
         let milli::SearchResult {
@@ -124,6 +299,19 @@ impl Index {
             ..
         } = search.execute()?;
 
+        let (documents_ids, pagination) = match query.page_and_hits_per_page() {
+            Some((page, hits_per_page)) => {
+                let pagination = Pagination::new(page, hits_per_page, documents_ids.len());
+                let documents_ids = documents_ids
+                    .into_iter()
+                    .skip(pagination.offset())
+                    .take(pagination.limit())
+                    .collect();
+                (documents_ids, Some(pagination))
+            }
+            None => (documents_ids, None),
+        };
+
         let fields_ids_map = self.fields_ids_map(&rtxn).unwrap();
 
         let displayed_ids = self
@@ -177,12 +365,53 @@ impl Index {
             &displayed_ids,
         );
 
-        let stop_words = fst::Set::default();
+        // The stop words are ignored by the search, so they are neither highlighted nor reported in
+        // the matches.
+        let stop_words = self.stop_words(&rtxn)?;
         let mut config = AnalyzerConfig::default();
-        config.stop_words(&stop_words);
+        if let Some(ref stop_words) = stop_words {
+            config.stop_words(stop_words);
+        }
         let analyzer = Analyzer::new(config);
 
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let matcher = QueryMatcher {
+            synonyms: match query.q {
+                Some(ref q) => self.query_synonyms(&rtxn, q, &analyzer)?,
+                None => HashSet::new(),
+            },
+            matching_words,
+        };
+
+        let highlight_pre_tag = match query.highlight_pre_tag {
+            Some(tag) => tag,
+            None => self.highlight_pre_tag(&rtxn)?,
+        };
+        let highlight_post_tag = match query.highlight_post_tag {
+            Some(tag) => tag,
+            None => self.highlight_post_tag(&rtxn)?,
+        };
+        let crop_marker = match query.crop_marker {
+            Some(marker) => marker,
+            None => self.crop_marker(&rtxn)?,
+        };
+        let formatter = Formatter::new(
+            &analyzer,
+            (highlight_pre_tag, highlight_post_tag),
+            crop_marker,
+            query.crop_snippets,
+        );
+
+        let ranker = if query.show_ranking_score || query.show_ranking_score_details {
+            Some(Ranker::new(
+                &analyzer,
+                query.q.as_deref(),
+                self.criteria(&rtxn)?,
+                sort_criteria,
+                self.searchable_ids(&rtxn, &fields_ids_map)?,
+            ))
+        } else {
+            None
+        };
 
         let mut documents = Vec::new();
 
@@ -193,13 +422,13 @@ impl Index {
 
             let matches_info = query
                 .matches
-                .then(|| compute_matches(&matching_words, &document, &analyzer));
+                .then(|| compute_matches(&matcher, &document, &analyzer));
 
             let formatted = format_fields(
                 &fields_ids_map,
                 obkv,
                 &formatter,
-                &matching_words,
+                &matcher,
                 &formatted_options,
             )?;
 
@@ -207,17 +436,51 @@ impl Index {
                 insert_geo_distance(sort, &mut document);
             }
 
+            let (ranking_score, ranking_score_details) = match ranker {
+                Some(ref ranker) => {
+                    let (score, details) = ranker.score(&fields_ids_map, obkv);
+                    (
+                        query.show_ranking_score.then(|| score),
+                        query.show_ranking_score_details.then(|| details),
+                    )
+                }
+                None => (None, None),
+            };
+
             let hit = SearchHit {
                 document,
                 formatted,
                 matches_info,
+                ranking_score,
+                ranking_score_details,
             };
             documents.push(hit);
         }
 
-        let nb_hits = candidates.len();
+        let nb_hits = match pagination {
+            Some(ref pagination) => pagination.total_hits as u64,
+            None => candidates.len(),
+        };
 
-        let facets_distribution = match query.facets_distribution {
+        let facet_stats = match query.facets_distribution {
+            Some(ref fields) if query.show_facet_stats => {
+                let fields = self.facet_fields(&rtxn, fields)?;
+                Some(self.facet_stats(&rtxn, &fields, &candidates)?)
+            }
+            _ => None,
+        };
+
+        let (facets_distribution, exhaustive_facets_count) = match query.facets_distribution {
+            Some(ref fields) if query.exhaustive_facets_count => {
+                let (distribution, exhaustive) = self.exhaustive_facets_distribution(
+                    &rtxn,
+                    fields,
+                    &candidates,
+                    query.max_values_per_facet,
+                )?;
+
+                (Some(distribution), Some(exhaustive))
+            }
             Some(ref fields) => {
                 let mut facets_distribution = self.facets_distribution(&rtxn);
                 if fields.iter().all(|f| f != "*") {
@@ -225,26 +488,235 @@ impl Index {
                 }
                 let distribution = facets_distribution.candidates(candidates).execute()?;
 
-                Some(distribution)
+                (Some(distribution), Some(false))
             }
-            None => None,
+            None => (None, None),
         };
 
-        let exhaustive_facets_count = facets_distribution.as_ref().map(|_| false); // not implemented yet
+        let (limit, offset) = match pagination {
+            Some(ref pagination) => (pagination.limit(), pagination.offset()),
+            None => (query.limit, query.offset.unwrap_or_default()),
+        };
 
         let result = SearchResult {
-            exhaustive_nb_hits: false, // not implemented yet
+            exhaustive_nb_hits: pagination.is_some(),
             hits: documents,
             nb_hits,
             query: query.q.clone().unwrap_or_default(),
-            limit: query.limit,
-            offset: query.offset.unwrap_or_default(),
+            limit,
+            offset,
             processing_time_ms: before_search.elapsed().as_millis(),
+            pagination,
             facets_distribution,
             exhaustive_facets_count,
+            facet_stats,
         };
         Ok(result)
     }
+
+    /// Returns the words of the synonyms of the query, according to the synonyms of the index.
+    fn query_synonyms<A: AsRef<[u8]>>(
+        &self,
+        rtxn: &RoTxn,
+        query: &str,
+        analyzer: &Analyzer<A>,
+    ) -> Result<HashSet<String>> {
+        let synonyms = self.synonyms(rtxn)?;
+        if synonyms.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let analyzed = analyzer.analyze(query);
+        let words: Vec<_> = analyzed
+            .tokens()
+            .filter(|token| token.is_word())
+            .map(|token| token.text().to_string())
+            .collect();
+
+        let mut query_synonyms = HashSet::new();
+        for (synonym, alternatives) in synonyms {
+            // A synonym can be made of several words, that must follow each other in the query.
+            if !synonym.is_empty()
+                && words
+                    .windows(synonym.len())
+                    .any(|w| w == synonym.as_slice())
+            {
+                query_synonyms.extend(alternatives.into_iter().flatten());
+            }
+        }
+
+        Ok(query_synonyms)
+    }
+
+    /// Returns the ids of the searchable attributes, by order of importance. All the fields are
+    /// searchable when the searchable attributes are not set.
+    fn searchable_ids(&self, rtxn: &RoTxn, fields_ids_map: &FieldsIdsMap) -> Result<Vec<FieldId>> {
+        let ids = match self.searchable_fields(rtxn)? {
+            Some(fields) => fields
+                .into_iter()
+                .filter_map(|name| fields_ids_map.id(name))
+                .collect(),
+            None => fields_ids_map.iter().map(|(id, _)| id).collect(),
+        };
+
+        Ok(ids)
+    }
+
+    /// Returns the facets to compute for the requested `fields`: all the filterable attributes
+    /// for `*`. All the requested fields must be filterable.
+    fn facet_fields(&self, rtxn: &RoTxn, fields: &[String]) -> Result<BTreeSet<String>> {
+        let filterable_fields = self.filterable_fields(rtxn)?;
+        if fields.iter().any(|f| f == "*") {
+            return Ok(filterable_fields.into_iter().collect());
+        }
+
+        let invalid_facets: BTreeSet<_> = fields
+            .iter()
+            .filter(|f| !filterable_fields.contains(*f))
+            .cloned()
+            .collect();
+        if !invalid_facets.is_empty() {
+            let error = milli::UserError::InvalidFacetsDistribution {
+                invalid_facets_name: invalid_facets.into_iter().collect(),
+            };
+            return Err(IndexError::Milli(error.into()));
+        }
+
+        Ok(fields.iter().cloned().collect())
+    }
+
+    /// Computes the statistics of the numeric values of the `fields` facets in the `candidates`
+    /// documents, from the facet number database. The facets without any numeric value in the
+    /// candidates are left out.
+    fn facet_stats(
+        &self,
+        rtxn: &RoTxn,
+        fields: &BTreeSet<String>,
+        candidates: &RoaringBitmap,
+    ) -> Result<BTreeMap<String, FacetStats>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let mut stats = BTreeMap::new();
+
+        for name in fields {
+            let fid = match fields_ids_map.id(name) {
+                Some(fid) => fid,
+                None => continue,
+            };
+
+            // The level 0 of the facet number database holds an entry per value of the field, in
+            // ascending order, along with the ids of the documents having this value.
+            let range = (fid, 0, f64::MIN, f64::MIN)..=(fid, 0, f64::MAX, f64::MAX);
+            let mut min = None;
+            let mut max = 0.0;
+            let mut sum = 0.0;
+            let mut count = 0;
+            for entry in self.facet_id_f64_docids.range(rtxn, &range)? {
+                let ((_fid, _level, value, _right), docids) = entry?;
+                let matching = (&docids & candidates).len();
+                if matching == 0 {
+                    continue;
+                }
+
+                min.get_or_insert(value);
+                max = value;
+                sum += value * matching as f64;
+                count += matching;
+            }
+
+            if let Some(min) = min {
+                let avg = sum / count as f64;
+                stats.insert(name.clone(), FacetStats { min, max, sum, avg });
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Counts the values of the `fields` facets in all the `candidates` documents, from the facet
+    /// databases. The values are counted as milli indexes them: the strings are grouped by their
+    /// normalized form, and are displayed as they were first indexed. Only the first
+    /// `max_values_per_facet` values of a facet found in the candidates are counted, the numbers
+    /// in ascending order then the strings in lexicographic order, but the count of these values
+    /// is exact. Returns whether all the values of all the facets were counted.
+    pub(super) fn exhaustive_facets_distribution(
+        &self,
+        rtxn: &RoTxn,
+        fields: &[String],
+        candidates: &RoaringBitmap,
+        max_values_per_facet: usize,
+    ) -> Result<(BTreeMap<String, BTreeMap<String, u64>>, bool)> {
+        let fields = self.facet_fields(rtxn, fields)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let mut distribution = BTreeMap::new();
+        let mut exhaustive = true;
+
+        for name in fields {
+            let mut values_count = BTreeMap::new();
+            if let Some(fid) = fields_ids_map.id(&name) {
+                for value in self.facet_values(rtxn, fid)? {
+                    let FacetValue {
+                        original, docids, ..
+                    } = value?;
+                    let count = (&docids & candidates).len();
+                    if count == 0 {
+                        continue;
+                    }
+
+                    if values_count.len() == max_values_per_facet {
+                        exhaustive = false;
+                        break;
+                    }
+                    values_count.insert(original, count);
+                }
+            }
+            distribution.insert(name, values_count);
+        }
+
+        Ok((distribution, exhaustive))
+    }
+
+    /// Returns the values of a facet, read from the level 0 of the facet databases: the numbers
+    /// in ascending order, then the strings in lexicographic order of their normalized form.
+    pub(super) fn facet_values<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        fid: FieldId,
+    ) -> Result<impl Iterator<Item = heed::Result<FacetValue>> + 't> {
+        let range = (fid, 0, f64::MIN, f64::MIN)..=(fid, 0, f64::MAX, f64::MAX);
+        let numbers = self.facet_id_f64_docids.range(rtxn, &range)?.map(|entry| {
+            entry.map(|((_fid, _level, value, _right), docids)| FacetValue {
+                // The numbers are formatted as f64, like the filters parse them.
+                normalized: value.to_string(),
+                original: value.to_string(),
+                docids,
+            })
+        });
+
+        // The string keys are prefixed by the field id and the level, the empty string matches
+        // all the values of the level 0.
+        let strings = self
+            .facet_id_string_docids
+            .prefix_iter(rtxn, &(fid, ""))?
+            .map(|entry| {
+                entry.map(|((_fid, normalized), (original, docids))| FacetValue {
+                    normalized: normalized.to_string(),
+                    original: original.to_string(),
+                    docids,
+                })
+            });
+
+        Ok(numbers.chain(strings))
+    }
+}
+
+/// A value of a facet, as stored in the facet databases.
+pub(super) struct FacetValue {
+    /// The value as it is matched by the filters: the strings are trimmed and lowercased
+    pub normalized: String,
+    /// The value as it was first indexed
+    pub original: String,
+    /// The documents having this value
+    pub docids: RoaringBitmap,
 }
 
 fn insert_geo_distance(sorts: &[String], document: &mut Document) {
@@ -502,14 +974,47 @@ impl Matcher for MatchingWords {
     }
 }
 
+/// Matches the words of the query, along with the words of its synonyms which are matched
+/// entirely.
+struct QueryMatcher {
+    matching_words: MatchingWords,
+    synonyms: HashSet<String>,
+}
+
+impl Matcher for QueryMatcher {
+    fn matches(&self, w: &str) -> Option<usize> {
+        self.matching_words.matches(w).or_else(|| {
+            if self.synonyms.contains(w) {
+                Some(w.len())
+            } else {
+                None
+            }
+        })
+    }
+}
+
 struct Formatter<'a, A> {
     analyzer: &'a Analyzer<'a, A>,
     marks: (String, String),
+    /// Inserted where a cropped text was truncated
+    crop_marker: String,
+    /// The maximum number of snippets kept when cropping a text
+    crop_snippets: usize,
 }
 
 impl<'a, A: AsRef<[u8]>> Formatter<'a, A> {
-    pub fn new(analyzer: &'a Analyzer<'a, A>, marks: (String, String)) -> Self {
-        Self { analyzer, marks }
+    pub fn new(
+        analyzer: &'a Analyzer<'a, A>,
+        marks: (String, String),
+        crop_marker: String,
+        crop_snippets: usize,
+    ) -> Self {
+        Self {
+            analyzer,
+            marks,
+            crop_marker,
+            crop_snippets,
+        }
     }
 
     fn format_value(
@@ -572,84 +1077,171 @@ impl<'a, A: AsRef<[u8]>> Formatter<'a, A> {
         format_options: FormatOptions,
     ) -> String {
         let analyzed = self.analyzer.analyze(&s);
+        let tokens: Vec<_> = analyzed.reconstruct().collect();
 
-        let tokens: Box<dyn Iterator<Item = (&str, Token)>> = match format_options.crop {
-            Some(crop_len) => {
-                let mut buffer = Vec::new();
-                let mut tokens = analyzed.reconstruct().peekable();
-
-                while let Some((word, token)) =
-                    tokens.next_if(|(_, token)| matcher.matches(token.text()).is_none())
-                {
-                    buffer.push((word, token));
-                }
+        let snippets = match format_options.crop {
+            Some(crop_len) => crop_windows(&tokens, crop_len, self.crop_snippets, matcher),
+            None => vec![(0, tokens.len())],
+        };
 
-                match tokens.next() {
-                    Some(token) => {
-                        let mut total_len: usize = buffer.iter().map(|(word, _)| word.len()).sum();
-                        let before_iter = buffer.into_iter().skip_while(move |(word, _)| {
-                            total_len -= word.len();
-                            total_len >= crop_len
-                        });
+        let mut out = String::new();
+        let mut previous_end = 0;
+        for (start, end) in snippets {
+            if start > previous_end {
+                out.push_str(&self.crop_marker);
+            }
+            for (word, token) in &tokens[start..end] {
+                self.push_word(&mut out, word, token, matcher, format_options.highlight);
+            }
+            previous_end = end;
+        }
 
-                        let mut taken_after = 0;
-                        let after_iter = tokens.take_while(move |(word, _)| {
-                            let take = taken_after < crop_len;
-                            taken_after += word.chars().count();
-                            take
-                        });
+        if previous_end < tokens.len() {
+            out.push_str(&self.crop_marker);
+        }
 
-                        let iter = before_iter.chain(Some(token)).chain(after_iter);
+        out
+    }
 
-                        Box::new(iter)
+    fn push_word(
+        &self,
+        out: &mut String,
+        word: &str,
+        token: &Token,
+        matcher: &impl Matcher,
+        highlight: bool,
+    ) {
+        // Check if we need to do highlighting or computed matches before calling
+        // Matcher::match since the call is expensive.
+        if highlight && token.is_word() {
+            if let Some(length) = matcher.matches(token.text()) {
+                match word.get(..length).zip(word.get(length..)) {
+                    Some((head, tail)) => {
+                        out.push_str(&self.marks.0);
+                        out.push_str(head);
+                        out.push_str(&self.marks.1);
+                        out.push_str(tail);
                     }
-                    // If no word matches in the attribute
+                    // if we are in the middle of a character
+                    // or if all the word should be highlighted,
+                    // we highlight the complete word.
                     None => {
-                        let mut count = 0;
-                        let iter = buffer.into_iter().take_while(move |(word, _)| {
-                            let take = count < crop_len;
-                            count += word.len();
-                            take
-                        });
-
-                        Box::new(iter)
+                        out.push_str(&self.marks.0);
+                        out.push_str(word);
+                        out.push_str(&self.marks.1);
                     }
                 }
+                return;
             }
-            None => Box::new(analyzed.reconstruct()),
-        };
+        }
+        out.push_str(word);
+    }
+}
 
-        tokens.fold(String::new(), |mut out, (word, token)| {
-            // Check if we need to do highlighting or computed matches before calling
-            // Matcher::match since the call is expensive.
-            if format_options.highlight && token.is_word() {
-                if let Some(length) = matcher.matches(token.text()) {
-                    match word.get(..length).zip(word.get(length..)) {
-                        Some((head, tail)) => {
-                            out.push_str(&self.marks.0);
-                            out.push_str(head);
-                            out.push_str(&self.marks.1);
-                            out.push_str(tail);
-                        }
-                        // if we are in the middle of a character
-                        // or if all the word should be highlighted,
-                        // we highlight the complete word.
-                        None => {
-                            out.push_str(&self.marks.0);
-                            out.push_str(word);
-                            out.push_str(&self.marks.1);
-                        }
-                    }
-                    return out;
+/// Returns the bounds of the tokens kept when cropping a text, one pair of bounds by snippet, in
+/// the order of the text.
+///
+/// A snippet is a window of `crop_len` words. The windows containing the most distinct matched
+/// words are picked first, and among them the ones whose matches are the closest to their center.
+/// At most `max_snippets` windows are picked, they never overlap and all of them contain a match,
+/// unless nothing matches in the text: it is then cropped to its `crop_len` first words.
+fn crop_windows(
+    tokens: &[(&str, Token)],
+    crop_len: usize,
+    max_snippets: usize,
+    matcher: &impl Matcher,
+) -> Vec<(usize, usize)> {
+    // The position of each word in the tokens, along with its text if it matches the query. The
+    // stop words are never matched but they are counted in the crop length.
+    let words: Vec<(usize, Option<&str>)> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, token))| token.is_word() || token.is_stopword())
+        .map(|(position, (_, token))| {
+            let is_match = token.is_word() && matcher.matches(token.text()).is_some();
+            (position, is_match.then(|| token.text()))
+        })
+        .collect();
+
+    // A window always keeps at least the matched word.
+    let crop_len = crop_len.max(1);
+    if words.len() <= crop_len {
+        return vec![(0, tokens.len())];
+    }
+
+    let mut windows: Vec<_> = (0..=words.len() - crop_len)
+        .map(|start| {
+            let window = &words[start..start + crop_len];
+            let distinct_matches = window
+                .iter()
+                .filter_map(|(_, matched)| *matched)
+                .collect::<HashSet<_>>()
+                .len();
+
+            // The distance between the center of the window and the center of its matches,
+            // doubled to stay in integers.
+            let mut matches = window
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, matched))| matched.is_some())
+                .map(|(i, _)| i);
+            let off_center = match matches.next() {
+                Some(first) => {
+                    let last = matches.last().unwrap_or(first);
+                    let center = first + last;
+                    let window_center = crop_len - 1;
+                    center.max(window_center) - center.min(window_center)
                 }
-            }
-            out.push_str(word);
-            out
+                None => 0,
+            };
+
+            (start, distinct_matches, off_center)
         })
+        .collect();
+    windows.sort_by_key(|&(start, distinct_matches, off_center)| {
+        (Reverse(distinct_matches), off_center, start)
+    });
+
+    let max_snippets = max_snippets.max(1);
+    let mut snippets: Vec<(usize, usize)> = Vec::new();
+    for (start, distinct_matches, _) in windows {
+        // The windows without matches come last, only the first snippet can be one of them.
+        let no_more_matches = distinct_matches == 0 && !snippets.is_empty();
+        if snippets.len() == max_snippets || no_more_matches {
+            break;
+        }
+
+        let end = start + crop_len;
+        if snippets.iter().all(|&(s, e)| end <= s || start >= e) {
+            snippets.push((start, end));
+        }
     }
+    snippets.sort_unstable();
+
+    // Contiguous snippets are merged, so that the separators between them are kept.
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in snippets {
+        match merged.last_mut() {
+            Some((_, last_end)) if *last_end == start => *last_end = end,
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(start, end)| {
+            let start = if start == 0 { 0 } else { words[start].0 };
+            let end = if end == words.len() {
+                tokens.len()
+            } else {
+                words[end - 1].0 + 1
+            };
+            (start, end)
+        })
+        .collect()
 }
 
-fn parse_filter(facets: &Value) -> Result<Option<Filter>> {
+pub(super) fn parse_filter(facets: &Value) -> Result<Option<Filter>> {
     match facets {
         Value::String(expr) => {
             let condition = Filter::from_str(expr)?;
@@ -698,7 +1290,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let id = fields.insert("test").unwrap();
@@ -733,7 +1330,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -797,7 +1399,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -884,7 +1491,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -948,7 +1560,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -1009,7 +1626,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -1063,7 +1685,7 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(value["title"], "Harry Potter and");
+        assert_eq!(value["title"], "Harry Potter…");
         assert_eq!(value["author"], "J. K. Rowling");
     }
 
@@ -1073,7 +1695,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -1127,7 +1754,7 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(value["title"], "Harry Potter and the Half");
+        assert_eq!(value["title"], "Harry Potter and the Half-Blood Prince");
         assert_eq!(value["author"], "J. K. Rowling");
     }
 
@@ -1137,7 +1764,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -1191,7 +1823,7 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(value["title"], "Potter");
+        assert_eq!(value["title"], "…Potter…");
         assert_eq!(value["author"], "J. K. Rowling");
     }
 
@@ -1201,7 +1833,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -1255,7 +1892,7 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(value["title"], "Harry ");
+        assert_eq!(value["title"], "Harry Potter and the Half-Blood…");
         assert_eq!(value["author"], "J. K. Rowling");
     }
 
@@ -1265,7 +1902,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -1319,7 +1961,7 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(value["title"], " <em>and</em> ");
+        assert_eq!(value["title"], "…<em>and</em>…");
         assert_eq!(value["author"], "J. K. Rowling");
     }
 
@@ -1329,7 +1971,12 @@ mod test {
         let mut config = AnalyzerConfig::default();
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
 
         let mut fields = FieldsIdsMap::new();
         let title = fields.insert("title").unwrap();
@@ -1360,7 +2007,7 @@ mod test {
             title,
             FormatOptions {
                 highlight: true,
-                crop: Some(9),
+                crop: Some(3),
             },
         );
         formatted_options.insert(
@@ -1383,10 +2030,173 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(value["title"], "the Half-<em>Blo</em>od Prince");
+        assert_eq!(value["title"], "…Half-<em>Blo</em>od Prince");
         assert_eq!(value["author"], "J. K. Rowling");
     }
 
+    #[test]
+    fn formatted_with_highlight_ignores_stop_words() {
+        let stop_words = fst::Set::from_iter(vec!["the"]).unwrap();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
+
+        let mut fields = FieldsIdsMap::new();
+        let title = fields.insert("title").unwrap();
+
+        let mut buf = Vec::new();
+        let mut obkv = obkv::KvWriter::new(&mut buf);
+        obkv.insert(
+            title,
+            Value::String("The Hobbit".into()).to_string().as_bytes(),
+        )
+        .unwrap();
+        obkv.finish().unwrap();
+
+        let obkv = obkv::KvReader::new(&buf);
+
+        let mut formatted_options = BTreeMap::new();
+        formatted_options.insert(
+            title,
+            FormatOptions {
+                highlight: true,
+                crop: None,
+            },
+        );
+
+        let mut matching_words = BTreeMap::new();
+        matching_words.insert("the", Some(3));
+        matching_words.insert("hobbit", Some(6));
+
+        let value = format_fields(
+            &fields,
+            obkv,
+            &formatter,
+            &matching_words,
+            &formatted_options,
+        )
+        .unwrap();
+
+        assert_eq!(value["title"], "The <em>Hobbit</em>");
+    }
+
+    #[test]
+    fn formatted_with_crop_on_densest_window() {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            1,
+        );
+
+        let mut fields = FieldsIdsMap::new();
+        let title = fields.insert("title").unwrap();
+
+        let mut buf = Vec::new();
+        let mut obkv = obkv::KvWriter::new(&mut buf);
+        obkv.insert(
+            title,
+            Value::String("Potter is a wizard. The boy Harry Potter lives with his aunt.".into())
+                .to_string()
+                .as_bytes(),
+        )
+        .unwrap();
+        obkv.finish().unwrap();
+
+        let obkv = obkv::KvReader::new(&buf);
+
+        let mut formatted_options = BTreeMap::new();
+        formatted_options.insert(
+            title,
+            FormatOptions {
+                highlight: false,
+                crop: Some(2),
+            },
+        );
+
+        let mut matching_words = BTreeMap::new();
+        matching_words.insert("harry", Some(5));
+        matching_words.insert("potter", Some(6));
+
+        let value = format_fields(
+            &fields,
+            obkv,
+            &formatter,
+            &matching_words,
+            &formatted_options,
+        )
+        .unwrap();
+
+        assert_eq!(value["title"], "…Harry Potter…");
+    }
+
+    #[test]
+    fn formatted_with_crop_and_several_snippets() {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let formatter = Formatter::new(
+            &analyzer,
+            (String::from("<em>"), String::from("</em>")),
+            String::from("…"),
+            2,
+        );
+
+        let mut fields = FieldsIdsMap::new();
+        let title = fields.insert("title").unwrap();
+
+        let mut buf = Vec::new();
+        let mut obkv = obkv::KvWriter::new(&mut buf);
+        obkv.insert(
+            title,
+            Value::String("Potter is a wizard. The boy Harry Potter lives with his aunt.".into())
+                .to_string()
+                .as_bytes(),
+        )
+        .unwrap();
+        obkv.finish().unwrap();
+
+        let obkv = obkv::KvReader::new(&buf);
+
+        let mut formatted_options = BTreeMap::new();
+        formatted_options.insert(
+            title,
+            FormatOptions {
+                highlight: true,
+                crop: Some(2),
+            },
+        );
+
+        let mut matching_words = BTreeMap::new();
+        matching_words.insert("harry", Some(5));
+        matching_words.insert("potter", Some(6));
+
+        let value = format_fields(
+            &fields,
+            obkv,
+            &formatter,
+            &matching_words,
+            &formatted_options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value["title"],
+            "<em>Potter</em> is…<em>Harry</em> <em>Potter</em>…"
+        );
+    }
+
     #[test]
     fn test_compute_value_matches() {
         let text = "Call me Ishmael. Some years ago—never mind how long precisely—having little or no money in my purse, and nothing particular to interest me on shore, I thought I would sail about a little and see the watery part of the world.";
@@ -0,0 +1,305 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::SearchResult;
+
+/// An in-memory LRU cache of the search results, shared by all the indexes. The results of an
+/// index are dropped each time a write is committed to this index.
+pub struct SearchCache {
+    /// The maximum size of the cached results, in bytes. The cache is disabled when it is 0.
+    max_size: usize,
+    inner: Mutex<CacheInner>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    /// The cached results of each index, by normalized query
+    entries: HashMap<Uuid, HashMap<String, CacheEntry>>,
+    /// The keys of the entries, ordered by last use
+    recently_used: BTreeMap<u64, (Uuid, String)>,
+    /// The number of times the results of each index were invalidated. A result computed while
+    /// its index was written to is not cached.
+    generations: HashMap<Uuid, u64>,
+    clock: u64,
+    size: usize,
+}
+
+struct CacheEntry {
+    result: SearchResult,
+    size: usize,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// The size of the cached results, in bytes
+    pub size: u64,
+    pub max_size: u64,
+}
+
+impl SearchCache {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            inner: Mutex::new(CacheInner::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_size > 0
+    }
+
+    /// Returns the cached result of a query on an index, the query must be normalized.
+    pub fn get(&self, index_uuid: Uuid, query: &str) -> Option<SearchResult> {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        inner.clock += 1;
+
+        match inner
+            .entries
+            .get_mut(&index_uuid)
+            .and_then(|entries| entries.get_mut(query))
+        {
+            Some(entry) => {
+                let key = inner
+                    .recently_used
+                    .remove(&entry.last_used)
+                    .unwrap_or_else(|| (index_uuid, query.to_string()));
+                entry.last_used = inner.clock;
+                inner.recently_used.insert(inner.clock, key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.result.clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Returns the number of times the results of an index were invalidated, to be given back to
+    /// `insert` once the result is computed.
+    pub fn generation(&self, index_uuid: Uuid) -> u64 {
+        let inner = self.inner.lock();
+        inner
+            .generations
+            .get(&index_uuid)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Caches the result of a query, unless the index was written to since `generation` was
+    /// retrieved. The least recently used results are evicted to make room for it.
+    pub fn insert(&self, index_uuid: Uuid, query: String, generation: u64, result: &SearchResult) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        // The size of a result is estimated by the size of its serialization.
+        let size = match serde_json::to_vec(result) {
+            Ok(bytes) => bytes.len() + query.len(),
+            Err(_) => return,
+        };
+        if size > self.max_size {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        if inner
+            .generations
+            .get(&index_uuid)
+            .copied()
+            .unwrap_or_default()
+            != generation
+        {
+            return;
+        }
+
+        inner.remove(index_uuid, &query);
+        while inner.size + size > self.max_size {
+            match inner.recently_used.keys().next().copied() {
+                Some(last_used) => {
+                    let (uuid, query) = inner.recently_used[&last_used].clone();
+                    inner.remove(uuid, &query);
+                }
+                None => break,
+            }
+        }
+
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.size += size;
+        inner
+            .recently_used
+            .insert(last_used, (index_uuid, query.clone()));
+        inner.entries.entry(index_uuid).or_default().insert(
+            query,
+            CacheEntry {
+                result: result.clone(),
+                size,
+                last_used,
+            },
+        );
+    }
+
+    /// Drops all the cached results of an index.
+    pub fn invalidate(&self, index_uuid: Uuid) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        *inner.generations.entry(index_uuid).or_default() += 1;
+        if let Some(entries) = inner.entries.remove(&index_uuid) {
+            for entry in entries.values() {
+                inner.recently_used.remove(&entry.last_used);
+                inner.size -= entry.size;
+            }
+        }
+    }
+
+    pub fn stats(&self) -> SearchCacheStats {
+        SearchCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            size: self.inner.lock().size as u64,
+            max_size: self.max_size as u64,
+        }
+    }
+}
+
+impl CacheInner {
+    fn remove(&mut self, index_uuid: Uuid, query: &str) {
+        let entry = self
+            .entries
+            .get_mut(&index_uuid)
+            .and_then(|entries| entries.remove(query));
+
+        if let Some(entry) = entry {
+            self.recently_used.remove(&entry.last_used);
+            self.size -= entry.size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn result(nb_hits: u64) -> SearchResult {
+        SearchResult {
+            hits: Vec::new(),
+            nb_hits,
+            exhaustive_nb_hits: false,
+            query: String::new(),
+            limit: 20,
+            offset: 0,
+            processing_time_ms: 0,
+            facets_distribution: None,
+            exhaustive_facets_count: None,
+            facet_stats: None,
+            pagination: None,
+        }
+    }
+
+    #[test]
+    fn cache_hit_and_miss() {
+        let cache = SearchCache::new(10_000);
+        let uuid = Uuid::new_v4();
+
+        assert!(cache.get(uuid, "query").is_none());
+        cache.insert(
+            uuid,
+            "query".to_string(),
+            cache.generation(uuid),
+            &result(1),
+        );
+        assert_eq!(cache.get(uuid, "query").unwrap().nb_hits, 1);
+        assert!(cache.get(Uuid::new_v4(), "query").is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[test]
+    fn disabled_cache_stores_nothing() {
+        let cache = SearchCache::new(0);
+        let uuid = Uuid::new_v4();
+
+        cache.insert(
+            uuid,
+            "query".to_string(),
+            cache.generation(uuid),
+            &result(1),
+        );
+        assert!(cache.get(uuid, "query").is_none());
+        assert_eq!(cache.stats().size, 0);
+    }
+
+    #[test]
+    fn invalidation_drops_the_results_of_the_index() {
+        let cache = SearchCache::new(10_000);
+        let uuid = Uuid::new_v4();
+        let other = Uuid::new_v4();
+
+        cache.insert(
+            uuid,
+            "query".to_string(),
+            cache.generation(uuid),
+            &result(1),
+        );
+        cache.insert(
+            other,
+            "query".to_string(),
+            cache.generation(other),
+            &result(2),
+        );
+        cache.invalidate(uuid);
+
+        assert!(cache.get(uuid, "query").is_none());
+        assert_eq!(cache.get(other, "query").unwrap().nb_hits, 2);
+    }
+
+    #[test]
+    fn result_computed_during_a_write_is_not_cached() {
+        let cache = SearchCache::new(10_000);
+        let uuid = Uuid::new_v4();
+
+        let generation = cache.generation(uuid);
+        cache.invalidate(uuid);
+        cache.insert(uuid, "query".to_string(), generation, &result(1));
+
+        assert!(cache.get(uuid, "query").is_none());
+    }
+
+    #[test]
+    fn least_recently_used_results_are_evicted() {
+        let size = serde_json::to_vec(&result(1)).unwrap().len() + "a".len();
+        // Room for two results only.
+        let cache = SearchCache::new(size * 2 + 1);
+        let uuid = Uuid::new_v4();
+
+        cache.insert(uuid, "a".to_string(), 0, &result(1));
+        cache.insert(uuid, "b".to_string(), 0, &result(1));
+        // "a" becomes the most recently used result.
+        assert!(cache.get(uuid, "a").is_some());
+        cache.insert(uuid, "c".to_string(), 0, &result(1));
+
+        assert!(cache.get(uuid, "a").is_some());
+        assert!(cache.get(uuid, "b").is_none());
+        assert!(cache.get(uuid, "c").is_some());
+        assert!(cache.stats().size <= cache.stats().max_size);
+    }
+}
@@ -2,16 +2,22 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::marker::PhantomData;
 use std::num::NonZeroUsize;
 
+use heed::types::{SerdeJson, Str};
 use log::{debug, info, trace};
 use milli::documents::DocumentBatchReader;
 use milli::update::{
     DocumentAdditionResult, DocumentDeletionResult, IndexDocumentsMethod, Setting,
+    UpdateIndexingStep,
 };
 use serde::{Deserialize, Serialize, Serializer};
 use uuid::Uuid;
 
 use super::error::Result;
-use super::index::{Index, IndexMeta};
+use super::index::{
+    Index, IndexMeta, CROP_MARKER_KEY, HIGHLIGHT_POST_TAG_KEY, HIGHLIGHT_PRE_TAG_KEY,
+    MAX_TOTAL_HITS_KEY,
+};
+use crate::tasks::task::{DocumentDeletion, TaskResult, TransactionOperation};
 use crate::update_file_store::UpdateFileStore;
 
 fn serialize_with_wildcard<S>(
@@ -79,6 +85,18 @@ pub struct Settings<T> {
     #[serde(default, skip_serializing_if = "Setting::is_not_set")]
     #[cfg_attr(test, proptest(strategy = "test::setting_strategy()"))]
     pub distinct_attribute: Setting<String>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    #[cfg_attr(test, proptest(strategy = "test::setting_strategy()"))]
+    pub max_total_hits: Setting<usize>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    #[cfg_attr(test, proptest(strategy = "test::setting_strategy()"))]
+    pub highlight_pre_tag: Setting<String>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    #[cfg_attr(test, proptest(strategy = "test::setting_strategy()"))]
+    pub highlight_post_tag: Setting<String>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    #[cfg_attr(test, proptest(strategy = "test::setting_strategy()"))]
+    pub crop_marker: Setting<String>,
 
     #[serde(skip)]
     pub _kind: PhantomData<T>,
@@ -95,6 +113,10 @@ impl Settings<Checked> {
             stop_words: Setting::Reset,
             synonyms: Setting::Reset,
             distinct_attribute: Setting::Reset,
+            max_total_hits: Setting::Reset,
+            highlight_pre_tag: Setting::Reset,
+            highlight_post_tag: Setting::Reset,
+            crop_marker: Setting::Reset,
             _kind: PhantomData,
         }
     }
@@ -109,6 +131,10 @@ impl Settings<Checked> {
             stop_words,
             synonyms,
             distinct_attribute,
+            max_total_hits,
+            highlight_pre_tag,
+            highlight_post_tag,
+            crop_marker,
             ..
         } = self;
 
@@ -121,6 +147,10 @@ impl Settings<Checked> {
             stop_words,
             synonyms,
             distinct_attribute,
+            max_total_hits,
+            highlight_pre_tag,
+            highlight_post_tag,
+            crop_marker,
             _kind: PhantomData,
         }
     }
@@ -159,6 +189,10 @@ impl Settings<Unchecked> {
             stop_words: self.stop_words,
             synonyms: self.synonyms,
             distinct_attribute: self.distinct_attribute,
+            max_total_hits: self.max_total_hits,
+            highlight_pre_tag: self.highlight_pre_tag,
+            highlight_post_tag: self.highlight_post_tag,
+            crop_marker: self.crop_marker,
             _kind: PhantomData,
         }
     }
@@ -197,21 +231,46 @@ impl Index {
     /// Deletes `ids` from the index, and returns how many documents were deleted.
     pub fn delete_documents(&self, ids: &[String]) -> Result<DocumentDeletionResult> {
         let mut txn = self.write_txn()?;
+        let deleted = self.delete_documents_txn(&mut txn, ids)?;
+        txn.commit()?;
+        self.search_cache.invalidate(self.uuid);
+
+        Ok(deleted)
+    }
+
+    /// Deletes each group of `ids` in the same write transaction, and returns how many documents
+    /// were deleted for each group. If any deletion fails, nothing is committed.
+    pub fn delete_documents_batch(
+        &self,
+        batch: &[Vec<String>],
+    ) -> Result<Vec<DocumentDeletionResult>> {
+        let mut txn = self.write_txn()?;
+        let results = batch
+            .iter()
+            .map(|ids| self.delete_documents_txn(&mut txn, ids))
+            .collect::<Result<Vec<_>>>()?;
+        txn.commit()?;
+        self.search_cache.invalidate(self.uuid);
+
+        Ok(results)
+    }
+
+    fn delete_documents_txn<'a, 'b>(
+        &'a self,
+        txn: &mut heed::RwTxn<'a, 'b>,
+        ids: &[String],
+    ) -> Result<DocumentDeletionResult> {
         let mut builder = self
             .update_handler
             .update_builder()
-            .delete_documents(&mut txn, self)?;
+            .delete_documents(txn, self)?;
 
         // We ignore unexisting document ids
         ids.iter().for_each(|id| {
             builder.delete_external_id(id);
         });
 
-        let deleted = builder.execute()?;
-
-        txn.commit()?;
-
-        Ok(deleted)
+        Ok(builder.execute()?)
     }
 
     pub fn clear_documents(&self) -> Result<()> {
@@ -222,18 +281,49 @@ impl Index {
             .execute()?;
 
         txn.commit()?;
+        self.search_cache.invalidate(self.uuid);
 
         Ok(())
     }
 
+    /// Indexes the content of the update file. The `progress` callback is called with each
+    /// indexing step reported by the indexer.
     pub fn update_documents(
         &self,
         method: IndexDocumentsMethod,
         content_uuid: Uuid,
         primary_key: Option<String>,
         file_store: UpdateFileStore,
+        progress: impl Fn(UpdateIndexingStep) + Sync,
     ) -> Result<DocumentAdditionResult> {
-        trace!("performing document addition");
+        let mut results = self.update_documents_batch(
+            method,
+            &[content_uuid],
+            primary_key,
+            file_store,
+            |_, step| progress(step),
+        )?;
+        Ok(results.remove(0))
+    }
+
+    /// Indexes the content of all the update files in `content_uuids` in a single write
+    /// transaction, and returns the result of each addition in the same order. If any addition
+    /// fails, nothing is committed.
+    ///
+    /// The `progress` callback is called with the position of the addition in `content_uuids`
+    /// and each indexing step reported by the indexer.
+    pub fn update_documents_batch(
+        &self,
+        method: IndexDocumentsMethod,
+        content_uuids: &[Uuid],
+        primary_key: Option<String>,
+        file_store: UpdateFileStore,
+        progress: impl Fn(usize, UpdateIndexingStep) + Sync,
+    ) -> Result<Vec<DocumentAdditionResult>> {
+        trace!(
+            "performing document addition of {} update(s)",
+            content_uuids.len()
+        );
         let mut txn = self.write_txn()?;
 
         if let Some(primary_key) = primary_key {
@@ -242,20 +332,46 @@ impl Index {
             }
         }
 
-        let indexing_callback = |indexing_step| debug!("update: {:?}", indexing_step);
+        let mut results = Vec::with_capacity(content_uuids.len());
+        for (position, content_uuid) in content_uuids.iter().enumerate() {
+            let addition =
+                self.index_documents_txn(&mut txn, method, *content_uuid, &file_store, |step| {
+                    progress(position, step)
+                })?;
+            results.push(addition);
+        }
+
+        txn.commit()?;
+        self.search_cache.invalidate(self.uuid);
+
+        Ok(results)
+    }
+
+    fn index_documents_txn<'a, 'b>(
+        &'a self,
+        txn: &mut heed::RwTxn<'a, 'b>,
+        method: IndexDocumentsMethod,
+        content_uuid: Uuid,
+        file_store: &UpdateFileStore,
+        progress: impl Fn(UpdateIndexingStep) + Sync,
+    ) -> Result<DocumentAdditionResult> {
+        let indexing_callback = |indexing_step| {
+            debug!("update: {:?}", indexing_step);
+            progress(indexing_step);
+        };
 
-        let content_file = file_store.get_update(content_uuid).unwrap();
-        let reader = DocumentBatchReader::from_reader(content_file).unwrap();
+        // The update file can be missing when the task was cancelled, or its payload deleted, while
+        // it was processed: only this task fails then.
+        let content_file = file_store.get_update(content_uuid)?;
+        let reader = DocumentBatchReader::from_reader(content_file)?;
 
         let mut builder = self
             .update_handler
             .update_builder()
-            .index_documents(&mut txn, self);
+            .index_documents(txn, self);
         builder.index_documents_method(method);
         let addition = builder.execute(reader, indexing_callback)?;
 
-        txn.commit()?;
-
         info!("document addition done: {:?}", addition);
 
         Ok(addition)
@@ -264,18 +380,91 @@ impl Index {
     pub fn update_settings(&self, settings: &Settings<Checked>) -> Result<()> {
         // We must use the write transaction of the update here.
         let mut txn = self.write_txn()?;
-        let mut builder = self
-            .update_handler
-            .update_builder()
-            .settings(&mut txn, self);
+        self.update_settings_txn(&mut txn, settings)?;
+        txn.commit()?;
+        self.search_cache.invalidate(self.uuid);
+
+        Ok(())
+    }
+
+    fn update_settings_txn<'a, 'b>(
+        &'a self,
+        txn: &mut heed::RwTxn<'a, 'b>,
+        settings: &Settings<Checked>,
+    ) -> Result<()> {
+        let mut builder = self.update_handler.update_builder().settings(txn, self);
 
         apply_settings_to_builder(settings, &mut builder);
 
         builder.execute(|indexing_step| debug!("update: {:?}", indexing_step))?;
+        apply_meilisearch_settings(self, txn, settings)?;
+
+        Ok(())
+    }
+
+    /// Applies the operations of a transaction in order, in a single write transaction, and
+    /// returns the result of each operation. If any operation fails, nothing is committed.
+    pub fn apply_transaction(
+        &self,
+        operations: &[TransactionOperation],
+        file_store: UpdateFileStore,
+    ) -> Result<Vec<TaskResult>> {
+        trace!(
+            "performing a transaction of {} operation(s)",
+            operations.len()
+        );
+        let mut txn = self.write_txn()?;
+
+        let mut results = Vec::with_capacity(operations.len());
+        for operation in operations {
+            let result = match operation {
+                TransactionOperation::DocumentAddition {
+                    content_uuid,
+                    merge_strategy,
+                    primary_key,
+                    ..
+                } => {
+                    if let Some(primary_key) = primary_key {
+                        if self.primary_key(&txn)?.is_none() {
+                            self.update_primary_key_txn(&mut txn, primary_key.clone())?;
+                        }
+                    }
+
+                    self.index_documents_txn(
+                        &mut txn,
+                        *merge_strategy,
+                        *content_uuid,
+                        &file_store,
+                        |_| (),
+                    )?
+                    .into()
+                }
+                TransactionOperation::DocumentDeletion(DocumentDeletion::Ids(ids)) => {
+                    let DocumentDeletionResult {
+                        deleted_documents, ..
+                    } = self.delete_documents_txn(&mut txn, ids)?;
+                    TaskResult::DocumentDeletion { deleted_documents }
+                }
+                TransactionOperation::DocumentDeletion(DocumentDeletion::Clear) => {
+                    let deleted_documents = self.number_of_documents(&txn)?;
+                    self.update_handler
+                        .update_builder()
+                        .clear_documents(&mut txn, self)
+                        .execute()?;
+                    TaskResult::ClearAll { deleted_documents }
+                }
+                TransactionOperation::SettingsUpdate { settings } => {
+                    self.update_settings_txn(&mut txn, &settings.clone().check())?;
+                    TaskResult::Other
+                }
+            };
+            results.push(result);
+        }
 
         txn.commit()?;
+        self.search_cache.invalidate(self.uuid);
 
-        Ok(())
+        Ok(results)
     }
 }
 
@@ -334,6 +523,48 @@ pub fn apply_settings_to_builder(
     }
 }
 
+/// Applies the settings milli doesn't know about, they are stored in the main database of the
+/// index.
+pub fn apply_meilisearch_settings(
+    index: &milli::Index,
+    txn: &mut heed::RwTxn,
+    settings: &Settings<Checked>,
+) -> heed::Result<()> {
+    apply_meilisearch_setting(index, txn, MAX_TOTAL_HITS_KEY, &settings.max_total_hits)?;
+    apply_meilisearch_setting(
+        index,
+        txn,
+        HIGHLIGHT_PRE_TAG_KEY,
+        &settings.highlight_pre_tag,
+    )?;
+    apply_meilisearch_setting(
+        index,
+        txn,
+        HIGHLIGHT_POST_TAG_KEY,
+        &settings.highlight_post_tag,
+    )?;
+    apply_meilisearch_setting(index, txn, CROP_MARKER_KEY, &settings.crop_marker)?;
+
+    Ok(())
+}
+
+fn apply_meilisearch_setting<T: Serialize>(
+    index: &milli::Index,
+    txn: &mut heed::RwTxn,
+    key: &str,
+    setting: &Setting<T>,
+) -> heed::Result<()> {
+    match setting {
+        Setting::Set(value) => index.main.put::<_, Str, SerdeJson<T>>(txn, key, value)?,
+        Setting::Reset => {
+            index.main.delete::<_, Str>(txn, key)?;
+        }
+        Setting::NotSet => (),
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     use proptest::prelude::*;
@@ -360,6 +591,10 @@ pub(crate) mod test {
             stop_words: Setting::NotSet,
             synonyms: Setting::NotSet,
             distinct_attribute: Setting::NotSet,
+            max_total_hits: Setting::NotSet,
+            highlight_pre_tag: Setting::NotSet,
+            highlight_post_tag: Setting::NotSet,
+            crop_marker: Setting::NotSet,
             _kind: PhantomData::<Unchecked>,
         };
 
@@ -381,6 +616,10 @@ pub(crate) mod test {
             stop_words: Setting::NotSet,
             synonyms: Setting::NotSet,
             distinct_attribute: Setting::NotSet,
+            max_total_hits: Setting::NotSet,
+            highlight_pre_tag: Setting::NotSet,
+            highlight_post_tag: Setting::NotSet,
+            crop_marker: Setting::NotSet,
             _kind: PhantomData::<Unchecked>,
         };
 
@@ -75,6 +75,7 @@ impl From<Update> for TaskContent {
                 // document count is unknown for legacy updates
                 documents_count: 0,
                 allow_index_creation: true,
+                retry_of: None,
             },
             Update::Settings(settings) => TaskContent::SettingsUpdate {
                 settings,
@@ -186,6 +187,7 @@ impl From<(UpdateStatus, String, TaskId)> for Task {
             index_uid: IndexUid::new(uid).unwrap(),
             content: TaskContent::IndexDeletion,
             events: Vec::new(),
+            not_before: None,
         };
 
         match update {
@@ -35,9 +35,11 @@ pub enum IndexControllerError {
     MissingPayload(DocumentAdditionFormat),
     #[error("The provided payload reached the size limit.")]
     PayloadTooLarge,
+    #[error("A transaction must contain at least one operation.")]
+    EmptyTransaction,
 }
 
-internal_error!(IndexControllerError: JoinError, UpdateFileStoreError);
+internal_error!(IndexControllerError: JoinError, UpdateFileStoreError, serde_json::Error);
 
 impl From<actix_web::error::PayloadError> for IndexControllerError {
     fn from(other: actix_web::error::PayloadError) -> Self {
@@ -59,6 +61,7 @@ impl ErrorCode for IndexControllerError {
             IndexControllerError::DocumentFormatError(e) => e.error_code(),
             IndexControllerError::MissingPayload(_) => Code::MissingPayload,
             IndexControllerError::PayloadTooLarge => Code::PayloadTooLarge,
+            IndexControllerError::EmptyTransaction => Code::InvalidTransaction,
             IndexControllerError::DumpError(e) => e.error_code(),
         }
     }
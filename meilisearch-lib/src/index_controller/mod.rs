@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::Hasher;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -12,21 +13,29 @@ use futures::Stream;
 use futures::StreamExt;
 use milli::update::IndexDocumentsMethod;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use siphasher::sip::SipHasher13;
+use tokio::sync::{broadcast, mpsc};
 use tokio::task::spawn_blocking;
 use tokio::time::sleep;
 use uuid::Uuid;
 
 use crate::document_formats::{read_csv, read_json, read_ndjson};
+use crate::index::search_cache::{SearchCache, SearchCacheStats};
 use crate::index::{
-    Checked, Document, IndexMeta, IndexStats, SearchQuery, SearchResult, Settings, Unchecked,
+    Checked, Document, FacetSearchQuery, FacetSearchResult, IndexMeta, IndexStats, SearchQuery,
+    SearchResult, Settings, Unchecked,
 };
 use crate::index_controller::dump_actor::{load_dump, DumpActor, DumpActorHandleImpl};
-use crate::options::IndexerOpts;
+use crate::options::{IndexerOpts, SchedulerConfig, TaskRetentionConfig, WebhookConfig};
 use crate::snapshot::{load_snapshot, SnapshotService};
+use crate::tasks::batch::{BatchId, BatchInfo};
 use crate::tasks::create_task_store;
 use crate::tasks::error::TaskError;
-use crate::tasks::task::{DocumentDeletion, Task, TaskContent, TaskId};
+use crate::tasks::retention::RetentionService;
+use crate::tasks::task::{
+    DocumentDeletion, IdempotencyKey, Task, TaskContent, TaskId, TaskProgress, TransactionOperation,
+};
+use crate::tasks::webhook::{Webhook, Webhooks};
 use crate::tasks::{TaskFilter, TaskStore};
 use error::Result;
 
@@ -70,6 +79,9 @@ pub struct IndexController<U, I> {
     task_store: TaskStore,
     dump_handle: dump_actor::DumpActorHandleImpl,
     update_file_store: UpdateFileStore,
+    task_retention: TaskRetentionConfig,
+    webhooks: Webhooks,
+    search_cache: Arc<SearchCache>,
 }
 
 /// Need a custom implementation for clone because deriving require that U and I are clone.
@@ -80,6 +92,9 @@ impl<U, I> Clone for IndexController<U, I> {
             task_store: self.task_store.clone(),
             dump_handle: self.dump_handle.clone(),
             update_file_store: self.update_file_store.clone(),
+            task_retention: self.task_retention.clone(),
+            webhooks: self.webhooks.clone(),
+            search_cache: self.search_cache.clone(),
         }
     }
 }
@@ -106,7 +121,9 @@ impl fmt::Display for DocumentAdditionFormat {
 pub struct Stats {
     pub database_size: u64,
     pub last_update: Option<DateTime<Utc>>,
+    pub is_indexing_paused: bool,
     pub indexes: BTreeMap<String, IndexStats>,
+    pub search_cache: SearchCacheStats,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -136,6 +153,24 @@ pub enum Update {
     UpdateIndex {
         primary_key: Option<String>,
     },
+    /// Several updates of the same index, that are applied all together or not at all.
+    Transaction {
+        operations: Vec<TransactionUpdate>,
+        allow_index_creation: bool,
+    },
+}
+
+/// An operation of an `Update::Transaction`.
+#[derive(Debug)]
+pub enum TransactionUpdate {
+    DocumentAddition {
+        documents: Vec<Document>,
+        primary_key: Option<String>,
+        method: IndexDocumentsMethod,
+    },
+    DeleteDocuments(Vec<String>),
+    ClearDocuments,
+    Settings(Settings<Unchecked>),
 }
 
 #[derive(Default, Debug)]
@@ -150,6 +185,10 @@ pub struct IndexControllerBuilder {
     schedule_snapshot: bool,
     dump_src: Option<PathBuf>,
     dump_dst: Option<PathBuf>,
+    scheduler_config: Option<SchedulerConfig>,
+    task_retention_config: Option<TaskRetentionConfig>,
+    webhook_config: Option<WebhookConfig>,
+    search_cache_size: Option<usize>,
 }
 
 impl IndexControllerBuilder {
@@ -204,16 +243,22 @@ impl IndexControllerBuilder {
         // Create or overwrite the version file for this DB
         versioning::create_version_file(db_path.as_ref())?;
 
+        let search_cache = Arc::new(SearchCache::new(self.search_cache_size.unwrap_or_default()));
         let index_resolver = Arc::new(create_index_resolver(
             &db_path,
             index_size,
             &indexer_options,
+            search_cache.clone(),
             meta_env.clone(),
             update_file_store.clone(),
         )?);
 
-        let task_store =
-            create_task_store(meta_env, index_resolver.clone()).map_err(|e| anyhow::anyhow!(e))?;
+        let task_store = create_task_store(
+            meta_env,
+            index_resolver.clone(),
+            self.scheduler_config.unwrap_or_default(),
+        )
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         let dump_path = self
             .dump_dst
@@ -256,11 +301,27 @@ impl IndexControllerBuilder {
             tokio::task::spawn(snapshot_service.run());
         }
 
+        // The retention service always runs, since it also deletes the update files of the
+        // failed document additions once their grace period is over.
+        let task_retention = self.task_retention_config.unwrap_or_default();
+        let retention_service = RetentionService {
+            task_store: task_store.clone(),
+            update_file_store: update_file_store.clone(),
+            config: task_retention.clone(),
+        };
+        tokio::task::spawn(retention_service.run());
+
+        let webhooks = Webhooks::new(&self.webhook_config.unwrap_or_default())
+            .map_err(|e| anyhow::anyhow!(e))?;
+
         Ok(IndexController {
             index_resolver,
             task_store,
             dump_handle,
             update_file_store,
+            task_retention,
+            webhooks,
+            search_cache,
         })
     }
 
@@ -325,6 +386,34 @@ impl IndexControllerBuilder {
         self.schedule_snapshot = true;
         self
     }
+
+    /// Set the index controller builder's scheduler config.
+    pub fn set_scheduler_config(&mut self, scheduler_config: SchedulerConfig) -> &mut Self {
+        self.scheduler_config = Some(scheduler_config);
+        self
+    }
+
+    /// Set the index controller builder's task retention config.
+    pub fn set_task_retention_config(
+        &mut self,
+        task_retention_config: TaskRetentionConfig,
+    ) -> &mut Self {
+        self.task_retention_config = Some(task_retention_config);
+        self
+    }
+
+    /// Set the index controller builder's webhook config.
+    pub fn set_webhook_config(&mut self, webhook_config: WebhookConfig) -> &mut Self {
+        self.webhook_config = Some(webhook_config);
+        self
+    }
+
+    /// Set the index controller builder's search cache size, in bytes. The cache is disabled
+    /// when it is 0.
+    pub fn set_search_cache_size(&mut self, search_cache_size: usize) -> &mut Self {
+        self.search_cache_size = Some(search_cache_size);
+        self
+    }
 }
 
 impl<U, I> IndexController<U, I>
@@ -336,8 +425,23 @@ where
         IndexControllerBuilder::default()
     }
 
-    pub async fn register_update(&self, uid: String, update: Update) -> Result<Task> {
+    /// Registers the update as a new task. When `not_before` is set, the task is only processed
+    /// once that date is reached.
+    ///
+    /// When a task was registered with the same `idempotency_key` during the configured retention
+    /// period, this task is returned instead, and the update is discarded. The key can't be reused
+    /// for an update of another index, or with another content.
+    pub async fn register_update(
+        &self,
+        uid: String,
+        update: Update,
+        not_before: Option<DateTime<Utc>>,
+        idempotency_key: Option<String>,
+    ) -> Result<Task> {
         let uid = IndexUid::new(uid)?;
+        // Hashes the documents of the update, the rest of the update is hashed from its content.
+        let mut payload_hasher = SipHasher13::new();
+
         let content = match update {
             Update::DeleteDocuments(ids) => {
                 TaskContent::DocumentDeletion(DocumentDeletion::Ids(ids))
@@ -364,25 +468,8 @@ where
                     let bytes = bytes?;
                     buffer.extend_from_slice(&bytes);
                 }
-                let (content_uuid, mut update_file) = self.update_file_store.new_update()?;
-                let documents_count = tokio::task::spawn_blocking(move || -> Result<_> {
-                    // check if the payload is empty, and return an error
-                    if buffer.is_empty() {
-                        return Err(IndexControllerError::MissingPayload(format));
-                    }
-
-                    let reader = Cursor::new(buffer);
-                    let count = match format {
-                        DocumentAdditionFormat::Json => read_json(reader, &mut *update_file)?,
-                        DocumentAdditionFormat::Csv => read_csv(reader, &mut *update_file)?,
-                        DocumentAdditionFormat::Ndjson => read_ndjson(reader, &mut *update_file)?,
-                    };
-
-                    update_file.persist()?;
-
-                    Ok(count)
-                })
-                .await??;
+                payload_hasher.write(&buffer);
+                let (content_uuid, documents_count) = self.persist_payload(buffer, format).await?;
 
                 TaskContent::DocumentAddition {
                     content_uuid,
@@ -390,23 +477,312 @@ where
                     primary_key,
                     documents_count,
                     allow_index_creation,
+                    retry_of: None,
                 }
             }
             Update::DeleteIndex => TaskContent::IndexDeletion,
             Update::CreateIndex { primary_key } => TaskContent::IndexCreation { primary_key },
             Update::UpdateIndex { primary_key } => TaskContent::IndexUpdate { primary_key },
+            Update::Transaction {
+                operations,
+                allow_index_creation,
+            } => TaskContent::Transaction {
+                operations: self
+                    .register_transaction_operations(operations, &mut payload_hasher)
+                    .await?,
+                allow_index_creation,
+            },
         };
 
-        let task = self.task_store.register(uid, content).await?;
+        let content_uuids = content.content_uuids();
+        let idempotency_key = idempotency_key.map(|key| IdempotencyKey {
+            key,
+            expires_at: Utc::now()
+                + chrono::Duration::seconds(
+                    self.task_retention.idempotency_key_retention_sec as i64,
+                ),
+            fingerprint: update_fingerprint(&content, payload_hasher),
+        });
+        let task = match self
+            .task_store
+            .register(uid, content, not_before, idempotency_key)
+            .await
+        {
+            Ok(task) => task,
+            Err(e) => {
+                self.delete_update_files(content_uuids).await;
+                return Err(e.into());
+            }
+        };
+
+        // A task was already registered with the same idempotency key, so the payload of this
+        // request is never used.
+        if task.content_uuids() != content_uuids {
+            self.delete_update_files(content_uuids).await;
+        }
 
         Ok(task)
     }
 
+    /// Writes the payload of a document addition to a new update file. Returns the uuid of the
+    /// update file and the number of documents in the payload.
+    async fn persist_payload(
+        &self,
+        buffer: Vec<u8>,
+        format: DocumentAdditionFormat,
+    ) -> Result<(Uuid, usize)> {
+        let (content_uuid, mut update_file) = self.update_file_store.new_update()?;
+        let documents_count = tokio::task::spawn_blocking(move || -> Result<_> {
+            // check if the payload is empty, and return an error
+            if buffer.is_empty() {
+                return Err(IndexControllerError::MissingPayload(format));
+            }
+
+            let reader = Cursor::new(buffer);
+            let count = match format {
+                DocumentAdditionFormat::Json => read_json(reader, &mut *update_file)?,
+                DocumentAdditionFormat::Csv => read_csv(reader, &mut *update_file)?,
+                DocumentAdditionFormat::Ndjson => read_ndjson(reader, &mut *update_file)?,
+            };
+
+            update_file.persist()?;
+
+            Ok(count)
+        })
+        .await??;
+
+        Ok((content_uuid, documents_count))
+    }
+
+    /// Converts the operations of a transaction, writing the documents of each addition to an
+    /// update file. If an operation is invalid, the update files that were already written are
+    /// deleted.
+    async fn register_transaction_operations(
+        &self,
+        operations: Vec<TransactionUpdate>,
+        payload_hasher: &mut impl Hasher,
+    ) -> Result<Vec<TransactionOperation>> {
+        if operations.is_empty() {
+            return Err(IndexControllerError::EmptyTransaction);
+        }
+
+        let mut registered = Vec::with_capacity(operations.len());
+        let mut content_uuids = Vec::new();
+        for operation in operations {
+            let operation = match operation {
+                TransactionUpdate::DocumentAddition {
+                    documents,
+                    primary_key,
+                    method,
+                } => {
+                    let persisted = match serde_json::to_vec(&documents) {
+                        Ok(buffer) => {
+                            payload_hasher.write(&buffer);
+                            self.persist_payload(buffer, DocumentAdditionFormat::Json)
+                                .await
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+
+                    let (content_uuid, documents_count) = match persisted {
+                        Ok(persisted) => persisted,
+                        Err(e) => {
+                            self.delete_update_files(content_uuids).await;
+                            return Err(e);
+                        }
+                    };
+                    content_uuids.push(content_uuid);
+
+                    TransactionOperation::DocumentAddition {
+                        content_uuid,
+                        merge_strategy: method,
+                        primary_key,
+                        documents_count,
+                    }
+                }
+                TransactionUpdate::DeleteDocuments(ids) => {
+                    TransactionOperation::DocumentDeletion(DocumentDeletion::Ids(ids))
+                }
+                TransactionUpdate::ClearDocuments => {
+                    TransactionOperation::DocumentDeletion(DocumentDeletion::Clear)
+                }
+                TransactionUpdate::Settings(settings) => {
+                    TransactionOperation::SettingsUpdate { settings }
+                }
+            };
+            registered.push(operation);
+        }
+
+        Ok(registered)
+    }
+
+    async fn delete_update_files(&self, content_uuids: Vec<Uuid>) {
+        for content_uuid in content_uuids {
+            if let Err(e) = self.update_file_store.delete(content_uuid).await {
+                log::error!("error deleting update file: {}", e);
+            }
+        }
+    }
+
     pub async fn get_task(&self, id: TaskId, filter: Option<TaskFilter>) -> Result<Task> {
         let task = self.task_store.get_task(id, filter).await?;
         Ok(task)
     }
 
+    /// Returns the indexing progress of a task, if it is a document addition that is processing.
+    pub fn get_task_progress(&self, id: TaskId) -> Option<TaskProgress> {
+        self.index_resolver.get_task_progress(id)
+    }
+
+    /// Cancels a pending task and deletes its update file, if it has one.
+    pub async fn cancel_task(&self, id: TaskId, filter: Option<TaskFilter>) -> Result<Task> {
+        let task = self.task_store.cancel_task(id, filter).await?;
+        self.delete_task_content(&task).await;
+
+        Ok(task)
+    }
+
+    /// Cancels all the pending tasks of an index and deletes their update files.
+    pub async fn cancel_index_tasks(&self, index_uid: String) -> Result<Vec<Task>> {
+        let tasks = self.task_store.cancel_index_tasks(index_uid).await?;
+        for task in &tasks {
+            self.delete_task_content(task).await;
+        }
+
+        Ok(tasks)
+    }
+
+    /// Enqueues a new document addition reusing the update file of a failed document addition.
+    /// The update file of a failed task is only kept for the configured grace period.
+    pub async fn retry_task(&self, id: TaskId, filter: Option<TaskFilter>) -> Result<Task> {
+        let task = self.task_store.get_task(id, filter).await?;
+        let content_uuid = match task.get_content_uuid() {
+            Some(content_uuid) if task.is_failed_document_addition() => content_uuid,
+            _ => return Err(TaskError::TaskNotRetryable(id).into()),
+        };
+
+        if self.update_file_store.get_update(content_uuid).is_err() {
+            return Err(TaskError::TaskPayloadDeleted(id).into());
+        }
+
+        let content = match task.content {
+            TaskContent::DocumentAddition {
+                merge_strategy,
+                primary_key,
+                documents_count,
+                allow_index_creation,
+                ..
+            } => TaskContent::DocumentAddition {
+                content_uuid,
+                merge_strategy,
+                primary_key,
+                documents_count,
+                allow_index_creation,
+                retry_of: Some(id),
+            },
+            _ => unreachable!("only document additions can be retried"),
+        };
+        // The task store checks that the task isn't already being retried.
+        let task = self
+            .task_store
+            .register_retry(task.index_uid, content)
+            .await?;
+
+        Ok(task)
+    }
+
+    /// Stops the processing of the tasks once the processing batches are finished. The tasks are
+    /// still registered while the processing is paused, and the paused state survives a restart.
+    pub async fn pause_tasks(&self) -> Result<()> {
+        self.task_store.set_paused(true).await?;
+        Ok(())
+    }
+
+    /// Resumes the processing of the tasks.
+    pub async fn resume_tasks(&self) -> Result<()> {
+        self.task_store.set_paused(false).await?;
+        Ok(())
+    }
+
+    pub fn is_indexing_paused(&self) -> bool {
+        self.task_store.is_paused()
+    }
+
+    /// Deletes the update files of a cancelled task. The update file of a retry is shared with the
+    /// failed task it retries, it is kept so that this task can still be retried.
+    async fn delete_task_content(&self, task: &Task) {
+        if matches!(
+            task.content,
+            TaskContent::DocumentAddition {
+                retry_of: Some(_),
+                ..
+            }
+        ) {
+            return;
+        }
+
+        self.delete_update_files(task.content_uuids()).await;
+    }
+
+    /// Deletes the finished tasks that don't match the retention policy. The `max_age_sec` and
+    /// `max_count` parameters override the configured retention policy. Returns the number of
+    /// deleted tasks.
+    pub async fn prune_tasks(
+        &self,
+        max_age_sec: Option<u64>,
+        max_count: Option<usize>,
+    ) -> Result<usize> {
+        let mut config = self.task_retention.clone();
+        if max_age_sec.is_some() {
+            config.task_retention_max_age_sec = max_age_sec;
+        }
+        if max_count.is_some() {
+            config.task_retention_max_count = max_count;
+        }
+
+        let deleted = self
+            .task_store
+            .prune(config, self.update_file_store.clone())
+            .await?;
+
+        Ok(deleted)
+    }
+
+    /// Returns a receiver that gets the state of a task each time it gains a new event.
+    pub fn subscribe_tasks(&self) -> broadcast::Receiver<Task> {
+        self.task_store.subscribe()
+    }
+
+    /// Returns a receiver that gets each task once it is finished. The finished tasks are queued
+    /// until they are read, so none of them is missed.
+    pub fn finished_tasks(&self) -> mpsc::UnboundedReceiver<Task> {
+        self.task_store.finished_tasks()
+    }
+
+    pub async fn list_webhooks(&self) -> Vec<Webhook> {
+        self.webhooks.list().await
+    }
+
+    pub async fn register_webhook(
+        &self,
+        url: String,
+        headers: BTreeMap<String, String>,
+    ) -> Result<Webhook> {
+        let webhook = self.webhooks.register(url, headers).await?;
+        Ok(webhook)
+    }
+
+    pub async fn delete_webhook(&self, uid: String) -> Result<Webhook> {
+        let webhook = self.webhooks.delete(uid).await?;
+        Ok(webhook)
+    }
+
+    /// Sends the payload to each registered webhook. The delivery happens in the background.
+    pub async fn notify_webhooks(&self, payload: &impl Serialize) -> Result<()> {
+        self.webhooks.notify(payload).await?;
+        Ok(())
+    }
+
     pub async fn get_index_task(&self, index_uid: String, task_id: TaskId) -> Result<Task> {
         let creation_task_id = self
             .index_resolver
@@ -423,17 +799,37 @@ where
         Ok(task)
     }
 
+    /// Returns the `limit` most recent tasks matching the `filter`, starting from the task with
+    /// id `from`.
     pub async fn list_tasks(
         &self,
         filter: Option<TaskFilter>,
         limit: Option<usize>,
-        offset: Option<TaskId>,
+        from: Option<TaskId>,
     ) -> Result<Vec<Task>> {
-        let tasks = self.task_store.list_tasks(offset, filter, limit).await?;
+        let tasks = self.task_store.list_tasks(from, filter, limit).await?;
 
         Ok(tasks)
     }
 
+    pub async fn get_batch(&self, id: BatchId, filter: Option<TaskFilter>) -> Result<BatchInfo> {
+        let batch = self.task_store.get_batch(id, filter).await?;
+        Ok(batch)
+    }
+
+    pub async fn list_batches(
+        &self,
+        filter: Option<TaskFilter>,
+        limit: Option<usize>,
+        from: Option<BatchId>,
+    ) -> Result<Vec<BatchInfo>> {
+        let batches = self.task_store.list_batches(from, filter, limit).await?;
+
+        Ok(batches)
+    }
+
+    /// Returns the `limit` most recent tasks of the index, ignoring the first `offset` task ids
+    /// following the creation of the index.
     pub async fn list_index_task(
         &self,
         index_uid: String,
@@ -444,17 +840,16 @@ where
             .index_resolver
             .get_index_creation_task_id(index_uid.clone())
             .await?;
+        let first_task_id = offset.unwrap_or_default().saturating_add(task_id);
 
         let mut filter = TaskFilter::default();
         filter.filter_index(index_uid);
+        // Ignore the tasks of a previous index with the same uid.
+        filter.filter_fn(move |task| task.id >= first_task_id);
 
         let tasks = self
             .task_store
-            .list_tasks(
-                Some(offset.unwrap_or_default() + task_id),
-                Some(filter),
-                limit,
-            )
+            .list_tasks(None, Some(filter), limit)
             .await?;
 
         Ok(tasks)
@@ -516,6 +911,16 @@ where
         Ok(result)
     }
 
+    pub async fn facet_search(
+        &self,
+        uid: String,
+        query: FacetSearchQuery,
+    ) -> Result<FacetSearchResult> {
+        let index = self.index_resolver.get_index(uid).await?;
+        let result = spawn_blocking(move || index.perform_facet_search(query)).await??;
+        Ok(result)
+    }
+
     pub async fn get_index(&self, uid: String) -> Result<IndexMetadata> {
         let index = self.index_resolver.get_index(uid.clone()).await?;
         let uuid = index.uuid();
@@ -530,11 +935,11 @@ where
     }
 
     pub async fn get_index_stats(&self, uid: String) -> Result<IndexStats> {
-        let last_task = self.task_store.get_processing_task().await?;
-        // Check if the currently indexing update is from our index.
-        let is_indexing = last_task
-            .map(|task| task.index_uid.into_inner() == uid)
-            .unwrap_or_default();
+        let processing_tasks = self.task_store.get_processing_tasks().await?;
+        // Check if one of the currently indexing updates is from our index.
+        let is_indexing = processing_tasks
+            .iter()
+            .any(|task| task.index_uid.as_str() == uid);
 
         let index = self.index_resolver.get_index(uid).await?;
         let mut stats = spawn_blocking(move || index.stats()).await??;
@@ -547,7 +952,7 @@ where
         let mut last_task: Option<DateTime<_>> = None;
         let mut indexes = BTreeMap::new();
         let mut database_size = 0;
-        let processing_task = self.task_store.get_processing_task().await?;
+        let processing_tasks = self.task_store.get_processing_tasks().await?;
 
         for (index_uid, index) in self.index_resolver.list().await? {
             if index_filter
@@ -569,11 +974,12 @@ where
                 Some(last.max(meta.updated_at))
             });
 
-            // Check if the currently indexing update is from our index.
-            stats.is_indexing = processing_task
-                .as_ref()
-                .map(|p| p.index_uid.as_str() == index_uid)
-                .or(Some(false));
+            // Check if one of the currently indexing updates is from our index.
+            stats.is_indexing = Some(
+                processing_tasks
+                    .iter()
+                    .any(|task| task.index_uid.as_str() == index_uid),
+            );
 
             indexes.insert(index_uid, stats);
         }
@@ -581,7 +987,9 @@ where
         Ok(Stats {
             database_size,
             last_update: last_task,
+            is_indexing_paused: self.task_store.is_paused(),
             indexes,
+            search_cache: self.search_cache.stats(),
         })
     }
 
@@ -594,6 +1002,35 @@ where
     }
 }
 
+/// Returns the fingerprint of an update, used to detect the reuse of an idempotency key for
+/// another update. `payload_hasher` already hashed the documents of the update, the uuids of the
+/// update files are ignored since they are different for each request.
+fn update_fingerprint(content: &TaskContent, mut payload_hasher: SipHasher13) -> u64 {
+    let mut content = content.clone();
+    match content {
+        TaskContent::DocumentAddition {
+            ref mut content_uuid,
+            ..
+        } => *content_uuid = Uuid::nil(),
+        TaskContent::Transaction {
+            ref mut operations, ..
+        } => {
+            for operation in operations {
+                if let TransactionOperation::DocumentAddition { content_uuid, .. } = operation {
+                    *content_uuid = Uuid::nil();
+                }
+            }
+        }
+        _ => (),
+    }
+
+    // The content is made of plain data, so its serialization can't fail.
+    if let Ok(content) = serde_json::to_vec(&content) {
+        payload_hasher.write(&content);
+    }
+    payload_hasher.finish()
+}
+
 pub async fn get_arc_ownership_blocking<T>(mut item: Arc<T>) -> T {
     loop {
         match Arc::try_unwrap(item) {
@@ -633,6 +1070,9 @@ mod test {
                 task_store,
                 dump_handle,
                 update_file_store,
+                task_retention: TaskRetentionConfig::default(),
+                webhooks: Webhooks::new(&WebhookConfig::default()).unwrap(),
+                search_cache: Arc::new(SearchCache::new(0)),
             }
         }
     }
@@ -645,14 +1085,25 @@ mod test {
             q: Some(String::from("hello world")),
             offset: Some(10),
             limit: 0,
+            page: None,
+            hits_per_page: None,
             attributes_to_retrieve: Some(vec!["string".to_owned()].into_iter().collect()),
             attributes_to_crop: None,
             crop_length: 18,
+            crop_snippets: 1,
             attributes_to_highlight: None,
+            highlight_pre_tag: None,
+            highlight_post_tag: None,
+            crop_marker: None,
             matches: true,
+            show_ranking_score: false,
+            show_ranking_score_details: false,
             filter: None,
             sort: None,
             facets_distribution: None,
+            exhaustive_facets_count: false,
+            max_values_per_facet: 100,
+            show_facet_stats: false,
         };
 
         let result = SearchResult {
@@ -663,8 +1114,10 @@ mod test {
             limit: 24,
             offset: 0,
             processing_time_ms: 50,
+            pagination: None,
             facets_distribution: None,
             exhaustive_facets_count: Some(true),
+            facet_stats: None,
         };
 
         let mut uuid_store = MockIndexMetaStore::new();
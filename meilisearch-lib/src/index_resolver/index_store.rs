@@ -8,6 +8,7 @@ use tokio::task::spawn_blocking;
 use uuid::Uuid;
 
 use super::error::{IndexResolverError, Result};
+use crate::index::search_cache::SearchCache;
 use crate::index::update_handler::UpdateHandler;
 use crate::index::Index;
 use crate::options::IndexerOpts;
@@ -27,6 +28,7 @@ pub struct MapIndexStore {
     path: PathBuf,
     index_size: usize,
     update_handler: Arc<UpdateHandler>,
+    search_cache: Arc<SearchCache>,
 }
 
 impl MapIndexStore {
@@ -34,6 +36,7 @@ impl MapIndexStore {
         path: impl AsRef<Path>,
         index_size: usize,
         indexer_opts: &IndexerOpts,
+        search_cache: Arc<SearchCache>,
     ) -> anyhow::Result<Self> {
         let update_handler = Arc::new(UpdateHandler::new(indexer_opts)?);
         let path = path.as_ref().join("indexes/");
@@ -43,6 +46,7 @@ impl MapIndexStore {
             path,
             index_size,
             update_handler,
+            search_cache,
         })
     }
 }
@@ -64,8 +68,9 @@ impl IndexStore for MapIndexStore {
 
         let index_size = self.index_size;
         let update_handler = self.update_handler.clone();
+        let search_cache = self.search_cache.clone();
         let index = spawn_blocking(move || -> Result<Index> {
-            let index = Index::open(path, index_size, uuid, update_handler)?;
+            let index = Index::open(path, index_size, uuid, update_handler, search_cache)?;
             Ok(index)
         })
         .await??;
@@ -89,9 +94,11 @@ impl IndexStore for MapIndexStore {
 
                 let index_size = self.index_size;
                 let update_handler = self.update_handler.clone();
-                let index =
-                    spawn_blocking(move || Index::open(path, index_size, uuid, update_handler))
-                        .await??;
+                let search_cache = self.search_cache.clone();
+                let index = spawn_blocking(move || {
+                    Index::open(path, index_size, uuid, update_handler, search_cache)
+                })
+                .await??;
                 self.index_store.write().await.insert(uuid, index.clone());
                 Ok(Some(index))
             }
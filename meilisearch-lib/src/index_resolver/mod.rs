@@ -2,9 +2,10 @@ pub mod error;
 pub mod index_store;
 pub mod meta_store;
 
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use chrono::Utc;
 use error::{IndexResolverError, Result};
@@ -12,15 +13,19 @@ use heed::Env;
 use index_store::{IndexStore, MapIndexStore};
 use meilisearch_error::ResponseError;
 use meta_store::{HeedMetaStore, IndexMetaStore};
-use milli::update::DocumentDeletionResult;
+use milli::update::{DocumentDeletionResult, UpdateIndexingStep};
 use serde::{Deserialize, Serialize};
 use tokio::task::spawn_blocking;
 use uuid::Uuid;
 
-use crate::index::{error::Result as IndexResult, update_handler::UpdateHandler, Index};
+use crate::index::{
+    error::Result as IndexResult, search_cache::SearchCache, update_handler::UpdateHandler, Index,
+};
 use crate::options::IndexerOpts;
-use crate::tasks::batch::Batch;
-use crate::tasks::task::{DocumentDeletion, Job, Task, TaskContent, TaskEvent, TaskId, TaskResult};
+use crate::tasks::batch::{Batch, IndexingStep};
+use crate::tasks::task::{
+    DocumentDeletion, Job, Task, TaskContent, TaskEvent, TaskId, TaskProgress, TaskResult,
+};
 use crate::tasks::{Pending, TaskPerformer};
 use crate::update_file_store::UpdateFileStore;
 
@@ -30,7 +35,7 @@ pub type HardStateIndexResolver = IndexResolver<HeedMetaStore, MapIndexStore>;
 
 /// An index uid is composed of only ascii alphanumeric characters, - and _, between 1 and 400
 /// bytes long
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 pub struct IndexUid(#[cfg_attr(test, proptest(regex("[a-zA-Z0-9_-]{1,400}")))] String);
 
@@ -38,11 +43,12 @@ pub fn create_index_resolver(
     path: impl AsRef<Path>,
     index_size: usize,
     indexer_opts: &IndexerOpts,
+    search_cache: Arc<SearchCache>,
     meta_env: Arc<heed::Env>,
     file_store: UpdateFileStore,
 ) -> anyhow::Result<HardStateIndexResolver> {
     let uuid_store = HeedMetaStore::new(meta_env)?;
-    let index_store = MapIndexStore::new(&path, index_size, indexer_opts)?;
+    let index_store = MapIndexStore::new(&path, index_size, indexer_opts, search_cache)?;
     Ok(IndexResolver::new(uuid_store, index_store, file_store))
 }
 
@@ -99,52 +105,83 @@ where
     type Error = ResponseError;
 
     async fn process(&self, mut batch: Batch) -> Batch {
-        // Until batching is implemented, all batch should contain only one update.
-        debug_assert_eq!(batch.len(), 1);
-
-        match batch.tasks.first_mut() {
-            Some(Pending::Task(task)) => {
-                task.events.push(TaskEvent::Processing(Utc::now()));
-
-                match self.process_task(task).await {
-                    Ok(success) => {
-                        task.events.push(TaskEvent::Succeded {
-                            result: success,
-                            timestamp: Utc::now(),
-                        });
-                    }
-                    Err(err) => task.events.push(TaskEvent::Failed {
-                        error: err.into(),
-                        timestamp: Utc::now(),
-                    }),
-                }
-            }
-            Some(Pending::Job(job)) => {
-                let job = std::mem::take(job);
-                self.process_job(job).await;
-            }
-
-            None => (),
+        if batch.len() > 1 {
+            self.process_task_batch(&mut batch.tasks).await;
+        } else {
+            self.process_single(&mut batch).await;
         }
 
+        batch.steps = self.indexing_steps(&batch);
         batch
     }
 
     async fn finish(&self, batch: &Batch) {
         for task in &batch.tasks {
-            if let Some(content_uuid) = task.get_content_uuid() {
-                if let Err(e) = self.file_store.delete(content_uuid).await {
-                    log::error!("error deleting update file: {}", e);
+            if let Pending::Task(task) = task {
+                self.progress.write().unwrap().remove(&task.id);
+
+                // The update file is deleted by the retention service once the grace period of
+                // the failed task is over.
+                if task.is_failed_document_addition() {
+                    continue;
+                }
+
+                for content_uuid in task.content_uuids() {
+                    if let Err(e) = self.file_store.delete(content_uuid).await {
+                        log::error!("error deleting update file: {}", e);
+                    }
                 }
             }
         }
     }
 }
 
+/// What the indexer reported about a processing document addition.
+struct IndexingReport {
+    progress: TaskProgress,
+    /// The indexing steps the task went through, in order
+    steps: Vec<IndexingStep>,
+}
+
+/// Records the indexing step reported for a processing task, and the time spent in each step.
+fn update_progress(
+    progress: &RwLock<HashMap<TaskId, IndexingReport>>,
+    id: TaskId,
+    step: UpdateIndexingStep,
+) {
+    let now = Utc::now();
+    let mut progress = progress.write().unwrap();
+    let previous = progress.remove(&id);
+    let task_progress = TaskProgress::new(step, previous.as_ref().map(|report| &report.progress));
+
+    let mut steps = previous.map(|report| report.steps).unwrap_or_default();
+    if let Some(last) = steps.last_mut() {
+        last.finished_at = now;
+    }
+    if steps
+        .last()
+        .map_or(true, |last| last.name != task_progress.step)
+    {
+        steps.push(IndexingStep {
+            name: task_progress.step.clone(),
+            started_at: now,
+            finished_at: now,
+        });
+    }
+
+    let report = IndexingReport {
+        progress: task_progress,
+        steps,
+    };
+    progress.insert(id, report);
+}
+
 pub struct IndexResolver<U, I> {
     index_uuid_store: U,
     index_store: I,
     file_store: UpdateFileStore,
+    /// The progress of the document additions that are currently processing
+    progress: Arc<RwLock<HashMap<TaskId, IndexingReport>>>,
 }
 
 impl IndexResolver<HeedMetaStore, MapIndexStore> {
@@ -177,6 +214,184 @@ where
             index_uuid_store,
             index_store,
             file_store,
+            progress: Default::default(),
+        }
+    }
+
+    /// Returns the progress of the task if it is a document addition that is processing.
+    pub fn get_task_progress(&self, id: TaskId) -> Option<TaskProgress> {
+        self.progress
+            .read()
+            .unwrap()
+            .get(&id)
+            .map(|report| report.progress.clone())
+    }
+
+    /// Returns the indexing steps the tasks of the batch went through, in the order of the
+    /// tasks.
+    fn indexing_steps(&self, batch: &Batch) -> Vec<IndexingStep> {
+        let progress = self.progress.read().unwrap();
+        batch
+            .tasks
+            .iter()
+            .filter_map(|task| match task {
+                Pending::Task(task) => progress.get(&task.id),
+                Pending::Job(_) => None,
+            })
+            .flat_map(|report| report.steps.iter().cloned())
+            .collect()
+    }
+
+    /// Processes a batch made of a single task or job.
+    async fn process_single(&self, batch: &mut Batch) {
+        match batch.tasks.first_mut() {
+            Some(Pending::Task(task)) => {
+                task.events.push(TaskEvent::Processing(Utc::now()));
+
+                match self.process_task(task).await {
+                    Ok(success) => {
+                        task.events.push(TaskEvent::Succeded {
+                            result: success,
+                            timestamp: Utc::now(),
+                        });
+                    }
+                    Err(err) => task.events.push(TaskEvent::Failed {
+                        error: err.into(),
+                        timestamp: Utc::now(),
+                    }),
+                }
+            }
+            Some(Pending::Job(job)) => {
+                let job = std::mem::take(job);
+                self.process_job(job).await;
+            }
+
+            None => (),
+        }
+    }
+
+    /// Processes a batch made of several tasks. Document additions and document deletions that
+    /// were batched together are applied in a single write transaction. If that fails, or if the
+    /// tasks can't be grouped, each task is processed on its own, so that a faulty task doesn't
+    /// make the other tasks of the batch fail.
+    async fn process_task_batch(&self, tasks: &mut [Pending<Task>]) {
+        let mut tasks: Vec<&mut Task> = tasks
+            .iter_mut()
+            .filter_map(|task| match task {
+                Pending::Task(task) => Some(task),
+                Pending::Job(_) => None,
+            })
+            .collect();
+
+        for task in tasks.iter_mut() {
+            task.events.push(TaskEvent::Processing(Utc::now()));
+        }
+
+        let groupable = matches!(
+            tasks.first().map(|t| &t.content),
+            Some(TaskContent::DocumentAddition { .. })
+                | Some(TaskContent::DocumentDeletion(DocumentDeletion::Ids(_)))
+        );
+
+        if groupable {
+            match self.process_grouped_tasks(&tasks).await {
+                Ok(results) => {
+                    for (task, result) in tasks.iter_mut().zip(results) {
+                        task.events.push(TaskEvent::Succeded {
+                            result,
+                            timestamp: Utc::now(),
+                        });
+                    }
+                    return;
+                }
+                Err(e) => log::warn!(
+                    "grouped processing of {} tasks failed, processing them one by one: {}",
+                    tasks.len(),
+                    e
+                ),
+            }
+        }
+
+        for task in tasks {
+            match self.process_task(task).await {
+                Ok(result) => task.events.push(TaskEvent::Succeded {
+                    result,
+                    timestamp: Utc::now(),
+                }),
+                Err(err) => task.events.push(TaskEvent::Failed {
+                    error: err.into(),
+                    timestamp: Utc::now(),
+                }),
+            }
+        }
+    }
+
+    /// Applies document additions or document deletions in a single write transaction, returning
+    /// the result of each task in order. All the tasks must target the same index, and be
+    /// batchable with the first one.
+    async fn process_grouped_tasks(&self, tasks: &[&mut Task]) -> Result<Vec<TaskResult>> {
+        let first = &tasks[0];
+        let index_uid = first.index_uid.clone();
+
+        match &first.content {
+            TaskContent::DocumentAddition {
+                merge_strategy,
+                primary_key,
+                allow_index_creation,
+                ..
+            } => {
+                let primary_key = primary_key.clone();
+                let method = *merge_strategy;
+                let content_uuids: Vec<Uuid> =
+                    tasks.iter().filter_map(|t| t.get_content_uuid()).collect();
+                let task_ids: Vec<TaskId> = tasks.iter().map(|t| t.id).collect();
+
+                let index = if *allow_index_creation {
+                    self.get_or_create_index(index_uid, first.id).await?
+                } else {
+                    self.get_index(index_uid.into_inner()).await?
+                };
+                let file_store = self.file_store.clone();
+                let progress = self.progress.clone();
+                let results = spawn_blocking(move || {
+                    index.update_documents_batch(
+                        method,
+                        &content_uuids,
+                        primary_key,
+                        file_store,
+                        |position, step| update_progress(&progress, task_ids[position], step),
+                    )
+                })
+                .await??;
+
+                Ok(results.into_iter().map(Into::into).collect())
+            }
+            TaskContent::DocumentDeletion(DocumentDeletion::Ids(_)) => {
+                let ids: Vec<Vec<String>> = tasks
+                    .iter()
+                    .filter_map(|t| match &t.content {
+                        TaskContent::DocumentDeletion(DocumentDeletion::Ids(ids)) => {
+                            Some(ids.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let index = self.get_index(index_uid.into_inner()).await?;
+
+                let results = spawn_blocking(move || index.delete_documents_batch(&ids)).await??;
+
+                Ok(results
+                    .into_iter()
+                    .map(
+                        |DocumentDeletionResult {
+                             deleted_documents, ..
+                         }| {
+                            TaskResult::DocumentDeletion { deleted_documents }
+                        },
+                    )
+                    .collect())
+            }
+            _ => unreachable!("only document additions and deletions are grouped"),
         }
     }
 
@@ -200,8 +415,12 @@ where
                     self.get_index(index_uid.into_inner()).await?
                 };
                 let file_store = self.file_store.clone();
+                let progress = self.progress.clone();
+                let task_id = task.id;
                 let result = spawn_blocking(move || {
-                    index.update_documents(method, content_uuid, primary_key, file_store)
+                    index.update_documents(method, content_uuid, primary_key, file_store, |step| {
+                        update_progress(&progress, task_id, step)
+                    })
                 })
                 .await??;
 
@@ -274,6 +493,24 @@ where
 
                 Ok(TaskResult::Other)
             }
+            TaskContent::Transaction {
+                operations,
+                allow_index_creation,
+            } => {
+                let index = if *allow_index_creation {
+                    self.get_or_create_index(index_uid, task.id).await?
+                } else {
+                    self.get_index(index_uid.into_inner()).await?
+                };
+
+                let operations = operations.clone();
+                let file_store = self.file_store.clone();
+                let results =
+                    spawn_blocking(move || index.apply_transaction(&operations, file_store))
+                        .await??;
+
+                Ok(TaskResult::Transaction { results })
+            }
         }
     }
 
@@ -417,6 +654,7 @@ mod test {
         error::{IndexError, Result as IndexResult},
         Checked, IndexMeta, IndexStats, Settings,
     };
+    use crate::tasks::task::TransactionOperation;
     use index_store::MockIndexStore;
     use meta_store::MockIndexMetaStore;
 
@@ -481,6 +719,16 @@ mod test {
                         mocker.when::<(), IndexResult<()>>("clear_documents")
                             .then(move |_| result());
                     },
+                    TaskContent::Transaction { .. } => {
+                        let result = move || if !index_op_fails {
+                            Ok(Vec::new())
+                        } else {
+                            // return this error because it's easy to generate...
+                            Err(IndexError::DocumentNotFound("a doc".into()))
+                        };
+                        mocker.when::<(&[TransactionOperation], UpdateFileStore), IndexResult<Vec<TaskResult>>>("apply_transaction")
+                            .then(move |_| result());
+                    }
                     TaskContent::IndexDeletion => {
                         mocker.when::<(), ()>("close")
                             .times(index_exists as usize)
@@ -509,6 +757,7 @@ mod test {
                 match &task.content {
                     // an unexisting index should trigger an index creation in the folllowing cases:
                     TaskContent::DocumentAddition { allow_index_creation: true, .. }
+                    | TaskContent::Transaction { allow_index_creation: true, .. }
                     | TaskContent::SettingsUpdate { allow_index_creation: true, is_deletion: false, .. }
                     | TaskContent::IndexCreation { .. } if !index_exists => {
                         index_store
@@ -573,6 +822,7 @@ mod test {
                                                                 | TaskContent::SettingsUpdate { is_deletion: true, ..}
                                                                 | TaskContent::SettingsUpdate { allow_index_creation: false, ..}
                                                                 | TaskContent::DocumentAddition { allow_index_creation: false, ..}
+                                                                | TaskContent::Transaction { allow_index_creation: false, ..}
                                                                 | TaskContent::IndexUpdate { .. } ))
                 {
                     assert!(result.is_err(), "{:?}", result);
@@ -582,4 +832,38 @@ mod test {
             });
         }
     }
+
+    #[test]
+    fn test_update_progress_records_steps() {
+        let progress = RwLock::new(HashMap::new());
+
+        update_progress(
+            &progress,
+            0,
+            UpdateIndexingStep::RemapDocumentAddition { documents_seen: 10 },
+        );
+        update_progress(
+            &progress,
+            0,
+            UpdateIndexingStep::RemapDocumentAddition { documents_seen: 20 },
+        );
+        update_progress(
+            &progress,
+            0,
+            UpdateIndexingStep::ComputeIdsAndMergeDocuments {
+                documents_seen: 0,
+                total_documents: 20,
+            },
+        );
+
+        let progress = progress.read().unwrap();
+        let report = &progress[&0];
+        let names: Vec<_> = report.steps.iter().map(|step| step.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["remapDocumentAddition", "computeIdsAndMergeDocuments"]
+        );
+        assert_eq!(report.steps[0].finished_at, report.steps[1].started_at);
+        assert_eq!(report.progress.step, "computeIdsAndMergeDocuments");
+    }
 }
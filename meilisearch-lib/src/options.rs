@@ -56,6 +56,107 @@ impl Default for IndexerOpts {
     }
 }
 
+#[derive(Debug, Clone, Default, StructOpt)]
+pub struct SchedulerConfig {
+    /// The engine will disable task auto-batching, and will sequencialy compute each task one by
+    /// one.
+    #[structopt(long, env = "MEILI_DISABLE_AUTO_BATCHING")]
+    pub disable_auto_batching: bool,
+
+    /// The maximum number of tasks that can be grouped in a single batch. Defaults to no limit.
+    #[structopt(long, env = "MEILI_MAX_BATCH_SIZE")]
+    pub max_batch_size: Option<usize>,
+
+    /// The maximum number of batches, each targeting a different index, that can be processed
+    /// concurrently. Defaults to 1.
+    #[structopt(long, env = "MEILI_MAX_CONCURRENT_BATCHES")]
+    pub max_concurrent_batches: Option<usize>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct TaskRetentionConfig {
+    /// Finished tasks older than this number of seconds are deleted from the task store.
+    #[structopt(long, env = "MEILI_TASK_RETENTION_MAX_AGE_SEC")]
+    pub task_retention_max_age_sec: Option<u64>,
+
+    /// Only this number of the most recent finished tasks is kept in the task store, the older
+    /// ones are deleted.
+    #[structopt(long, env = "MEILI_TASK_RETENTION_MAX_COUNT")]
+    pub task_retention_max_count: Option<usize>,
+
+    /// Defines the interval, in seconds, at which the retention policy is applied.
+    #[structopt(
+        long,
+        env = "MEILI_TASK_RETENTION_INTERVAL_SEC",
+        default_value = "3600"
+    )]
+    pub task_retention_interval_sec: u64,
+
+    /// The number of seconds during which the payload of a failed document addition is kept, so
+    /// the task can be retried.
+    #[structopt(
+        long,
+        env = "MEILI_FAILED_TASK_PAYLOAD_RETENTION_SEC",
+        default_value = "86400"
+    )]
+    pub failed_task_payload_retention_sec: u64,
+
+    /// The number of seconds during which an `Idempotency-Key` is remembered. A request sent with
+    /// the same key during this period returns the task registered by the first request.
+    #[structopt(
+        long,
+        env = "MEILI_IDEMPOTENCY_KEY_RETENTION_SEC",
+        default_value = "86400"
+    )]
+    pub idempotency_key_retention_sec: u64,
+}
+
+impl Default for TaskRetentionConfig {
+    fn default() -> Self {
+        Self {
+            task_retention_max_age_sec: None,
+            task_retention_max_count: None,
+            task_retention_interval_sec: 3600,
+            failed_task_payload_retention_sec: 86400,
+            idempotency_key_retention_sec: 86400,
+        }
+    }
+}
+
+impl TaskRetentionConfig {
+    /// Return true when a retention policy is defined.
+    pub fn is_enabled(&self) -> bool {
+        self.task_retention_max_age_sec.is_some() || self.task_retention_max_count.is_some()
+    }
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct WebhookConfig {
+    /// The urls to which each finished task is sent. Several urls can be provided, separated by
+    /// commas.
+    #[structopt(long = "webhook-url", env = "MEILI_WEBHOOK_URLS", use_delimiter = true)]
+    pub webhook_urls: Vec<String>,
+
+    /// A header, formatted as `Name: value`, sent with each request to the webhook urls. Can be
+    /// repeated to send several headers.
+    #[structopt(long = "webhook-header", env = "MEILI_WEBHOOK_HEADER")]
+    pub webhook_headers: Vec<String>,
+
+    /// The number of times the delivery of a task to a webhook is retried before giving up.
+    #[structopt(long, env = "MEILI_WEBHOOK_MAX_RETRIES", default_value = "5")]
+    pub webhook_max_retries: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            webhook_urls: Vec::new(),
+            webhook_headers: Vec::new(),
+            webhook_max_retries: 5,
+        }
+    }
+}
+
 /// A type used to detect the max memory available and use 2/3 of it.
 #[derive(Debug, Clone, Copy)]
 pub struct MaxMemory(Option<Byte>);
@@ -1,6 +1,10 @@
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-use super::{task::Task, task_store::Pending};
+use super::{
+    task::{Task, TaskContent, TaskId, TransactionOperation},
+    task_store::Pending,
+};
 
 pub type BatchId = u32;
 
@@ -9,6 +13,8 @@ pub struct Batch {
     pub id: BatchId,
     pub created_at: DateTime<Utc>,
     pub tasks: Vec<Pending<Task>>,
+    /// The indexing steps the tasks of the batch went through, filled while processing
+    pub steps: Vec<IndexingStep>,
 }
 
 impl Batch {
@@ -20,3 +26,80 @@ impl Batch {
         self.tasks.is_empty()
     }
 }
+
+/// An indexing step, as reported by the indexer while processing a document addition.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexingStep {
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    /// The last time the indexer reported this step
+    pub finished_at: DateTime<Utc>,
+}
+
+/// The record of a batch of tasks, that is kept in the task store once the batch started
+/// processing. Batches made of a job are not recorded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchInfo {
+    pub id: BatchId,
+    pub index_uid: String,
+    pub task_ids: Vec<TaskId>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The number of documents received by the document additions of the batch
+    pub total_documents: usize,
+    pub steps: Vec<IndexingStep>,
+}
+
+impl BatchInfo {
+    /// Creates the record of a batch, returns `None` for a job. The batch starts processing as
+    /// soon as it is created.
+    pub fn new(batch: &Batch, finished_at: Option<DateTime<Utc>>) -> Option<Self> {
+        let tasks: Vec<&Task> = batch
+            .tasks
+            .iter()
+            .filter_map(|task| match task {
+                Pending::Task(task) => Some(task),
+                Pending::Job(_) => None,
+            })
+            .collect();
+
+        let index_uid = tasks.first()?.index_uid.to_string();
+        let task_ids = tasks.iter().map(|task| task.id).collect();
+        let total_documents = tasks
+            .iter()
+            .map(|task| received_documents(&task.content))
+            .sum();
+
+        Some(Self {
+            id: batch.id,
+            index_uid,
+            task_ids,
+            started_at: batch.created_at,
+            finished_at,
+            total_documents,
+            steps: batch.steps.clone(),
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished_at.is_some()
+    }
+}
+
+fn received_documents(content: &TaskContent) -> usize {
+    match content {
+        TaskContent::DocumentAddition {
+            documents_count, ..
+        } => *documents_count,
+        TaskContent::Transaction { operations, .. } => operations
+            .iter()
+            .map(|operation| match operation {
+                TransactionOperation::DocumentAddition {
+                    documents_count, ..
+                } => *documents_count,
+                _ => 0,
+            })
+            .sum(),
+        _ => 0,
+    }
+}
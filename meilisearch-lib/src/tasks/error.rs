@@ -3,6 +3,7 @@ use tokio::task::JoinError;
 
 use crate::update_file_store::UpdateFileStoreError;
 
+use super::batch::BatchId;
 use super::task::TaskId;
 
 pub type Result<T> = std::result::Result<T, TaskError>;
@@ -11,6 +12,26 @@ pub type Result<T> = std::result::Result<T, TaskError>;
 pub enum TaskError {
     #[error("Task `{0}` not found.")]
     UnexistingTask(TaskId),
+    #[error("Task `{0}` is already processing and can't be canceled.")]
+    TaskAlreadyProcessing(TaskId),
+    #[error("Task `{0}` is already finished and can't be canceled.")]
+    TaskAlreadyFinished(TaskId),
+    #[error("`{0}` is not a valid webhook url. The url must use the http or https scheme.")]
+    InvalidWebhookUrl(String),
+    #[error("`{0}` is not a valid webhook header. Headers must be formatted as `Name: value`.")]
+    InvalidWebhookHeader(String),
+    #[error("Webhook `{0}` not found.")]
+    UnexistingWebhook(String),
+    #[error("Task `{0}` can't be retried. Only failed document additions can be retried.")]
+    TaskNotRetryable(TaskId),
+    #[error("Task `{0}` is already being retried by task `{1}`.")]
+    TaskAlreadyRetried(TaskId, TaskId),
+    #[error("The payload of task `{0}` has been deleted, the task can't be retried anymore.")]
+    TaskPayloadDeleted(TaskId),
+    #[error("Batch `{0}` not found.")]
+    UnexistingBatch(BatchId),
+    #[error("Idempotency key `{0}` was already used for another request. A key can only be reused to send the same request to the same index.")]
+    IdempotencyKeyReused(String),
     #[error("Internal error: {0}")]
     Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
 }
@@ -20,6 +41,7 @@ internal_error!(
     JoinError,
     std::io::Error,
     serde_json::Error,
+    reqwest::Error,
     UpdateFileStoreError
 );
 
@@ -27,6 +49,16 @@ impl ErrorCode for TaskError {
     fn error_code(&self) -> Code {
         match self {
             TaskError::UnexistingTask(_) => Code::TaskNotFound,
+            TaskError::TaskAlreadyProcessing(_) => Code::TaskAlreadyProcessing,
+            TaskError::TaskAlreadyFinished(_) => Code::TaskAlreadyFinished,
+            TaskError::InvalidWebhookUrl(_) => Code::InvalidWebhookUrl,
+            TaskError::InvalidWebhookHeader(_) => Code::InvalidWebhookHeader,
+            TaskError::UnexistingWebhook(_) => Code::WebhookNotFound,
+            TaskError::TaskNotRetryable(_) => Code::TaskNotRetryable,
+            TaskError::TaskAlreadyRetried(..) => Code::TaskAlreadyRetried,
+            TaskError::TaskPayloadDeleted(_) => Code::TaskPayloadDeleted,
+            TaskError::UnexistingBatch(_) => Code::BatchNotFound,
+            TaskError::IdempotencyKeyReused(_) => Code::IdempotencyKeyReused,
             TaskError::Internal(_) => Code::Internal,
         }
     }
@@ -1,5 +1,4 @@
 use std::sync::Arc;
-use std::time::Duration;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -15,11 +14,15 @@ use batch::Batch;
 use error::Result;
 use scheduler::Scheduler;
 
+use crate::options::SchedulerConfig;
+
 pub mod batch;
 pub mod error;
+pub mod retention;
 pub mod scheduler;
 pub mod task;
 mod task_store;
+pub mod webhook;
 
 #[cfg_attr(test, mockall::automock(type Error=test::DebugError;))]
 #[async_trait]
@@ -32,12 +35,16 @@ pub trait TaskPerformer: Sync + Send + 'static {
     async fn finish(&self, batch: &Batch);
 }
 
-pub fn create_task_store<P>(env: Arc<heed::Env>, performer: Arc<P>) -> Result<TaskStore>
+pub fn create_task_store<P>(
+    env: Arc<heed::Env>,
+    performer: Arc<P>,
+    config: SchedulerConfig,
+) -> Result<TaskStore>
 where
     P: TaskPerformer,
 {
     let task_store = TaskStore::new(env)?;
-    let scheduler = Scheduler::new(task_store.clone(), performer, Duration::from_millis(1));
+    let scheduler = Scheduler::new(task_store.clone(), performer, config)?;
     tokio::task::spawn_local(scheduler.run());
     Ok(task_store)
 }
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use log::{error, info};
+use tokio::time::sleep;
+
+use super::TaskStore;
+use crate::options::TaskRetentionConfig;
+use crate::update_file_store::UpdateFileStore;
+
+/// Periodically deletes the finished tasks that don't match the retention policy from the
+/// `TaskStore`, and the update files of the failed document additions whose grace period is over.
+pub struct RetentionService {
+    pub(crate) task_store: TaskStore,
+    pub(crate) update_file_store: UpdateFileStore,
+    pub(crate) config: TaskRetentionConfig,
+}
+
+impl RetentionService {
+    pub async fn run(self) {
+        let period = Duration::from_secs(self.config.task_retention_interval_sec.max(1));
+        if self.config.is_enabled() {
+            info!("Task retention policy applied every {}s.", period.as_secs());
+        }
+        loop {
+            match self
+                .task_store
+                .prune(self.config.clone(), self.update_file_store.clone())
+                .await
+            {
+                Ok(0) => (),
+                Ok(deleted) => info!("Pruned {} finished tasks.", deleted),
+                Err(e) => error!("Error pruning the finished tasks: {}", e),
+            }
+
+            sleep(period).await;
+        }
+    }
+}
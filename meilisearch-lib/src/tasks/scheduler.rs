@@ -1,10 +1,16 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 
-use super::batch::Batch;
+use super::batch::{Batch, BatchId, BatchInfo};
 use super::error::Result;
 #[cfg(test)]
 use super::task_store::test::MockTaskStore as TaskStore;
@@ -12,18 +18,48 @@ use super::task_store::Pending;
 #[cfg(not(test))]
 use super::task_store::TaskStore;
 use super::TaskPerformer;
-use crate::tasks::task::TaskEvent;
+use crate::index_resolver::IndexUid;
+use crate::options::SchedulerConfig;
+use crate::tasks::task::{Job, Task, TaskEvent, TaskId};
 
-/// The scheduler roles is to perform batches of tasks one at a time. It will monitor the TaskStore
-/// for new tasks, put them in a batch, and process the batch as soon as possible.
+/// The delay after which the scheduler tries to prepare a batch again after a failure.
+const PREPARE_BATCH_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The scheduler roles is to perform batches of tasks. It will monitor the TaskStore for new
+/// tasks, put them in a batch, and process the batch as soon as possible.
+///
+/// When there is nothing to process, the scheduler sleeps until the `TaskStore` notifies it that
+/// a new task or job was registered, or until the next scheduled task is due.
 ///
-/// When a batch is currently processing, the scheduler is just waiting.
+/// Batches of tasks targeting different indexes can be processed concurrently, up to
+/// `SchedulerConfig::max_concurrent_batches`. The tasks of a same index are always processed one
+/// batch at a time, in the order they were registered. Jobs are processed alone: when a job is
+/// pending, the scheduler waits for the processing batches to finish, and doesn't start any new
+/// batch until the job is done.
+///
+/// When the scheduler is paused, it finishes the processing batches and stops taking new tasks
+/// from the `TaskStore`, but it still processes the jobs.
 pub struct Scheduler<P: TaskPerformer> {
     store: TaskStore,
     performer: Arc<P>,
 
-    /// The interval at which the the `TaskStore` should be checked for new updates
-    task_store_check_interval: Duration,
+    /// Notified by the `TaskStore` each time a new task or job is registered
+    notifier: Arc<Notify>,
+    /// Set by the `TaskStore` when the processing of the tasks is paused
+    paused: Arc<AtomicBool>,
+    config: SchedulerConfig,
+    /// The id that will be given to the next batch
+    next_batch_id: BatchId,
+    /// The indexes for which a batch is currently processing
+    processing_indexes: HashSet<IndexUid>,
+    /// Whether a job is currently processing
+    processing_job: bool,
+    /// A job that was taken from the store, but that must wait for the processing batches to
+    /// finish
+    waiting_job: Option<Job>,
+    /// The index of the pending tasks that were skipped because their index was processing, so
+    /// that they don't have to be read again from the store
+    pending_index_uids: HashMap<TaskId, IndexUid>,
 }
 
 impl<P> Scheduler<P>
@@ -31,102 +67,295 @@ where
     P: TaskPerformer + Send + Sync + 'static,
     P::Error: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
 {
-    pub fn new(store: TaskStore, performer: Arc<P>, task_store_check_interval: Duration) -> Self {
-        Self {
+    pub fn new(store: TaskStore, performer: Arc<P>, config: SchedulerConfig) -> Result<Self> {
+        let next_batch_id = store.next_batch_id()?;
+        let notifier = store.notifier();
+        let paused = store.paused();
+        Ok(Self {
             store,
             performer,
-            task_store_check_interval,
+            notifier,
+            paused,
+            config,
+            next_batch_id,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
+        })
+    }
+
+    pub async fn run(mut self) {
+        let mut processing = FuturesUnordered::new();
+        'scheduling: loop {
+            while processing.len() < self.max_concurrent_batches() {
+                match self.prepare_batch().await {
+                    Ok(Some(batch)) => processing.push(self.process_batch(batch)),
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("an error occured while preparing an update batch: {}", e);
+                        // The error may be transient, we wait a bit before we retry.
+                        tokio::time::sleep(PREPARE_BATCH_RETRY_DELAY).await;
+                        continue 'scheduling;
+                    }
+                }
+            }
+
+            if processing.is_empty() {
+                // No update found to create a batch, we wait for a new one to be registered.
+                self.wait_for_pending_tasks().await;
+                continue;
+            }
+
+            // Wait for a batch to finish, or for a new task to be registered.
+            let finished = tokio::select! {
+                finished = processing.next() => finished,
+                _ = self.wait_for_pending_tasks() => None,
+            };
+
+            if let Some((index_uid, batch)) = finished {
+                if let Err(e) = self.handle_batch_result(index_uid, batch).await {
+                    log::error!("an error occured while processing an update batch: {}", e);
+                }
+            }
         }
     }
 
-    pub async fn run(self) {
-        loop {
-            if let Err(e) = self.process_next_batch().await {
-                log::error!("an error occured while processing an update batch: {}", e);
+    /// Waits until a new task or job is registered, or until the next scheduled task is due.
+    async fn wait_for_pending_tasks(&self) {
+        match self.store.next_scheduled_at().await {
+            Some(not_before) => {
+                let delay = (not_before - Utc::now()).to_std().unwrap_or_default();
+                let _ = tokio::time::timeout(delay, self.notifier.notified()).await;
             }
+            None => self.notifier.notified().await,
         }
     }
 
-    async fn process_next_batch(&self) -> Result<()> {
-        match self.prepare_batch().await? {
-            Some(mut batch) => {
-                for task in &mut batch.tasks {
-                    match task {
-                        Pending::Task(task) => task.events.push(TaskEvent::Processing(Utc::now())),
-                        Pending::Job(_) => (),
-                    }
+    fn max_concurrent_batches(&self) -> usize {
+        self.config.max_concurrent_batches.unwrap_or(1).max(1)
+    }
+
+    /// Returns a future processing the batch. The future resolves to the index of the batch,
+    /// if any, and the processed batch.
+    fn process_batch(
+        &self,
+        mut batch: Batch,
+    ) -> impl Future<Output = (Option<IndexUid>, Result<Batch>)> {
+        let store = self.store.clone();
+        let performer = self.performer.clone();
+        let index_uid = batch_index_uid(&batch);
+
+        async move {
+            for task in &mut batch.tasks {
+                match task {
+                    Pending::Task(task) => task.events.push(TaskEvent::Processing(Utc::now())),
+                    Pending::Job(_) => (),
                 }
+            }
 
-                // the jobs are ignored
-                batch.tasks = self.store.update_tasks(batch.tasks).await?;
+            // the jobs are ignored
+            let result = match store.update_tasks(batch.tasks).await {
+                Ok(tasks) => {
+                    batch.tasks = tasks;
+                    // All the tasks of the batch were cancelled before it started processing.
+                    if batch.is_empty() {
+                        Ok(batch)
+                    } else {
+                        record_batch(&store, &batch, None).await;
+                        Ok(performer.process(batch).await)
+                    }
+                }
+                Err(e) => Err(e),
+            };
 
-                let performer = self.performer.clone();
-                let batch_result = performer.process(batch).await;
-                self.handle_batch_result(batch_result).await?;
-            }
-            None => {
-                // No update found to create a batch we wait a bit before we retry.
-                tokio::time::sleep(self.task_store_check_interval).await;
-            }
+            (index_uid, result)
         }
-
-        Ok(())
     }
 
-    /// Checks for pending tasks and groups them in a batch. If there are no pending update,
-    /// return Ok(None)
+    /// Checks for pending tasks and groups them in a batch. If there are no pending update, or if
+    /// nothing can be processed until the processing batches are finished, return Ok(None)
     ///
-    /// Until batching is properly implemented, the batches contain only one task.
-    async fn prepare_batch(&self) -> Result<Option<Batch>> {
+    /// A batch contains either a single job, or the next pending task of an index that isn't
+    /// processing, followed by all the consecutive pending tasks that can be processed along
+    /// with it (see `Task::is_batchable_with`).
+    async fn prepare_batch(&mut self) -> Result<Option<Batch>> {
+        if self.processing_job {
+            return Ok(None);
+        }
+
+        if let Some(job) = self.waiting_job.take() {
+            return Ok(self.prepare_job_batch(job));
+        }
+
         match self.store.peek_pending_task().await {
-            Some(Pending::Task(next_task_id)) => {
-                let mut task = self.store.get_task(next_task_id, None).await?;
+            Some(Pending::Task(_)) if self.paused.load(Ordering::Relaxed) => Ok(None),
+            Some(Pending::Task(_)) => self.prepare_task_batch().await,
+            Some(Pending::Job(job)) => Ok(self.prepare_job_batch(job)),
+            None => Ok(None),
+        }
+    }
 
-                task.events.push(TaskEvent::Batched {
-                    timestamp: Utc::now(),
-                    batch_id: 0,
-                });
+    fn prepare_job_batch(&mut self, job: Job) -> Option<Batch> {
+        if !self.processing_indexes.is_empty() {
+            self.waiting_job = Some(job);
+            return None;
+        }
 
-                let batch = Batch {
-                    id: 0,
-                    // index_uid: task.index_uid.clone(),
-                    created_at: Utc::now(),
-                    tasks: vec![Pending::Task(task)],
-                };
-                Ok(Some(batch))
+        // The batches made of a job are not recorded, so they don't use up a batch id, which would
+        // leave a gap in the recorded batches. The job shares the id of the next batch of tasks.
+        self.processing_job = true;
+
+        Some(Batch {
+            id: self.next_batch_id,
+            created_at: Utc::now(),
+            tasks: vec![Pending::Job(job)],
+            steps: Vec::new(),
+        })
+    }
+
+    async fn prepare_task_batch(&mut self) -> Result<Option<Batch>> {
+        let max_batch_size = if self.config.disable_auto_batching {
+            1
+        } else {
+            self.config.max_batch_size.unwrap_or(usize::MAX).max(1)
+        };
+
+        let pending_ids = self.store.pending_task_ids(usize::MAX).await;
+        if self.pending_index_uids.len() > pending_ids.len() {
+            // Some of the cached tasks have been cancelled.
+            let pending: HashSet<_> = pending_ids.iter().collect();
+            self.pending_index_uids.retain(|id, _| pending.contains(id));
+        }
+
+        let mut pending_ids = pending_ids.into_iter();
+        let first_task = loop {
+            let id = match pending_ids.next() {
+                Some(id) => id,
+                None => return Ok(None),
+            };
+
+            if let Some(index_uid) = self.pending_index_uids.get(&id) {
+                if self.processing_indexes.contains(index_uid) {
+                    continue;
+                }
             }
-            Some(Pending::Job(job)) => Ok(Some(Batch {
-                id: 0,
-                created_at: Utc::now(),
-                tasks: vec![Pending::Job(job)],
-            })),
-            None => Ok(None),
+
+            let task = self.store.get_task(id, None).await?;
+            // The task was cancelled after we fetched the pending tasks.
+            if task.is_finished() {
+                continue;
+            }
+
+            if self.processing_indexes.contains(&task.index_uid) {
+                self.pending_index_uids.insert(id, task.index_uid);
+                continue;
+            }
+
+            break task;
+        };
+
+        let mut tasks = vec![first_task];
+        for id in pending_ids {
+            if tasks.len() >= max_batch_size {
+                break;
+            }
+
+            let task = self.store.get_task(id, None).await?;
+            // The task was cancelled after we fetched the pending tasks.
+            if task.is_finished() {
+                continue;
+            }
+
+            if !tasks[0].is_batchable_with(&task) {
+                break;
+            }
+            tasks.push(task);
         }
+
+        let batch_id = self.next_batch_id;
+        self.next_batch_id += 1;
+        self.processing_indexes.insert(tasks[0].index_uid.clone());
+
+        let timestamp = Utc::now();
+        let tasks = tasks
+            .into_iter()
+            .map(|mut task| {
+                self.pending_index_uids.remove(&task.id);
+                task.events.push(TaskEvent::Batched {
+                    timestamp,
+                    batch_id,
+                });
+                Pending::Task(task)
+            })
+            .collect();
+
+        let batch = Batch {
+            id: batch_id,
+            created_at: timestamp,
+            tasks,
+            steps: Vec::new(),
+        };
+        Ok(Some(batch))
     }
 
     /// Handles the result from a batch processing.
     ///
     /// When a task is processed, the result of the processing is pushed to its event list. The
     /// handle batch result make sure that the new state is save into its store.
-    /// The tasks are then removed from the processing queue.
-    async fn handle_batch_result(&self, mut batch: Batch) -> Result<()> {
+    /// The tasks are then removed from the processing queue, and their index can be processed
+    /// again.
+    async fn handle_batch_result(
+        &mut self,
+        index_uid: Option<IndexUid>,
+        batch: Result<Batch>,
+    ) -> Result<()> {
+        match index_uid {
+            Some(index_uid) => {
+                self.processing_indexes.remove(&index_uid);
+            }
+            None => self.processing_job = false,
+        }
+
+        let mut batch = batch?;
         let tasks = self.store.update_tasks(batch.tasks).await?;
         batch.tasks = tasks;
-        self.store.delete_pending(&batch.tasks[0]).await;
+        record_batch(&self.store, &batch, Some(Utc::now())).await;
+        self.store.delete_pending(&batch.tasks).await;
         self.performer.finish(&batch).await;
         Ok(())
     }
 }
 
+/// Records the batch in the store. The batches made of a job are not recorded.
+async fn record_batch(store: &TaskStore, batch: &Batch, finished_at: Option<DateTime<Utc>>) {
+    if let Some(info) = BatchInfo::new(batch, finished_at) {
+        if let Err(e) = store.put_batch(info).await {
+            log::error!("an error occured while recording batch {}: {}", batch.id, e);
+        }
+    }
+}
+
+/// Returns the index targeted by the tasks of the batch, or `None` for a job.
+fn batch_index_uid(batch: &Batch) -> Option<IndexUid> {
+    batch.tasks.first().and_then(|task| match task {
+        Pending::Task(Task { index_uid, .. }) => Some(index_uid.clone()),
+        Pending::Job(_) => None,
+    })
+}
+
 #[cfg(test)]
 mod test {
+    use milli::update::IndexDocumentsMethod;
     use nelson::Mocker;
+    use uuid::Uuid;
 
     use crate::index_resolver::IndexUid;
     use crate::tasks::task::Task;
+    use crate::tasks::task_store::test::tmp_env;
     use crate::tasks::task_store::TaskFilter;
 
-    use super::super::task::{TaskContent, TaskEvent, TaskId, TaskResult};
+    use super::super::task::{DocumentDeletion, TaskContent, TaskEvent, TaskId, TaskResult};
     use super::super::MockTaskPerformer;
     use super::*;
 
@@ -143,6 +372,7 @@ mod test {
                     index_uid: IndexUid::new("Test".to_string()).unwrap(),
                     content: TaskContent::IndexDeletion,
                     events: vec![TaskEvent::Created(Utc::now())],
+                    not_before: None,
                 };
                 Ok(Some(task))
             });
@@ -151,13 +381,24 @@ mod test {
             .when::<(), Option<Pending<TaskId>>>("peek_pending_task")
             .then(|()| Some(Pending::Task(1)));
 
+        mocker
+            .when::<usize, Vec<TaskId>>("pending_task_ids")
+            .then(|_| vec![1]);
+
         let store = TaskStore::mock(mocker);
         let performer = Arc::new(MockTaskPerformer::new());
 
-        let scheduler = Scheduler {
+        let mut scheduler = Scheduler {
             store,
             performer,
-            task_store_check_interval: Duration::from_millis(1),
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 0,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
         };
 
         let batch = scheduler.prepare_batch().await.unwrap().unwrap();
@@ -170,6 +411,256 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_prepare_batch_groups_document_additions() {
+        let mocker = Mocker::default();
+
+        mocker
+            .when::<(TaskId, Option<TaskFilter>), Result<Task>>("get_task")
+            .then(|(id, _filter)| {
+                let content = match id {
+                    // an index deletion stops the batch
+                    3 => TaskContent::IndexDeletion,
+                    _ => TaskContent::DocumentAddition {
+                        content_uuid: Uuid::new_v4(),
+                        merge_strategy: IndexDocumentsMethod::ReplaceDocuments,
+                        primary_key: None,
+                        documents_count: 1,
+                        allow_index_creation: true,
+                        retry_of: None,
+                    },
+                };
+                Ok(Task {
+                    id,
+                    index_uid: IndexUid::new("Test".to_string()).unwrap(),
+                    content,
+                    events: vec![TaskEvent::Created(Utc::now())],
+                    not_before: None,
+                })
+            });
+
+        mocker
+            .when::<(), Option<Pending<TaskId>>>("peek_pending_task")
+            .then(|()| Some(Pending::Task(0)));
+
+        mocker
+            .when::<usize, Vec<TaskId>>("pending_task_ids")
+            .then(|_| vec![0, 1, 2, 3, 4]);
+
+        let store = TaskStore::mock(mocker);
+        let performer = Arc::new(MockTaskPerformer::new());
+
+        let mut scheduler = Scheduler {
+            store,
+            performer,
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 42,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
+        };
+
+        let batch = scheduler.prepare_batch().await.unwrap().unwrap();
+
+        assert_eq!(batch.id, 42);
+        assert_eq!(batch.len(), 3);
+        for (expected_id, task) in batch.tasks.iter().enumerate() {
+            match task {
+                Pending::Task(task) => {
+                    assert_eq!(task.id, expected_id as TaskId);
+                    assert!(matches!(
+                        task.events.last(),
+                        Some(TaskEvent::Batched { batch_id: 42, .. })
+                    ));
+                }
+                Pending::Job(_) => panic!("expected a task, found a job"),
+            }
+        }
+        assert_eq!(scheduler.next_batch_id, 43);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_between_prepare_and_process() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let index_uid = IndexUid::new_unchecked("test");
+        let content = TaskContent::DocumentDeletion(DocumentDeletion::Ids(Vec::new()));
+        let cancelled = store
+            .register(index_uid.clone(), content.clone(), None, None)
+            .await
+            .unwrap();
+        let other_id = store
+            .register(index_uid, content, None, None)
+            .await
+            .unwrap()
+            .id;
+
+        // The performer must only process the task that wasn't cancelled.
+        let mut performer = MockTaskPerformer::new();
+        performer.expect_process().once().returning(move |batch| {
+            assert_eq!(batch.len(), 1);
+            assert!(matches!(&batch.tasks[0], Pending::Task(task) if task.id == other_id));
+            batch
+        });
+
+        let mut scheduler = Scheduler {
+            store: store.clone(),
+            performer: Arc::new(performer),
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 0,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
+        };
+
+        let batch = scheduler.prepare_batch().await.unwrap().unwrap();
+        assert_eq!(batch.len(), 2);
+
+        store.cancel_task(cancelled.id, None).await.unwrap();
+        let (_, batch) = scheduler.process_batch(batch).await;
+        assert_eq!(batch.unwrap().len(), 1);
+
+        let cancelled = store.get_task(cancelled.id, None).await.unwrap();
+        assert!(cancelled.is_cancelled());
+        assert_eq!(cancelled.events.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_batch_skips_processing_indexes() {
+        let mocker = Mocker::default();
+
+        mocker
+            .when::<(TaskId, Option<TaskFilter>), Result<Task>>("get_task")
+            .then(|(id, _filter)| {
+                let index_uid = match id {
+                    0 => "processing",
+                    _ => "idle",
+                };
+                Ok(Task {
+                    id,
+                    index_uid: IndexUid::new_unchecked(index_uid),
+                    content: TaskContent::IndexDeletion,
+                    events: vec![TaskEvent::Created(Utc::now())],
+                    not_before: None,
+                })
+            });
+
+        mocker
+            .when::<(), Option<Pending<TaskId>>>("peek_pending_task")
+            .then(|()| Some(Pending::Task(0)));
+
+        mocker
+            .when::<usize, Vec<TaskId>>("pending_task_ids")
+            .then(|_| vec![0, 1]);
+
+        let store = TaskStore::mock(mocker);
+        let performer = Arc::new(MockTaskPerformer::new());
+
+        let mut scheduler = Scheduler {
+            store,
+            performer,
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 0,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
+        };
+        scheduler
+            .processing_indexes
+            .insert(IndexUid::new_unchecked("processing"));
+
+        let batch = scheduler.prepare_batch().await.unwrap().unwrap();
+
+        assert_eq!(batch.len(), 1);
+        assert!(
+            matches!(batch.tasks[0], Pending::Task(Task { id: 1, .. })),
+            "{:?}",
+            batch.tasks[0]
+        );
+        assert!(scheduler
+            .processing_indexes
+            .contains(&IndexUid::new_unchecked("idle")));
+        assert!(scheduler.pending_index_uids.contains_key(&0));
+    }
+
+    #[tokio::test]
+    async fn test_job_waits_for_processing_batches() {
+        let mocker = Mocker::default();
+        let mut job = Some(Job::Empty);
+        mocker
+            .when::<(), Option<Pending<TaskId>>>("peek_pending_task")
+            .then(move |()| job.take().map(Pending::Job));
+
+        let store = TaskStore::mock(mocker);
+        let performer = Arc::new(MockTaskPerformer::new());
+
+        let mut scheduler = Scheduler {
+            store,
+            performer,
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 0,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
+        };
+        scheduler
+            .processing_indexes
+            .insert(IndexUid::new_unchecked("test"));
+
+        // A batch is processing, the job must wait.
+        assert!(scheduler.prepare_batch().await.unwrap().is_none());
+        assert!(scheduler.waiting_job.is_some());
+
+        scheduler.processing_indexes.clear();
+        let batch = scheduler.prepare_batch().await.unwrap().unwrap();
+        assert!(matches!(batch.tasks[0], Pending::Job(Job::Empty)));
+        assert!(scheduler.processing_job);
+        // The job doesn't use up a batch id.
+        assert_eq!(scheduler.next_batch_id, 0);
+
+        // No other batch can be processed while the job is processing.
+        assert!(scheduler.prepare_batch().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_batch_paused() {
+        let mocker = Mocker::default();
+        mocker
+            .when::<(), Option<Pending<TaskId>>>("peek_pending_task")
+            .then(|()| Some(Pending::Task(1)));
+
+        let store = TaskStore::mock(mocker);
+        let performer = Arc::new(MockTaskPerformer::new());
+
+        let mut scheduler = Scheduler {
+            store,
+            performer,
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(true)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 0,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
+        };
+
+        // The pending task isn't taken while the scheduler is paused.
+        assert!(scheduler.prepare_batch().await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_prepare_batch_empty() {
         let mocker = Mocker::default();
@@ -180,10 +671,17 @@ mod test {
         let store = TaskStore::mock(mocker);
         let performer = Arc::new(MockTaskPerformer::new());
 
-        let scheduler = Scheduler {
+        let mut scheduler = Scheduler {
             store,
             performer,
-            task_store_check_interval: Duration::from_millis(1),
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 0,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
         };
 
         assert!(scheduler.prepare_batch().await.unwrap().is_none());
@@ -205,10 +703,15 @@ mod test {
                     index_uid: IndexUid::new("Test".to_string()).unwrap(),
                     content: TaskContent::IndexDeletion,
                     events: vec![TaskEvent::Created(Utc::now())],
+                    not_before: None,
                 };
                 Ok(task)
             });
 
+        mocker
+            .when::<usize, Vec<TaskId>>("pending_task_ids")
+            .then(|_| vec![1]);
+
         mocker
             .when::<Vec<Pending<Task>>, Result<Vec<Pending<Task>>>>("update_tasks")
             .times(2)
@@ -217,8 +720,24 @@ mod test {
                 Ok(tasks)
             });
 
+        let mut recorded = 0;
+        mocker
+            .when::<BatchInfo, Result<()>>("put_batch")
+            .times(2)
+            .then(move |batch| {
+                assert_eq!(batch.task_ids, vec![1]);
+                // the batch is recorded when it starts processing, and once it is finished.
+                assert_eq!(batch.finished_at.is_some(), recorded == 1);
+                recorded += 1;
+                Ok(())
+            });
+
         mocker.when::<(), ()>("delete_pending").once().then(|_| ());
 
+        mocker
+            .when::<(), Option<DateTime<Utc>>>("next_scheduled_at")
+            .then(|()| None);
+
         let store = TaskStore::mock(mocker);
 
         let mut performer = MockTaskPerformer::new();
@@ -241,7 +760,14 @@ mod test {
         let scheduler = Scheduler {
             store,
             performer,
-            task_store_check_interval: Duration::from_millis(1),
+            notifier: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            config: SchedulerConfig::default(),
+            next_batch_id: 0,
+            processing_indexes: HashSet::new(),
+            processing_job: false,
+            waiting_job: None,
+            pending_index_uids: HashMap::new(),
         };
 
         let handle = tokio::spawn(scheduler.run());
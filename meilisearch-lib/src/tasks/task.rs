@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
 use meilisearch_error::ResponseError;
-use milli::update::{DocumentAdditionResult, IndexDocumentsMethod};
+use milli::update::{DocumentAdditionResult, IndexDocumentsMethod, UpdateIndexingStep};
 use serde::{Deserialize, Serialize};
 use tokio::sync::oneshot;
 use uuid::Uuid;
@@ -16,12 +16,35 @@ use crate::{
 
 pub type TaskId = u64;
 
+/// A key sent by a client along with a request registering a task. While the key isn't expired,
+/// the requests sent with the same key return the task registered by the first one instead of
+/// registering a new task. A key can't be reused for a request targeting another index or
+/// carrying another payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotencyKey {
+    pub key: String,
+    pub expires_at: DateTime<Utc>,
+    /// A hash of the request the key was sent with
+    pub fingerprint: u64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 pub enum TaskResult {
-    DocumentAddition { indexed_documents: u64 },
-    DocumentDeletion { deleted_documents: u64 },
-    ClearAll { deleted_documents: u64 },
+    DocumentAddition {
+        indexed_documents: u64,
+    },
+    DocumentDeletion {
+        deleted_documents: u64,
+    },
+    ClearAll {
+        deleted_documents: u64,
+    },
+    /// The result of each operation of a transaction, in order.
+    #[cfg_attr(test, proptest(skip))]
+    Transaction {
+        results: Vec<TaskResult>,
+    },
     Other,
 }
 
@@ -53,6 +76,86 @@ pub enum TaskEvent {
         #[cfg_attr(test, proptest(strategy = "test::datetime_strategy()"))]
         timestamp: DateTime<Utc>,
     },
+    Cancelled(#[cfg_attr(test, proptest(strategy = "test::datetime_strategy()"))] DateTime<Utc>),
+}
+
+/// The progress of a processing document addition, as reported by the indexer. It is kept in
+/// memory while the task is processing, and is never persisted.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskProgress {
+    /// The name of the current indexing step
+    pub step: String,
+    /// The position of the current step, starting at 1
+    pub current_step: usize,
+    pub number_of_steps: usize,
+    pub indexed_documents: usize,
+    /// Unknown until the indexer has read all the documents
+    pub total_documents: Option<usize>,
+    /// The overall progress of the addition, between 0 and 100
+    pub percentage: f64,
+}
+
+impl TaskProgress {
+    /// Computes the progress from the step reported by the indexer. The previous progress is
+    /// used to keep the document counters during the steps that don't report them.
+    pub fn new(step: UpdateIndexingStep, previous: Option<&TaskProgress>) -> Self {
+        let previous_total = previous.and_then(|progress| progress.total_documents);
+        let step_index = step.step();
+        let number_of_steps = step.number_of_steps();
+        let (name, indexed_documents, total_documents, step_ratio) = match step {
+            UpdateIndexingStep::RemapDocumentAddition { documents_seen } => {
+                ("remapDocumentAddition", documents_seen, None, 0.0)
+            }
+            UpdateIndexingStep::ComputeIdsAndMergeDocuments {
+                documents_seen,
+                total_documents,
+            } => (
+                "computeIdsAndMergeDocuments",
+                documents_seen,
+                Some(total_documents),
+                ratio(documents_seen, total_documents),
+            ),
+            UpdateIndexingStep::IndexDocuments {
+                documents_seen,
+                total_documents,
+            } => (
+                "indexDocuments",
+                documents_seen,
+                Some(total_documents),
+                ratio(documents_seen, total_documents),
+            ),
+            UpdateIndexingStep::MergeDataIntoFinalDatabase {
+                databases_seen,
+                total_databases,
+            } => (
+                "mergeDataIntoFinalDatabase",
+                // All the documents have been indexed at this point.
+                previous_total.unwrap_or_default(),
+                previous_total,
+                ratio(databases_seen, total_databases),
+            ),
+        };
+
+        let percentage = (step_index as f64 + step_ratio) / number_of_steps as f64 * 100.0;
+
+        Self {
+            step: name.to_string(),
+            current_step: step_index + 1,
+            number_of_steps,
+            indexed_documents,
+            total_documents,
+            percentage: (percentage * 100.0).round() / 100.0,
+        }
+    }
+}
+
+fn ratio(seen: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        seen as f64 / total as f64
+    }
 }
 
 /// A task represents an operation that MeiliSearch must do.
@@ -66,17 +169,113 @@ pub struct Task {
     pub index_uid: IndexUid,
     pub content: TaskContent,
     pub events: Vec<TaskEvent>,
+    /// The date before which the task must not be processed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, proptest(value = "None"))]
+    pub not_before: Option<DateTime<Utc>>,
 }
 
 impl Task {
     /// Return true when a task is finished.
-    /// A task is finished when its last state is either `Succeeded` or `Failed`.
+    /// A task is finished when its last state is either `Succeeded`, `Failed` or `Cancelled`.
     pub fn is_finished(&self) -> bool {
         self.events.last().map_or(false, |event| {
-            matches!(event, TaskEvent::Succeded { .. } | TaskEvent::Failed { .. })
+            matches!(
+                event,
+                TaskEvent::Succeded { .. } | TaskEvent::Failed { .. } | TaskEvent::Cancelled(_)
+            )
         })
     }
 
+    /// Return the date at which the task was enqueued.
+    pub fn enqueued_at(&self) -> Option<DateTime<Utc>> {
+        match self.events.first()? {
+            TaskEvent::Created(timestamp) => Some(*timestamp),
+            _ => None,
+        }
+    }
+
+    /// Return the date at which the task finished, if it is finished.
+    pub fn finished_at(&self) -> Option<DateTime<Utc>> {
+        match self.events.last()? {
+            TaskEvent::Succeded { timestamp, .. } | TaskEvent::Failed { timestamp, .. } => {
+                Some(*timestamp)
+            }
+            TaskEvent::Cancelled(timestamp) => Some(*timestamp),
+            _ => None,
+        }
+    }
+
+    /// Return true when the task was cancelled before being processed.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.events.last(), Some(TaskEvent::Cancelled(_)))
+    }
+
+    /// Return true when the task is waiting for its `not_before` date to be processed.
+    pub fn is_scheduled(&self) -> bool {
+        matches!(self.events.last(), Some(TaskEvent::Created(_)))
+            && self
+                .not_before
+                .map_or(false, |not_before| not_before > Utc::now())
+    }
+
+    /// Return true when the task has been picked by the scheduler, and can't be cancelled anymore.
+    pub fn is_processing(&self) -> bool {
+        matches!(
+            self.events.last(),
+            Some(TaskEvent::Batched { .. }) | Some(TaskEvent::Processing(_))
+        )
+    }
+
+    /// Return true when the task is a document addition that failed. The update file of such a
+    /// task is kept for a grace period, so the task can be retried.
+    pub fn is_failed_document_addition(&self) -> bool {
+        matches!(self.content, TaskContent::DocumentAddition { .. })
+            && matches!(self.events.last(), Some(TaskEvent::Failed { .. }))
+    }
+
+    /// Return true when `other` can be processed in the same batch as this task.
+    /// Only document additions sharing the same index, merge strategy, primary key and
+    /// permission to create the index, or document deletions on the same index can be batched
+    /// together.
+    pub fn is_batchable_with(&self, other: &Task) -> bool {
+        if self.index_uid != other.index_uid {
+            return false;
+        }
+
+        match (&self.content, &other.content) {
+            (
+                TaskContent::DocumentAddition {
+                    merge_strategy: lhs_method,
+                    primary_key: lhs_primary_key,
+                    allow_index_creation: lhs_allow_index_creation,
+                    ..
+                },
+                TaskContent::DocumentAddition {
+                    merge_strategy: rhs_method,
+                    primary_key: rhs_primary_key,
+                    allow_index_creation: rhs_allow_index_creation,
+                    ..
+                },
+            ) => {
+                lhs_method == rhs_method
+                    && lhs_primary_key == rhs_primary_key
+                    && lhs_allow_index_creation == rhs_allow_index_creation
+            }
+            (
+                TaskContent::DocumentDeletion(DocumentDeletion::Ids(_)),
+                TaskContent::DocumentDeletion(DocumentDeletion::Ids(_)),
+            ) => true,
+            _ => false,
+        }
+    }
+
+    /// Return the uuids of all the update files used by the task, including the ones of the
+    /// document additions of a transaction.
+    pub fn content_uuids(&self) -> Vec<Uuid> {
+        self.content.content_uuids()
+    }
+
     /// Return the content_uuid of the `Task` if there is one.
     pub fn get_content_uuid(&self) -> Option<Uuid> {
         match self {
@@ -135,6 +334,9 @@ pub enum TaskContent {
         primary_key: Option<String>,
         documents_count: usize,
         allow_index_creation: bool,
+        /// The id of the failed task this addition retries.
+        #[serde(default)]
+        retry_of: Option<TaskId>,
     },
     DocumentDeletion(DocumentDeletion),
     SettingsUpdate {
@@ -150,6 +352,48 @@ pub enum TaskContent {
     IndexUpdate {
         primary_key: Option<String>,
     },
+    /// Several operations on the same index, that are applied in a single write transaction: either
+    /// all of them succeed, or none of them is applied.
+    Transaction {
+        #[cfg_attr(test, proptest(value = "Vec::new()"))]
+        operations: Vec<TransactionOperation>,
+        allow_index_creation: bool,
+    },
+}
+
+impl TaskContent {
+    /// Return the uuids of the update files used by the task.
+    pub fn content_uuids(&self) -> Vec<Uuid> {
+        match self {
+            TaskContent::DocumentAddition { content_uuid, .. } => vec![*content_uuid],
+            TaskContent::Transaction { operations, .. } => operations
+                .iter()
+                .filter_map(|operation| match operation {
+                    TransactionOperation::DocumentAddition { content_uuid, .. } => {
+                        Some(*content_uuid)
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// An operation of a `TaskContent::Transaction`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum TransactionOperation {
+    DocumentAddition {
+        content_uuid: Uuid,
+        merge_strategy: IndexDocumentsMethod,
+        primary_key: Option<String>,
+        documents_count: usize,
+    },
+    DocumentDeletion(DocumentDeletion),
+    SettingsUpdate {
+        settings: Settings<Unchecked>,
+    },
 }
 
 #[cfg(test)]
@@ -168,4 +412,55 @@ mod test {
     pub(super) fn datetime_strategy() -> impl Strategy<Value = DateTime<Utc>> {
         Just(Utc::now())
     }
+
+    #[test]
+    fn test_is_batchable_with() {
+        let addition = |id, allow_index_creation| Task {
+            id,
+            index_uid: IndexUid::new_unchecked("test"),
+            content: TaskContent::DocumentAddition {
+                content_uuid: Uuid::new_v4(),
+                merge_strategy: IndexDocumentsMethod::ReplaceDocuments,
+                primary_key: None,
+                documents_count: 1,
+                allow_index_creation,
+                retry_of: None,
+            },
+            events: vec![TaskEvent::Created(Utc::now())],
+            not_before: None,
+        };
+
+        assert!(addition(0, true).is_batchable_with(&addition(1, true)));
+        assert!(addition(0, false).is_batchable_with(&addition(1, false)));
+        // A task that can't create the index must not be processed along with one that can.
+        assert!(!addition(0, true).is_batchable_with(&addition(1, false)));
+        assert!(!addition(0, false).is_batchable_with(&addition(1, true)));
+    }
+
+    #[test]
+    fn test_task_progress() {
+        let progress = TaskProgress::new(
+            UpdateIndexingStep::IndexDocuments {
+                documents_seen: 50,
+                total_documents: 100,
+            },
+            None,
+        );
+        assert_eq!(progress.step, "indexDocuments");
+        assert_eq!(progress.current_step, 3);
+        assert_eq!(progress.indexed_documents, 50);
+        assert_eq!(progress.total_documents, Some(100));
+        assert_eq!(progress.percentage, 62.5);
+
+        let progress = TaskProgress::new(
+            UpdateIndexingStep::MergeDataIntoFinalDatabase {
+                databases_seen: 0,
+                total_databases: 10,
+            },
+            Some(&progress),
+        );
+        assert_eq!(progress.indexed_documents, 100);
+        assert_eq!(progress.total_documents, Some(100));
+        assert_eq!(progress.percentage, 75.0);
+    }
 }
@@ -1,21 +1,24 @@
 mod store;
 
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::io::{BufWriter, Write};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Arc, Mutex};
 
-use chrono::Utc;
-use heed::{Env, RwTxn};
+use chrono::{DateTime, Utc};
+use heed::{Env, RoTxn, RwTxn};
 use log::debug;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, mpsc, Notify, RwLock, RwLockWriteGuard};
 use uuid::Uuid;
 
+use super::batch::{BatchId, BatchInfo};
 use super::error::TaskError;
-use super::task::{Job, Task, TaskContent, TaskId};
+use super::task::{IdempotencyKey, Job, Task, TaskContent, TaskId};
 use super::Result;
 use crate::index_resolver::IndexUid;
+use crate::options::TaskRetentionConfig;
 use crate::tasks::task::TaskEvent;
 use crate::update_file_store::UpdateFileStore;
 
@@ -24,20 +27,34 @@ pub use store::test::MockStore as Store;
 #[cfg(not(test))]
 pub use store::Store;
 
+type FilterFn = Box<dyn Fn(&Task) -> bool + Sync + Send + 'static>;
+
 /// Defines constraints to be applied when querying for Tasks from the store.
-#[derive(Default, Debug)]
+#[derive(Default)]
 pub struct TaskFilter {
     indexes: Option<HashSet<String>>,
+    filter_fns: Vec<FilterFn>,
 }
 
 impl TaskFilter {
-    fn pass(&self, task: &Task) -> bool {
+    /// Returns whether the task matches the filter.
+    pub fn pass(&self, task: &Task) -> bool {
+        self.pass_index(&task.index_uid) && self.filter_fns.iter().all(|f| f(task))
+    }
+
+    /// Returns whether the index matches the indexes of the filter.
+    pub fn pass_index(&self, index_uid: &str) -> bool {
         self.indexes
             .as_ref()
-            .map(|indexes| indexes.contains(&*task.index_uid))
+            .map(|indexes| indexes.contains(index_uid))
             .unwrap_or(true)
     }
 
+    /// Adds a predicate to the filter, so the tasks must match this predicate.
+    pub fn filter_fn(&mut self, f: impl Fn(&Task) -> bool + Sync + Send + 'static) {
+        self.filter_fns.push(Box::new(f));
+    }
+
     /// Adds an index to the filter, so the filter must match this index.
     pub fn filter_index(&mut self, index: String) {
         self.indexes
@@ -93,6 +110,10 @@ impl Pending<Task> {
     }
 }
 
+/// The number of task updates that can be buffered for a subscriber before it starts missing
+/// updates.
+const TASK_UPDATES_CAPACITY: usize = 1024;
+
 impl Ord for Pending<TaskId> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.partial_cmp(other).unwrap()
@@ -102,6 +123,18 @@ impl Ord for Pending<TaskId> {
 pub struct TaskStore {
     store: Arc<Store>,
     pending_queue: Arc<RwLock<BinaryHeap<Pending<TaskId>>>>,
+    /// The ids of the tasks that are currently processing
+    processing: Arc<RwLock<HashSet<TaskId>>>,
+    /// Notified each time a task or a job is registered
+    notifier: Arc<Notify>,
+    /// Receives the state of each task every time it gains a new event
+    updates: broadcast::Sender<Task>,
+    /// Receives each task once it is finished, see `TaskStore::finished_tasks`
+    finished: Arc<Mutex<Option<mpsc::UnboundedSender<Task>>>>,
+    /// Whether the scheduler is paused, persisted in the store so it survives a restart
+    paused: Arc<AtomicBool>,
+    /// The `not_before` date of the pending tasks that must not be processed yet
+    scheduled: Arc<RwLock<HashMap<TaskId, DateTime<Utc>>>>,
 }
 
 impl Clone for TaskStore {
@@ -109,6 +142,12 @@ impl Clone for TaskStore {
         Self {
             store: self.store.clone(),
             pending_queue: self.pending_queue.clone(),
+            processing: self.processing.clone(),
+            notifier: self.notifier.clone(),
+            updates: self.updates.clone(),
+            finished: self.finished.clone(),
+            paused: self.paused.clone(),
+            scheduled: self.scheduled.clone(),
         }
     }
 }
@@ -117,19 +156,58 @@ impl TaskStore {
     pub fn new(env: Arc<heed::Env>) -> Result<Self> {
         let mut store = Store::new(env)?;
         let unfinished_tasks = store.reset_and_return_unfinished_tasks()?;
+        let paused = store.is_paused(&store.rtxn()?)?;
+
+        let mut scheduled = HashMap::new();
+        let txn = store.rtxn()?;
+        for pending in unfinished_tasks.iter() {
+            if let Pending::Task(id) = pending {
+                if let Some(not_before) = store.get(&txn, *id)?.and_then(|task| task.not_before) {
+                    scheduled.insert(*id, not_before);
+                }
+            }
+        }
+        drop(txn);
+
         let store = Arc::new(store);
 
         Ok(Self {
             store,
             pending_queue: Arc::new(RwLock::new(unfinished_tasks)),
+            processing: Default::default(),
+            notifier: Default::default(),
+            updates: broadcast::channel(TASK_UPDATES_CAPACITY).0,
+            finished: Default::default(),
+            paused: Arc::new(AtomicBool::new(paused)),
+            scheduled: Arc::new(RwLock::new(scheduled)),
         })
     }
 
-    pub async fn register(&self, index_uid: IndexUid, content: TaskContent) -> Result<Task> {
+    /// Registers a new task. When `not_before` is set, the task isn't processed before this date.
+    ///
+    /// When a task was already registered with the same `idempotency_key`, and the key isn't
+    /// expired, this task is returned and no new task is registered. It is an error to reuse the
+    /// key for another index or another request, i.e. with another fingerprint.
+    pub async fn register(
+        &self,
+        index_uid: IndexUid,
+        content: TaskContent,
+        not_before: Option<DateTime<Utc>>,
+        idempotency_key: Option<IdempotencyKey>,
+    ) -> Result<Task> {
         debug!("registering update: {:?}", content);
         let store = self.store.clone();
-        let task = tokio::task::spawn_blocking(move || -> Result<Task> {
+        let (task, is_new) = tokio::task::spawn_blocking(move || -> Result<(Task, bool)> {
             let mut txn = store.wtxn()?;
+            if let Some(ref key) = idempotency_key {
+                if let Some((task, fingerprint)) = get_idempotent_task(&store, &txn, &key.key)? {
+                    if task.index_uid != index_uid || fingerprint != key.fingerprint {
+                        return Err(TaskError::IdempotencyKeyReused(key.key.clone()));
+                    }
+                    return Ok((task, false));
+                }
+            }
+
             let next_task_id = store.next_task_id(&mut txn)?;
             let created_at = TaskEvent::Created(Utc::now());
             let task = Task {
@@ -137,8 +215,64 @@ impl TaskStore {
                 index_uid,
                 content,
                 events: vec![created_at],
+                not_before,
             };
 
+            store.put(&mut txn, &task)?;
+            if let Some(ref key) = idempotency_key {
+                store.put_idempotency_key(&mut txn, key, task.id)?;
+            }
+            txn.commit()?;
+
+            Ok((task, true))
+        })
+        .await??;
+
+        if !is_new {
+            debug!(
+                "task {} already registered with this idempotency key",
+                task.id
+            );
+            return Ok(task);
+        }
+
+        self.enqueue(&task).await;
+
+        Ok(task)
+    }
+
+    /// Registers the retry of a failed document addition, `content` reusing its update file. It
+    /// is an error if an unfinished task already uses this update file; it is checked in the same
+    /// write transaction as the registration, so that a task is never retried twice at once.
+    pub async fn register_retry(&self, index_uid: IndexUid, content: TaskContent) -> Result<Task> {
+        let (content_uuid, retried) = match content {
+            TaskContent::DocumentAddition {
+                content_uuid,
+                retry_of: Some(retried),
+                ..
+            } => (content_uuid, retried),
+            _ => unreachable!("only document additions can be retried"),
+        };
+
+        let store = self.store.clone();
+        let task = tokio::task::spawn_blocking(move || -> Result<Task> {
+            let mut txn = store.wtxn()?;
+
+            let mut filter = TaskFilter::default();
+            filter.filter_fn(move |task| {
+                !task.is_finished() && task.get_content_uuid() == Some(content_uuid)
+            });
+            if let Some(retry) = store.list_tasks(&txn, None, Some(filter), Some(1))?.first() {
+                return Err(TaskError::TaskAlreadyRetried(retried, retry.id));
+            }
+
+            let task = Task {
+                id: store.next_task_id(&mut txn)?,
+                index_uid,
+                content,
+                events: vec![TaskEvent::Created(Utc::now())],
+                not_before: None,
+            };
             store.put(&mut txn, &task)?;
             txn.commit()?;
 
@@ -146,12 +280,33 @@ impl TaskStore {
         })
         .await??;
 
+        self.enqueue(&task).await;
+
+        Ok(task)
+    }
+
+    /// Publishes a newly registered task and puts it in the pending queue.
+    async fn enqueue(&self, task: &Task) {
+        self.publish(task, 0);
+
+        if let Some(not_before) = task.not_before {
+            self.scheduled.write().await.insert(task.id, not_before);
+        }
         self.pending_queue
             .write()
             .await
             .push(Pending::Task(task.id));
+        self.notifier.notify_one();
+    }
 
-        Ok(task)
+    /// Returns the task registered with the idempotency key, if the key isn't expired.
+    pub async fn get_idempotent_task(&self, key: String) -> Result<Option<Task>> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || {
+            let txn = store.rtxn()?;
+            Ok(get_idempotent_task(&store, &txn, &key)?.map(|(task, _)| task))
+        })
+        .await?
     }
 
     pub fn register_raw_update(&self, wtxn: &mut RwTxn, task: &Task) -> Result<()> {
@@ -164,30 +319,222 @@ impl TaskStore {
     pub async fn register_job(&self, content: Job) {
         debug!("registering a job: {:?}", content);
         self.pending_queue.write().await.push(Pending::Job(content));
+        self.notifier.notify_one();
+    }
+
+    /// Returns the `Notify` that is notified each time a task or a job is registered.
+    pub fn notifier(&self) -> Arc<Notify> {
+        self.notifier.clone()
+    }
+
+    /// Returns the flag telling the scheduler whether it must stop processing tasks.
+    pub fn paused(&self) -> Arc<AtomicBool> {
+        self.paused.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes the processing of the tasks. When paused, the scheduler finishes the
+    /// batches it is processing and stops taking new tasks, but the jobs, such as dumps, are
+    /// still processed.
+    pub async fn set_paused(&self, paused: bool) -> Result<()> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut txn = store.wtxn()?;
+            store.set_paused(&mut txn, paused)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await??;
+
+        self.paused.store(paused, atomic::Ordering::Relaxed);
+        // Wake the scheduler up, so it picks the pending tasks when it is resumed.
+        self.notifier.notify_one();
+
+        Ok(())
+    }
+
+    /// Returns a receiver that gets the state of a task every time it gains a new event, i.e.
+    /// when it is registered, batched, processed, finished, or cancelled.
+    pub fn subscribe(&self) -> broadcast::Receiver<Task> {
+        self.updates.subscribe()
     }
 
-    /// Returns the next task to process.
+    /// Returns a receiver that gets each task once it is finished, i.e. succeeded, failed or
+    /// cancelled. Unlike the subscribers, the receiver never misses a task: the finished tasks
+    /// are queued until they are read. Only the last returned receiver gets the tasks.
+    pub fn finished_tasks(&self) -> mpsc::UnboundedReceiver<Task> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        *self.finished.lock().unwrap() = Some(sender);
+        receiver
+    }
+
+    /// Sends the state of the task after each of its events, starting from the `from`th event,
+    /// to the subscribers.
+    fn publish(&self, task: &Task, from: usize) {
+        for len in from + 1..=task.events.len() {
+            let mut state = task.clone();
+            state.events.truncate(len);
+            // An error only means that nobody is listening.
+            let _ = self.updates.send(state);
+        }
+
+        if task.is_finished() && from < task.events.len() {
+            let mut finished = self.finished.lock().unwrap();
+            let is_closed = finished
+                .as_ref()
+                .map_or(false, |sender| sender.send(task.clone()).is_err());
+            // The receiver was dropped, stop queuing the finished tasks.
+            if is_closed {
+                *finished = None;
+            }
+        }
+    }
+
+    /// Returns the next task to process. The tasks that are scheduled after now are skipped.
     pub async fn peek_pending_task(&self) -> Option<Pending<TaskId>> {
         let mut pending_queue = self.pending_queue.write().await;
         loop {
             match pending_queue.peek()? {
                 Pending::Job(Job::Empty) => drop(pending_queue.pop()),
-                _ => return Some(pending_queue.peek_mut()?.take()),
+                Pending::Job(_) => return Some(pending_queue.peek_mut()?.take()),
+                // The jobs always come before the tasks, so only tasks are left in the queue.
+                Pending::Task(_) => break,
             }
         }
+
+        let scheduled = self.not_due_tasks().await;
+        pending_queue
+            .iter()
+            .filter_map(|pending| match pending {
+                Pending::Task(id) if !scheduled.contains_key(id) => Some(*id),
+                _ => None,
+            })
+            .min()
+            .map(Pending::Task)
     }
 
-    /// Returns the next task to process if there is one.
-    pub async fn get_processing_task(&self) -> Result<Option<Task>> {
-        match self.peek_pending_task().await {
-            Some(Pending::Task(tid)) => {
-                let task = self.get_task(tid, None).await?;
-                Ok(matches!(task.events.last(), Some(TaskEvent::Processing(_))).then(|| task))
-            }
-            _ => Ok(None),
+    /// Returns the scheduled tasks that are not due yet, and forgets about the others.
+    async fn not_due_tasks(&self) -> RwLockWriteGuard<'_, HashMap<TaskId, DateTime<Utc>>> {
+        let mut scheduled = self.scheduled.write().await;
+        let now = Utc::now();
+        scheduled.retain(|_, not_before| *not_before > now);
+        scheduled
+    }
+
+    /// Returns the date at which the next scheduled task becomes due, if any.
+    pub async fn next_scheduled_at(&self) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        self.scheduled
+            .read()
+            .await
+            .values()
+            .filter(|not_before| **not_before > now)
+            .min()
+            .copied()
+    }
+
+    /// Returns the ids of the `limit` first pending tasks, in the order they must be processed.
+    /// The volatile jobs and the tasks that are scheduled after now are ignored.
+    pub async fn pending_task_ids(&self, limit: usize) -> Vec<TaskId> {
+        let pending_queue = self.pending_queue.read().await;
+        let scheduled = self.not_due_tasks().await;
+        let mut ids = pending_queue
+            .iter()
+            .filter_map(|pending| match pending {
+                Pending::Task(id) if !scheduled.contains_key(id) => Some(*id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.truncate(limit);
+        ids
+    }
+
+    /// Returns the id of the next batch to be created.
+    pub fn next_batch_id(&self) -> Result<BatchId> {
+        let txn = self.store.rtxn()?;
+        let next_batch_id = self
+            .store
+            .last_batch_id(&txn)?
+            .map_or(0, |batch_id| batch_id + 1);
+        Ok(next_batch_id)
+    }
+
+    /// Records a batch, or updates its record.
+    pub async fn put_batch(&self, batch: BatchInfo) -> Result<()> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || -> Result<_> {
+            let mut txn = store.wtxn()?;
+            store.put_batch(&mut txn, &batch)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Returns the batch, if its index matches the filter.
+    pub async fn get_batch(&self, id: BatchId, filter: Option<TaskFilter>) -> Result<BatchInfo> {
+        let store = self.store.clone();
+        let batch = tokio::task::spawn_blocking(move || -> Result<_> {
+            let txn = store.rtxn()?;
+            let batch = store.get_batch(&txn, id)?;
+            Ok(batch)
+        })
+        .await??
+        .ok_or(TaskError::UnexistingBatch(id))?;
+
+        match filter {
+            Some(filter) => filter
+                .pass_index(&batch.index_uid)
+                .then(|| batch)
+                .ok_or(TaskError::UnexistingBatch(id)),
+            None => Ok(batch),
         }
     }
 
+    /// Returns the batches whose index matches the filter, from the most recent to the oldest.
+    pub async fn list_batches(
+        &self,
+        from: Option<BatchId>,
+        filter: Option<TaskFilter>,
+        limit: Option<usize>,
+    ) -> Result<Vec<BatchInfo>> {
+        let store = self.store.clone();
+        let filter = filter.unwrap_or_default();
+
+        tokio::task::spawn_blocking(move || {
+            let txn = store.rtxn()?;
+            let batches = store.list_batches(
+                &txn,
+                from,
+                |batch| filter.pass_index(&batch.index_uid),
+                limit,
+            )?;
+            Ok(batches)
+        })
+        .await?
+    }
+
+    /// Returns the tasks that are currently processing.
+    pub async fn get_processing_tasks(&self) -> Result<Vec<Task>> {
+        let ids = self
+            .processing
+            .read()
+            .await
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        let mut tasks = Vec::with_capacity(ids.len());
+        for id in ids {
+            tasks.push(self.get_task(id, None).await?);
+        }
+
+        Ok(tasks)
+    }
+
     pub async fn get_task(&self, id: TaskId, filter: Option<TaskFilter>) -> Result<Task> {
         let store = self.store.clone();
         let task = tokio::task::spawn_blocking(move || -> Result<_> {
@@ -207,46 +554,218 @@ impl TaskStore {
         }
     }
 
+    /// Saves the new state of the tasks, and returns them. A task that was finished in the
+    /// meantime, i.e. cancelled after it was put in a batch, is left untouched and removed from
+    /// the returned tasks.
     pub async fn update_tasks(&self, tasks: Vec<Pending<Task>>) -> Result<Vec<Pending<Task>>> {
         let store = self.store.clone();
 
-        let tasks = tokio::task::spawn_blocking(move || -> Result<_> {
+        let (tasks, known_events) = tokio::task::spawn_blocking(move || -> Result<_> {
             let mut txn = store.wtxn()?;
+            let mut updated = Vec::with_capacity(tasks.len());
+            // The number of events each task had before this update.
+            let mut known_events = Vec::with_capacity(tasks.len());
 
-            for task in &tasks {
+            for task in tasks {
                 match task {
-                    Pending::Task(task) => store.put(&mut txn, task)?,
-                    Pending::Job(_) => (),
+                    Pending::Task(task) => {
+                        let stored = store.get(&txn, task.id)?;
+                        if stored.as_ref().map_or(false, Task::is_finished) {
+                            debug!("task {} was finished while it was batched", task.id);
+                            continue;
+                        }
+
+                        known_events.push(stored.map_or(0, |t| t.events.len()));
+                        store.put(&mut txn, &task)?;
+                        updated.push(Pending::Task(task));
+                    }
+                    Pending::Job(job) => {
+                        known_events.push(0);
+                        updated.push(Pending::Job(job));
+                    }
                 }
             }
 
             txn.commit()?;
 
+            Ok((updated, known_events))
+        })
+        .await??;
+
+        let mut processing = self.processing.write().await;
+        for (task, known) in tasks.iter().zip(known_events) {
+            if let Pending::Task(task) = task {
+                if matches!(task.events.last(), Some(TaskEvent::Processing(_))) {
+                    processing.insert(task.id);
+                } else {
+                    processing.remove(&task.id);
+                }
+                self.publish(task, known);
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    /// Delete the tasks from the queue and remove all `Empty` job.
+    pub async fn delete_pending(&self, to_delete: &[Pending<Task>]) {
+        let to_delete = to_delete
+            .iter()
+            .filter_map(|pending| match pending {
+                Pending::Task(Task { id, .. }) => Some(*id),
+                Pending::Job(_) => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let mut pending_queue = self.pending_queue.write().await;
+        *pending_queue = std::mem::take(&mut *pending_queue)
+            .into_iter()
+            .filter(|pending| match pending {
+                Pending::Job(Job::Empty) => false,
+                Pending::Task(id) => !to_delete.contains(id),
+                _ => true,
+            })
+            .collect::<BinaryHeap<Pending<TaskId>>>();
+    }
+
+    /// Cancels a pending task. The task is removed from the pending queue and a `Cancelled` event
+    /// is appended to its events. Tasks that are processing or already finished can't be cancelled.
+    pub async fn cancel_task(&self, id: TaskId, filter: Option<TaskFilter>) -> Result<Task> {
+        // The task may have been put in a batch that is not saved as processing yet, in which case
+        // the task is cancelled anyway: the scheduler drops it from the batch when it saves the
+        // batch, see `update_tasks`.
+        let mut pending_queue = self.pending_queue.write().await;
+        let task = self.get_task(id, filter).await?;
+
+        if task.is_processing() {
+            return Err(TaskError::TaskAlreadyProcessing(id));
+        }
+
+        if task.is_finished() {
+            return Err(TaskError::TaskAlreadyFinished(id));
+        }
+
+        let mut tasks = self.cancel(&mut pending_queue, vec![task]).await?;
+        Ok(tasks.remove(0))
+    }
+
+    /// Cancels all the pending tasks of an index, and returns them. The tasks that are currently
+    /// processing are left untouched.
+    pub async fn cancel_index_tasks(&self, index_uid: String) -> Result<Vec<Task>> {
+        let mut pending_queue = self.pending_queue.write().await;
+        let ids = pending_queue
+            .iter()
+            .filter_map(|pending| match pending {
+                Pending::Task(id) => Some(*id),
+                Pending::Job(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        let store = self.store.clone();
+        let tasks = tokio::task::spawn_blocking(move || -> Result<Vec<Task>> {
+            let txn = store.rtxn()?;
+            let mut tasks = Vec::new();
+            for id in ids {
+                match store.get(&txn, id)? {
+                    Some(task)
+                        if *task.index_uid == index_uid
+                            && !task.is_processing()
+                            && !task.is_finished() =>
+                    {
+                        tasks.push(task)
+                    }
+                    _ => (),
+                }
+            }
+            Ok(tasks)
+        })
+        .await??;
+
+        self.cancel(&mut pending_queue, tasks).await
+    }
+
+    /// Marks the tasks as cancelled in the store and removes them from the pending queue.
+    async fn cancel(
+        &self,
+        pending_queue: &mut BinaryHeap<Pending<TaskId>>,
+        mut tasks: Vec<Task>,
+    ) -> Result<Vec<Task>> {
+        let timestamp = Utc::now();
+        for task in &mut tasks {
+            task.events.push(TaskEvent::Cancelled(timestamp));
+        }
+
+        let store = self.store.clone();
+        let tasks = tokio::task::spawn_blocking(move || -> Result<_> {
+            let mut txn = store.wtxn()?;
+            for task in &tasks {
+                store.put(&mut txn, task)?;
+            }
+            txn.commit()?;
+
             Ok(tasks)
         })
         .await??;
 
+        for task in &tasks {
+            self.publish(task, task.events.len() - 1);
+        }
+
+        let cancelled = tasks.iter().map(|task| task.id).collect::<HashSet<_>>();
+        *pending_queue = std::mem::take(pending_queue)
+            .into_iter()
+            .filter(|pending| match pending {
+                Pending::Task(id) => !cancelled.contains(id),
+                Pending::Job(_) => true,
+            })
+            .collect();
+        self.scheduled
+            .write()
+            .await
+            .retain(|id, _| !cancelled.contains(id));
+
         Ok(tasks)
     }
 
-    /// Delete one task from the queue and remove all `Empty` job.
-    pub async fn delete_pending(&self, to_delete: &Pending<Task>) {
-        if let Pending::Task(Task { id: pending_id, .. }) = to_delete {
-            let mut pending_queue = self.pending_queue.write().await;
-            *pending_queue = std::mem::take(&mut *pending_queue)
-                .into_iter()
-                .filter(|pending| match pending {
-                    Pending::Job(Job::Empty) => false,
-                    Pending::Task(id) => pending_id != id,
-                    _ => true,
-                })
-                .collect::<BinaryHeap<Pending<TaskId>>>();
+    /// Deletes the finished tasks that don't match the retention policy anymore, and returns the
+    /// number of deleted tasks. The update files of the failed document additions whose grace
+    /// period is over, or that were deleted, are removed from the `update_file_store`.
+    pub async fn prune(
+        &self,
+        config: TaskRetentionConfig,
+        update_file_store: UpdateFileStore,
+    ) -> Result<usize> {
+        let store = self.store.clone();
+        let (deleted, payloads) = tokio::task::spawn_blocking(move || -> Result<_> {
+            let older_than = config
+                .task_retention_max_age_sec
+                .map(|secs| Utc::now() - chrono::Duration::seconds(secs as i64));
+            let payloads_older_than = Utc::now()
+                - chrono::Duration::seconds(config.failed_task_payload_retention_sec as i64);
+
+            let mut txn = store.wtxn()?;
+            let deleted = store.prune(&mut txn, older_than, config.task_retention_max_count)?;
+            let payloads = store.expired_payloads(&txn, payloads_older_than, &deleted)?;
+            store.prune_idempotency_keys(&mut txn, Utc::now())?;
+            txn.commit()?;
+
+            Ok((deleted.len(), payloads))
+        })
+        .await??;
+
+        for content_uuid in payloads {
+            // The file may already have been deleted by a previous run.
+            if let Err(e) = update_file_store.delete(content_uuid).await {
+                debug!("error deleting update file {}: {}", content_uuid, e);
+            }
         }
+
+        Ok(deleted)
     }
 
     pub async fn list_tasks(
         &self,
-        offset: Option<TaskId>,
+        from: Option<TaskId>,
         filter: Option<TaskFilter>,
         limit: Option<usize>,
     ) -> Result<Vec<Task>> {
@@ -254,7 +773,7 @@ impl TaskStore {
 
         tokio::task::spawn_blocking(move || {
             let txn = store.rtxn()?;
-            let tasks = store.list_tasks(&txn, offset, filter, limit)?;
+            let tasks = store.list_tasks(&txn, from, filter, limit)?;
             Ok(tasks)
         })
         .await?
@@ -280,9 +799,17 @@ impl TaskStore {
                 updates_file.write_all(b"\n")?;
 
                 if !task.is_finished() {
-                    if let Some(content_uuid) = task.get_content_uuid() {
+                    for content_uuid in task.content_uuids() {
                         update_file_store.dump(content_uuid, &dir_path)?;
                     }
+                } else if task.is_failed_document_addition() {
+                    // The payload of a failed document addition is kept so the task can be
+                    // retried, unless its grace period is already over.
+                    for content_uuid in task.content_uuids() {
+                        if update_file_store.get_update(content_uuid).is_ok() {
+                            update_file_store.dump(content_uuid, &dir_path)?;
+                        }
+                    }
                 }
             }
             updates_file.flush()?;
@@ -313,11 +840,24 @@ impl TaskStore {
     }
 }
 
+/// Returns the task registered with the idempotency key, and the fingerprint of the request that
+/// registered it. The key is ignored if it is expired, or if its task was deleted by the retention
+/// policy.
+fn get_idempotent_task(store: &Store, txn: &RoTxn, key: &str) -> Result<Option<(Task, u64)>> {
+    match store.get_idempotent_task_id(txn, key)? {
+        Some((id, fingerprint)) => Ok(store.get(txn, id)?.map(|task| (task, fingerprint))),
+        None => Ok(None),
+    }
+}
+
 #[cfg(test)]
 pub mod test {
-    use crate::tasks::task_store::store::test::tmp_env;
+    pub use crate::tasks::task_store::store::test::tmp_env;
+
+    use meilisearch_error::{Code, ResponseError};
 
     use super::*;
+    use crate::tasks::task::TaskResult;
 
     use nelson::Mocker;
     use proptest::{
@@ -358,13 +898,27 @@ pub mod test {
             }
         }
 
-        pub async fn delete_pending(&self, to_delete: &Pending<Task>) {
+        pub async fn delete_pending(&self, to_delete: &[Pending<Task>]) {
             match self {
                 Self::Real(s) => s.delete_pending(to_delete).await,
                 Self::Mock(m) => unsafe { m.get("delete_pending").call(to_delete) },
             }
         }
 
+        pub async fn cancel_task(&self, id: TaskId, filter: Option<TaskFilter>) -> Result<Task> {
+            match self {
+                Self::Real(s) => s.cancel_task(id, filter).await,
+                Self::Mock(m) => unsafe { m.get("cancel_task").call((id, filter)) },
+            }
+        }
+
+        pub async fn cancel_index_tasks(&self, index_uid: String) -> Result<Vec<Task>> {
+            match self {
+                Self::Real(s) => s.cancel_index_tasks(index_uid).await,
+                Self::Mock(m) => unsafe { m.get("cancel_index_tasks").call(index_uid) },
+            }
+        }
+
         pub async fn get_task(&self, id: TaskId, filter: Option<TaskFilter>) -> Result<Task> {
             match self {
                 Self::Real(s) => s.get_task(id, filter).await,
@@ -372,11 +926,41 @@ pub mod test {
             }
         }
 
-        pub async fn get_processing_task(&self) -> Result<Option<Task>> {
+        pub async fn put_batch(&self, batch: BatchInfo) -> Result<()> {
+            match self {
+                Self::Real(s) => s.put_batch(batch).await,
+                Self::Mock(m) => unsafe { m.get("put_batch").call(batch) },
+            }
+        }
+
+        pub async fn get_batch(
+            &self,
+            id: BatchId,
+            filter: Option<TaskFilter>,
+        ) -> Result<BatchInfo> {
             match self {
-                Self::Real(s) => s.get_processing_task().await,
+                Self::Real(s) => s.get_batch(id, filter).await,
+                Self::Mock(m) => unsafe { m.get("get_batch").call((id, filter)) },
+            }
+        }
+
+        pub async fn list_batches(
+            &self,
+            from: Option<BatchId>,
+            filter: Option<TaskFilter>,
+            limit: Option<usize>,
+        ) -> Result<Vec<BatchInfo>> {
+            match self {
+                Self::Real(s) => s.list_batches(from, filter, limit).await,
+                Self::Mock(_m) => todo!(),
+            }
+        }
+
+        pub async fn get_processing_tasks(&self) -> Result<Vec<Task>> {
+            match self {
+                Self::Real(s) => s.get_processing_tasks().await,
                 Self::Mock(m) => unsafe {
-                    m.get::<_, Result<Option<Task>>>("get_pending_task")
+                    m.get::<_, Result<Vec<Task>>>("get_processing_tasks")
                         .call(())
                 },
             }
@@ -392,6 +976,27 @@ pub mod test {
             }
         }
 
+        pub async fn pending_task_ids(&self, limit: usize) -> Vec<TaskId> {
+            match self {
+                Self::Real(s) => s.pending_task_ids(limit).await,
+                Self::Mock(m) => unsafe { m.get::<_, Vec<TaskId>>("pending_task_ids").call(limit) },
+            }
+        }
+
+        pub async fn next_scheduled_at(&self) -> Option<DateTime<Utc>> {
+            match self {
+                Self::Real(s) => s.next_scheduled_at().await,
+                Self::Mock(m) => unsafe { m.get("next_scheduled_at").call(()) },
+            }
+        }
+
+        pub fn next_batch_id(&self) -> Result<BatchId> {
+            match self {
+                Self::Real(s) => s.next_batch_id(),
+                Self::Mock(m) => unsafe { m.get::<_, Result<BatchId>>("next_batch_id").call(()) },
+            }
+        }
+
         pub async fn list_tasks(
             &self,
             from: Option<TaskId>,
@@ -404,6 +1009,17 @@ pub mod test {
             }
         }
 
+        pub async fn prune(
+            &self,
+            config: TaskRetentionConfig,
+            update_file_store: UpdateFileStore,
+        ) -> Result<usize> {
+            match self {
+                Self::Real(s) => s.prune(config, update_file_store).await,
+                Self::Mock(m) => unsafe { m.get("prune").call(config) },
+            }
+        }
+
         pub async fn dump(&self, path: &Path, update_file_store: UpdateFileStore) -> Result<()> {
             match self {
                 Self::Real(s) => s.dump(path, update_file_store).await,
@@ -411,13 +1027,40 @@ pub mod test {
             }
         }
 
-        pub async fn register(&self, index_uid: IndexUid, content: TaskContent) -> Result<Task> {
+        pub async fn register(
+            &self,
+            index_uid: IndexUid,
+            content: TaskContent,
+            not_before: Option<DateTime<Utc>>,
+            idempotency_key: Option<IdempotencyKey>,
+        ) -> Result<Task> {
+            match self {
+                Self::Real(s) => {
+                    s.register(index_uid, content, not_before, idempotency_key)
+                        .await
+                }
+                Self::Mock(_m) => todo!(),
+            }
+        }
+
+        pub async fn register_retry(
+            &self,
+            index_uid: IndexUid,
+            content: TaskContent,
+        ) -> Result<Task> {
             match self {
-                Self::Real(s) => s.register(index_uid, content).await,
+                Self::Real(s) => s.register_retry(index_uid, content).await,
                 Self::Mock(_m) => todo!(),
             }
         }
 
+        pub async fn get_idempotent_task(&self, key: String) -> Result<Option<Task>> {
+            match self {
+                Self::Real(s) => s.get_idempotent_task(key).await,
+                Self::Mock(m) => unsafe { m.get("get_idempotent_task").call(key) },
+            }
+        }
+
         pub fn register_raw_update(&self, wtxn: &mut RwTxn, task: &Task) -> Result<()> {
             match self {
                 Self::Real(s) => s.register_raw_update(wtxn, task),
@@ -432,6 +1075,48 @@ pub mod test {
             }
         }
 
+        pub fn notifier(&self) -> Arc<Notify> {
+            match self {
+                Self::Real(s) => s.notifier(),
+                Self::Mock(_m) => todo!(),
+            }
+        }
+
+        pub fn subscribe(&self) -> broadcast::Receiver<Task> {
+            match self {
+                Self::Real(s) => s.subscribe(),
+                Self::Mock(_m) => todo!(),
+            }
+        }
+
+        pub fn finished_tasks(&self) -> mpsc::UnboundedReceiver<Task> {
+            match self {
+                Self::Real(s) => s.finished_tasks(),
+                Self::Mock(_m) => todo!(),
+            }
+        }
+
+        pub fn paused(&self) -> Arc<AtomicBool> {
+            match self {
+                Self::Real(s) => s.paused(),
+                Self::Mock(_m) => todo!(),
+            }
+        }
+
+        pub fn is_paused(&self) -> bool {
+            match self {
+                Self::Real(s) => s.is_paused(),
+                Self::Mock(m) => unsafe { m.get("is_paused").call(()) },
+            }
+        }
+
+        pub async fn set_paused(&self, paused: bool) -> Result<()> {
+            match self {
+                Self::Real(s) => s.set_paused(paused).await,
+                Self::Mock(m) => unsafe { m.get("set_paused").call(paused) },
+            }
+        }
+
         pub fn load_dump(path: impl AsRef<Path>, env: Arc<Env>) -> anyhow::Result<()> {
             TaskStore::load_dump(path, env)
         }
@@ -451,6 +1136,7 @@ pub mod test {
             index_uid: IndexUid::new_unchecked("test"),
             content: TaskContent::IndexCreation { primary_key: None },
             events: Vec::new(),
+            not_before: None,
         };
 
         let mut runner = TestRunner::new(Config::default());
@@ -477,4 +1163,403 @@ pub mod test {
             })
             .unwrap();
     }
+
+    #[actix_rt::test]
+    async fn test_cancel_task() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+
+        let index_uid = IndexUid::new_unchecked("test");
+        let first = store
+            .register(index_uid.clone(), TaskContent::IndexDeletion, None, None)
+            .await
+            .unwrap();
+        let second = store
+            .register(index_uid, TaskContent::IndexDeletion, None, None)
+            .await
+            .unwrap();
+
+        let cancelled = store.cancel_task(first.id, None).await.unwrap();
+        assert!(cancelled.is_cancelled());
+        assert!(store.get_task(first.id, None).await.unwrap().is_cancelled());
+        assert_eq!(store.pending_task_ids(usize::MAX).await, vec![second.id]);
+
+        assert!(matches!(
+            store.cancel_task(first.id, None).await,
+            Err(TaskError::TaskAlreadyFinished(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_update_tasks_skips_cancelled_tasks() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+
+        let index_uid = IndexUid::new_unchecked("test");
+        let mut cancelled = store
+            .register(index_uid.clone(), TaskContent::IndexDeletion, None, None)
+            .await
+            .unwrap();
+        let mut other = store
+            .register(index_uid, TaskContent::IndexDeletion, None, None)
+            .await
+            .unwrap();
+
+        // The tasks are batched, but the batch is not saved yet when the first task is cancelled.
+        store.cancel_task(cancelled.id, None).await.unwrap();
+        for task in [&mut cancelled, &mut other] {
+            task.events.push(TaskEvent::Processing(Utc::now()));
+        }
+
+        let updated = store
+            .update_tasks(vec![Pending::Task(cancelled.clone()), Pending::Task(other)])
+            .await
+            .unwrap();
+        assert!(matches!(&updated[..], [Pending::Task(task)] if task.id != cancelled.id));
+        assert!(store
+            .get_task(cancelled.id, None)
+            .await
+            .unwrap()
+            .is_cancelled());
+        assert_eq!(store.get_processing_tasks().await.unwrap().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_register_notifies_scheduler() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let notifier = store.notifier();
+
+        store
+            .register(
+                IndexUid::new_unchecked("test"),
+                TaskContent::IndexDeletion,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // The notification is stored until someone waits on it.
+        tokio::time::timeout(std::time::Duration::from_secs(1), notifier.notified())
+            .await
+            .expect("the scheduler should have been notified");
+    }
+
+    #[actix_rt::test]
+    async fn test_register_with_idempotency_key() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let index_uid = IndexUid::new_unchecked("test");
+        let key = IdempotencyKey {
+            key: "my-key".to_string(),
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            fingerprint: 0,
+        };
+
+        let first = store
+            .register(
+                index_uid.clone(),
+                TaskContent::IndexDeletion,
+                None,
+                Some(key.clone()),
+            )
+            .await
+            .unwrap();
+        let second = store
+            .register(
+                index_uid.clone(),
+                TaskContent::IndexDeletion,
+                None,
+                Some(key.clone()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(store.pending_task_ids(usize::MAX).await, vec![first.id]);
+        assert_eq!(
+            store
+                .get_idempotent_task("my-key".to_string())
+                .await
+                .unwrap(),
+            Some(first.clone())
+        );
+
+        let expired = IdempotencyKey {
+            expires_at: Utc::now() - chrono::Duration::seconds(1),
+            ..key
+        };
+        let third = store
+            .register(index_uid, TaskContent::IndexDeletion, None, Some(expired))
+            .await
+            .unwrap();
+        assert_ne!(first.id, third.id);
+    }
+
+    #[actix_rt::test]
+    async fn test_reuse_idempotency_key_for_another_request() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let key = IdempotencyKey {
+            key: "my-key".to_string(),
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            fingerprint: 0,
+        };
+
+        let first = store
+            .register(
+                IndexUid::new_unchecked("test"),
+                TaskContent::IndexDeletion,
+                None,
+                Some(key.clone()),
+            )
+            .await
+            .unwrap();
+
+        // The task of another index is never returned.
+        let other_index = store
+            .register(
+                IndexUid::new_unchecked("other"),
+                TaskContent::IndexDeletion,
+                None,
+                Some(key.clone()),
+            )
+            .await;
+        assert!(matches!(
+            other_index,
+            Err(TaskError::IdempotencyKeyReused(ref key)) if key == "my-key"
+        ));
+
+        let other_request = store
+            .register(
+                IndexUid::new_unchecked("test"),
+                TaskContent::IndexDeletion,
+                None,
+                Some(IdempotencyKey {
+                    fingerprint: 1,
+                    ..key
+                }),
+            )
+            .await;
+        assert!(matches!(
+            other_request,
+            Err(TaskError::IdempotencyKeyReused(_))
+        ));
+
+        assert_eq!(store.pending_task_ids(usize::MAX).await, vec![first.id]);
+    }
+
+    #[actix_rt::test]
+    async fn test_scheduled_tasks_are_skipped_until_due() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let index_uid = IndexUid::new_unchecked("test");
+        let not_before = Utc::now() + chrono::Duration::hours(1);
+
+        let scheduled = store
+            .register(
+                index_uid.clone(),
+                TaskContent::IndexDeletion,
+                Some(not_before),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(scheduled.is_scheduled());
+        assert!(store.peek_pending_task().await.is_none());
+        assert_eq!(store.next_scheduled_at().await, Some(not_before));
+
+        let task = store
+            .register(index_uid.clone(), TaskContent::IndexDeletion, None, None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.peek_pending_task().await,
+            Some(Pending::Task(id)) if id == task.id
+        ));
+        assert_eq!(store.pending_task_ids(usize::MAX).await, vec![task.id]);
+
+        // The scheduled tasks are restored after a restart.
+        let store = TaskStore::new(tmp.env()).unwrap();
+        assert_eq!(store.next_scheduled_at().await, Some(not_before));
+        assert_eq!(store.pending_task_ids(usize::MAX).await, vec![task.id]);
+
+        let due = store
+            .register(
+                index_uid,
+                TaskContent::IndexDeletion,
+                Some(Utc::now() - chrono::Duration::seconds(1)),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(!due.is_scheduled());
+        assert_eq!(
+            store.pending_task_ids(usize::MAX).await,
+            vec![task.id, due.id]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_scheduled_tasks_are_restored_after_later_tasks_finished() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let index_uid = IndexUid::new_unchecked("test");
+        let not_before = Utc::now() + chrono::Duration::hours(1);
+
+        let scheduled = store
+            .register(
+                index_uid.clone(),
+                TaskContent::IndexDeletion,
+                Some(not_before),
+                None,
+            )
+            .await
+            .unwrap();
+        let mut task = store
+            .register(index_uid, TaskContent::IndexDeletion, None, None)
+            .await
+            .unwrap();
+
+        // The later task is processed while the scheduled task is still pending.
+        task.events.push(TaskEvent::Processing(Utc::now()));
+        task.events.push(TaskEvent::Succeded {
+            result: TaskResult::Other,
+            timestamp: Utc::now(),
+        });
+        store
+            .update_tasks(vec![Pending::Task(task.clone())])
+            .await
+            .unwrap();
+        store.delete_pending(&[Pending::Task(task)]).await;
+
+        let store = TaskStore::new(tmp.env()).unwrap();
+        assert_eq!(store.next_scheduled_at().await, Some(not_before));
+        assert!(store.pending_task_ids(usize::MAX).await.is_empty());
+        assert!(store
+            .get_task(scheduled.id, None)
+            .await
+            .unwrap()
+            .is_scheduled());
+    }
+
+    #[actix_rt::test]
+    async fn test_register_retry_once_at_a_time() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let index_uid = IndexUid::new_unchecked("test");
+        let addition = |retry_of| TaskContent::DocumentAddition {
+            content_uuid: Uuid::nil(),
+            merge_strategy: milli::update::IndexDocumentsMethod::ReplaceDocuments,
+            primary_key: None,
+            documents_count: 1,
+            allow_index_creation: true,
+            retry_of,
+        };
+
+        let mut failed = store
+            .register(index_uid.clone(), addition(None), None, None)
+            .await
+            .unwrap();
+        failed.events.push(TaskEvent::Failed {
+            error: ResponseError::from_msg("failed".to_string(), Code::Internal),
+            timestamp: Utc::now(),
+        });
+        store
+            .update_tasks(vec![Pending::Task(failed.clone())])
+            .await
+            .unwrap();
+
+        let (first, second) = futures::join!(
+            store.register_retry(index_uid.clone(), addition(Some(failed.id))),
+            store.register_retry(index_uid.clone(), addition(Some(failed.id))),
+        );
+
+        let retries = [first, second];
+        assert_eq!(retries.iter().filter(|retry| retry.is_ok()).count(), 1);
+        assert!(retries.iter().any(|retry| matches!(
+            retry,
+            Err(TaskError::TaskAlreadyRetried(id, _)) if *id == failed.id
+        )));
+    }
+
+    #[actix_rt::test]
+    async fn test_paused_state_is_persisted() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        assert!(!store.is_paused());
+
+        store.set_paused(true).await.unwrap();
+        assert!(store.is_paused());
+        drop(store);
+
+        let store = TaskStore::new(tmp.env()).unwrap();
+        assert!(store.is_paused());
+
+        store.set_paused(false).await.unwrap();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        assert!(!store.is_paused());
+    }
+
+    #[actix_rt::test]
+    async fn test_publish_each_task_event() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let mut receiver = store.subscribe();
+
+        let mut task = store
+            .register(
+                IndexUid::new_unchecked("test"),
+                TaskContent::IndexDeletion,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        task.events.push(TaskEvent::Batched {
+            timestamp: Utc::now(),
+            batch_id: 0,
+        });
+        task.events.push(TaskEvent::Processing(Utc::now()));
+        store.update_tasks(vec![Pending::Task(task)]).await.unwrap();
+
+        let created = receiver.recv().await.unwrap();
+        assert!(matches!(created.events[..], [TaskEvent::Created(_)]));
+        let batched = receiver.recv().await.unwrap();
+        assert!(matches!(
+            batched.events.last(),
+            Some(TaskEvent::Batched { .. })
+        ));
+        let processing = receiver.recv().await.unwrap();
+        assert_eq!(processing.events.len(), 3);
+        assert!(processing.is_processing());
+    }
+
+    #[actix_rt::test]
+    async fn test_finished_tasks_are_never_missed() {
+        let tmp = tmp_env();
+        let store = TaskStore::new(tmp.env()).unwrap();
+        let mut finished = store.finished_tasks();
+
+        let count = TASK_UPDATES_CAPACITY + 1;
+        for _ in 0..count {
+            store
+                .register(
+                    IndexUid::new_unchecked("test"),
+                    TaskContent::IndexDeletion,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        // The tasks that are not finished are not queued.
+        assert!(finished.try_recv().is_err());
+
+        store.cancel_index_tasks("test".to_string()).await.unwrap();
+        for _ in 0..count {
+            assert!(finished.recv().await.unwrap().is_cancelled());
+        }
+        assert!(finished.try_recv().is_err());
+    }
 }
@@ -1,21 +1,31 @@
 #[allow(clippy::upper_case_acronyms)]
 type BEU64 = heed::zerocopy::U64<heed::byteorder::BE>;
+#[allow(clippy::upper_case_acronyms)]
+type BEU32 = heed::zerocopy::U32<heed::byteorder::BE>;
 
 const UID_TASK_IDS: &str = "uid_task_id";
 const TASKS: &str = "tasks";
+const SCHEDULER_STATE: &str = "scheduler_state";
+const IDEMPOTENCY_KEYS: &str = "idempotency_keys";
+const BATCHES: &str = "batches";
+
+const PAUSED_KEY: &str = "paused";
 
 use std::borrow::Cow;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashSet};
 use std::convert::TryInto;
 use std::mem::size_of;
 use std::ops::Range;
 use std::result::Result as StdResult;
 use std::sync::Arc;
 
-use heed::types::{ByteSlice, OwnedType, SerdeJson, Unit};
+use chrono::{DateTime, Utc};
+use heed::types::{ByteSlice, OwnedType, SerdeJson, Str, Unit};
 use heed::{BytesDecode, BytesEncode, Database, Env, RoTxn, RwTxn};
+use uuid::Uuid;
 
-use crate::tasks::task::{Task, TaskId};
+use crate::tasks::batch::{BatchId, BatchInfo};
+use crate::tasks::task::{IdempotencyKey, Task, TaskEvent, TaskId};
 
 use super::super::Result;
 
@@ -57,6 +67,11 @@ pub struct Store {
     env: Arc<Env>,
     uids_task_ids: Database<IndexUidTaskIdCodec, Unit>,
     tasks: Database<OwnedType<BEU64>, SerdeJson<Task>>,
+    scheduler_state: Database<Str, SerdeJson<bool>>,
+    /// The id of the task registered with an idempotency key, the date the key expires at, and
+    /// the fingerprint of the request that registered the task.
+    idempotency_keys: Database<Str, SerdeJson<(TaskId, DateTime<Utc>, u64)>>,
+    batches: Database<OwnedType<BEU32>, SerdeJson<BatchInfo>>,
 }
 
 impl Drop for Store {
@@ -76,31 +91,40 @@ impl Store {
     pub fn new(env: Arc<heed::Env>) -> Result<Self> {
         let uids_task_ids = env.create_database(Some(UID_TASK_IDS))?;
         let tasks = env.create_database(Some(TASKS))?;
+        let scheduler_state = env.create_database(Some(SCHEDULER_STATE))?;
+        let idempotency_keys = env.create_database(Some(IDEMPOTENCY_KEYS))?;
+        let batches = env.create_database(Some(BATCHES))?;
 
         Ok(Self {
             env,
             uids_task_ids,
             tasks,
+            scheduler_state,
+            idempotency_keys,
+            batches,
         })
     }
 
     /// This function should be called *right after* creating the store.
     /// It put back all unfinished update in the `Created` state. This
     /// allow us to re-enqueue an update that didn't had the time to finish
-    /// when MeiliSearch closed.
+    /// when MeiliSearch closed. The records of the unfinished batches are deleted, since their
+    /// tasks will be put in new batches.
+    ///
+    /// All the tasks are read: the batches of different indexes, the cancelled and the scheduled
+    /// tasks all finish out of the order of the task ids.
     pub fn reset_and_return_unfinished_tasks(&mut self) -> Result<BinaryHeap<Pending<TaskId>>> {
         let mut unfinished_tasks: BinaryHeap<Pending<TaskId>> = BinaryHeap::new();
 
         let mut wtxn = self.wtxn()?;
-        let mut iter = self.tasks.rev_iter_mut(&mut wtxn)?;
+        let mut iter = self.tasks.iter_mut(&mut wtxn)?;
 
         while let Some(entry) = iter.next() {
             let entry = entry?;
             let (id, mut task): (BEU64, Task) = entry;
 
-            // Since all tasks are ordered, we can stop iterating when we encounter our first non-finished task.
             if task.is_finished() {
-                break;
+                continue;
             }
 
             // we only keep the first state. It’s supposed to be a `Created` state.
@@ -114,6 +138,19 @@ impl Store {
         }
 
         drop(iter);
+
+        let mut unfinished_batches = Vec::new();
+        for entry in self.batches.iter(&wtxn)? {
+            let (id, batch) = entry?;
+            if batch.finished_at.is_none() {
+                unfinished_batches.push(id);
+            }
+        }
+
+        for id in &unfinished_batches {
+            self.batches.delete(&mut wtxn, id)?;
+        }
+
         wtxn.commit()?;
 
         Ok(unfinished_tasks)
@@ -141,6 +178,56 @@ impl Store {
         Ok(id)
     }
 
+    /// Returns whether the scheduler was paused, and must not process tasks.
+    pub fn is_paused(&self, txn: &RoTxn) -> Result<bool> {
+        let paused = self.scheduler_state.get(txn, PAUSED_KEY)?;
+        Ok(paused.unwrap_or(false))
+    }
+
+    pub fn set_paused(&self, txn: &mut RwTxn, paused: bool) -> Result<()> {
+        self.scheduler_state.put(txn, PAUSED_KEY, &paused)?;
+        Ok(())
+    }
+
+    /// Returns the id of the task registered with the idempotency key, and the fingerprint of the
+    /// request that registered it, if the key isn't expired.
+    pub fn get_idempotent_task_id(&self, txn: &RoTxn, key: &str) -> Result<Option<(TaskId, u64)>> {
+        let id = self
+            .idempotency_keys
+            .get(txn, key)?
+            .filter(|(_, expires_at, _)| *expires_at > Utc::now())
+            .map(|(id, _, fingerprint)| (id, fingerprint));
+        Ok(id)
+    }
+
+    pub fn put_idempotency_key(
+        &self,
+        txn: &mut RwTxn,
+        key: &IdempotencyKey,
+        id: TaskId,
+    ) -> Result<()> {
+        self.idempotency_keys
+            .put(txn, &key.key, &(id, key.expires_at, key.fingerprint))?;
+        Ok(())
+    }
+
+    /// Deletes the idempotency keys that expired before `now`, returns the number of deleted keys.
+    pub fn prune_idempotency_keys(&self, txn: &mut RwTxn, now: DateTime<Utc>) -> Result<usize> {
+        let mut expired = Vec::new();
+        for entry in self.idempotency_keys.iter(txn)? {
+            let (key, (_, expires_at, _)) = entry?;
+            if expires_at <= now {
+                expired.push(key.to_string());
+            }
+        }
+
+        for key in &expired {
+            self.idempotency_keys.delete(txn, key)?;
+        }
+
+        Ok(expired.len())
+    }
+
     pub fn put(&self, txn: &mut RwTxn, task: &Task) -> Result<()> {
         self.tasks.put(txn, &BEU64::new(task.id), task)?;
         self.uids_task_ids
@@ -154,6 +241,194 @@ impl Store {
         Ok(task)
     }
 
+    /// Deletes the finished tasks that are older than `older_than`, or that exceed the
+    /// `max_finished_tasks` most recent finished tasks, along with their entries in the
+    /// `index_uid`+`task_id` index. The entries of this index that point to a task that doesn't
+    /// exist anymore are deleted as well, and so are the batches whose tasks were all deleted.
+    ///
+    /// The most recent task is never deleted, since it is used to compute the id of the next task.
+    /// Returns the deleted tasks.
+    pub fn prune(
+        &self,
+        txn: &mut RwTxn,
+        older_than: Option<DateTime<Utc>>,
+        max_finished_tasks: Option<usize>,
+    ) -> Result<Vec<Task>> {
+        let mut to_delete = Vec::new();
+        let mut finished_tasks = 0;
+        for (i, entry) in self.tasks.rev_iter(txn)?.enumerate() {
+            let (_, task) = entry?;
+            if !task.is_finished() {
+                continue;
+            }
+
+            finished_tasks += 1;
+            if i == 0 {
+                continue;
+            }
+
+            let too_many = max_finished_tasks.map_or(false, |max| finished_tasks > max);
+            let too_old = older_than
+                .zip(task.finished_at())
+                .map_or(false, |(limit, finished_at)| finished_at < limit);
+
+            if too_many || too_old {
+                to_delete.push(task);
+            }
+        }
+
+        for task in &to_delete {
+            self.tasks.delete(txn, &BEU64::new(task.id))?;
+            self.uids_task_ids
+                .delete(txn, &(task.index_uid.as_str(), task.id))?;
+        }
+
+        let mut dangling = Vec::new();
+        for entry in self.uids_task_ids.iter(txn)? {
+            let ((index_uid, id), _) = entry?;
+            if self
+                .tasks
+                .lazily_decode_data()
+                .get(txn, &BEU64::new(id))?
+                .is_none()
+            {
+                dangling.push((index_uid.to_string(), id));
+            }
+        }
+
+        for (index_uid, id) in &dangling {
+            self.uids_task_ids.delete(txn, &(index_uid.as_str(), *id))?;
+        }
+
+        let mut empty_batches = Vec::new();
+        for entry in self.batches.iter(txn)? {
+            let (id, batch) = entry?;
+            let mut has_tasks = false;
+            for task_id in &batch.task_ids {
+                let task = self
+                    .tasks
+                    .lazily_decode_data()
+                    .get(txn, &BEU64::new(*task_id))?;
+                if task.is_some() {
+                    has_tasks = true;
+                    break;
+                }
+            }
+
+            if !has_tasks {
+                empty_batches.push(id);
+            }
+        }
+
+        for id in &empty_batches {
+            self.batches.delete(txn, id)?;
+        }
+
+        Ok(to_delete)
+    }
+
+    /// Returns the content uuids of the failed document additions that finished before
+    /// `older_than`, or that are part of the `pruned` tasks, and whose update file isn't used by
+    /// another task anymore. An update file is still used by the document additions that are not
+    /// finished, or that failed after `older_than`, since they may be retried.
+    pub fn expired_payloads(
+        &self,
+        txn: &RoTxn,
+        older_than: DateTime<Utc>,
+        pruned: &[Task],
+    ) -> Result<Vec<Uuid>> {
+        let mut expired: HashSet<Uuid> = pruned
+            .iter()
+            .filter(|task| task.is_failed_document_addition())
+            .filter_map(Task::get_content_uuid)
+            .collect();
+        let mut used = HashSet::new();
+
+        for entry in self.tasks.iter(txn)? {
+            let (_, task) = entry?;
+            let content_uuid = match task.get_content_uuid() {
+                Some(content_uuid) => content_uuid,
+                None => continue,
+            };
+
+            if !task.is_finished() {
+                used.insert(content_uuid);
+            } else if task.is_failed_document_addition() {
+                match task.finished_at() {
+                    Some(finished_at) if finished_at >= older_than => used.insert(content_uuid),
+                    _ => expired.insert(content_uuid),
+                };
+            }
+        }
+
+        Ok(expired.difference(&used).copied().collect())
+    }
+
+    pub fn put_batch(&self, txn: &mut RwTxn, batch: &BatchInfo) -> Result<()> {
+        self.batches.put(txn, &BEU32::new(batch.id), batch)?;
+        Ok(())
+    }
+
+    pub fn get_batch(&self, txn: &RoTxn, id: BatchId) -> Result<Option<BatchInfo>> {
+        let batch = self.batches.get(txn, &BEU32::new(id))?;
+        Ok(batch)
+    }
+
+    /// Returns the batches matching the `filter`, from the most recent to the oldest. When `from`
+    /// is given, only the batches whose id is lower or equal to `from` are returned.
+    pub fn list_batches(
+        &self,
+        txn: &RoTxn,
+        from: Option<BatchId>,
+        filter: impl Fn(&BatchInfo) -> bool,
+        limit: Option<usize>,
+    ) -> Result<Vec<BatchInfo>> {
+        let end = from.map_or(BatchId::MAX, |from| from.saturating_add(1));
+        let mut batches = Vec::new();
+        for entry in self
+            .batches
+            .rev_range(txn, &(BEU32::new(0)..BEU32::new(end)))?
+        {
+            if batches.len() >= limit.unwrap_or(usize::MAX) {
+                break;
+            }
+
+            let (_, batch) = entry?;
+            if filter(&batch) {
+                batches.push(batch);
+            }
+        }
+
+        Ok(batches)
+    }
+
+    /// Returns the id of the last batch that was recorded or that a task was put in, if any.
+    pub fn last_batch_id(&self, txn: &RoTxn) -> Result<Option<BatchId>> {
+        let last_recorded = self
+            .batches
+            .lazily_decode_data()
+            .last(txn)?
+            .map(|(id, _)| id.get());
+
+        for entry in self.tasks.rev_iter(txn)? {
+            let (_, task) = entry?;
+            let batch_id = task.events.iter().rev().find_map(|event| match event {
+                TaskEvent::Batched { batch_id, .. } => Some(*batch_id),
+                _ => None,
+            });
+
+            if batch_id.is_some() {
+                return Ok(batch_id.max(last_recorded));
+            }
+        }
+
+        Ok(last_recorded)
+    }
+
+    /// Returns the tasks matching the `filter`, from the most recent to the oldest. When `from` is
+    /// given, only the tasks whose id is lower or equal to `from` are returned. The filter is
+    /// applied while iterating over the tasks, so that only the matching tasks count toward the
+    /// `limit`.
     pub fn list_tasks<'a>(
         &self,
         txn: &'a RoTxn,
@@ -161,15 +436,16 @@ impl Store {
         filter: Option<TaskFilter>,
         limit: Option<usize>,
     ) -> Result<Vec<Task>> {
-        let from = from.unwrap_or_default();
-        let range = from..limit
-            .map(|limit| (limit as u64).saturating_add(from))
-            .unwrap_or(u64::MAX);
-        let iter: Box<dyn Iterator<Item = StdResult<_, heed::Error>>> = match filter {
-            Some(filter) => {
+        let range = 0..from.map_or(TaskId::MAX, |from| from.saturating_add(1));
+        let filter = filter.unwrap_or_default();
+
+        let iter: Box<dyn Iterator<Item = StdResult<_, heed::Error>>> = match filter.indexes {
+            Some(ref indexes) => {
                 let iter = self
-                    .compute_candidates(txn, filter, range)?
+                    .compute_candidates(txn, indexes, range)?
+                    .into_sorted_vec()
                     .into_iter()
+                    .rev()
                     .filter_map(|id| self.tasks.get(txn, &BEU64::new(id)).transpose());
 
                 Box::new(iter)
@@ -183,6 +459,7 @@ impl Store {
 
         // Collect 'limit' task if it exists or all of them.
         let tasks = iter
+            .filter(|task| task.as_ref().map_or(true, |task| filter.pass(task)))
             .take(limit.unwrap_or(usize::MAX))
             .try_fold::<_, _, StdResult<_, heed::Error>>(Vec::new(), |mut v, task| {
                 v.push(task?);
@@ -195,50 +472,48 @@ impl Store {
     fn compute_candidates(
         &self,
         txn: &heed::RoTxn,
-        filter: TaskFilter,
+        indexes: &HashSet<String>,
         range: Range<TaskId>,
     ) -> Result<BinaryHeap<TaskId>> {
         let mut candidates = BinaryHeap::new();
-        if let Some(indexes) = filter.indexes {
-            for index in indexes {
-                // We need to prefix search the null terminated string to make sure that we only
-                // get exact matches for the index, and not other uids that would share the same
-                // prefix, i.e test and test1.
-                let mut index_uid = index.as_bytes().to_vec();
-                index_uid.push(0);
-
-                self.uids_task_ids
-                    .remap_key_type::<ByteSlice>()
-                    .rev_prefix_iter(txn, &index_uid)?
-                    .map(|entry| -> StdResult<_, heed::Error> {
-                        let (key, _) = entry?;
-                        let (_, id) =
-                            IndexUidTaskIdCodec::bytes_decode(key).ok_or(heed::Error::Decoding)?;
-                        Ok(id)
-                    })
-                    .skip_while(|entry| {
-                        entry
-                            .as_ref()
-                            .ok()
-                            // we skip all elements till we enter in the range
-                            .map(|key| !range.contains(key))
-                            // if we encounter an error we returns true to collect it later
-                            .unwrap_or(true)
-                    })
-                    .take_while(|entry| {
-                        entry
-                            .as_ref()
-                            .ok()
-                            // as soon as we are out of the range we exit
-                            .map(|key| range.contains(key))
-                            // if we encounter an error we returns true to collect it later
-                            .unwrap_or(true)
-                    })
-                    .try_for_each::<_, StdResult<(), heed::Error>>(|id| {
-                        candidates.push(id?);
-                        Ok(())
-                    })?;
-            }
+        for index in indexes {
+            // We need to prefix search the null terminated string to make sure that we only
+            // get exact matches for the index, and not other uids that would share the same
+            // prefix, i.e test and test1.
+            let mut index_uid = index.as_bytes().to_vec();
+            index_uid.push(0);
+
+            self.uids_task_ids
+                .remap_key_type::<ByteSlice>()
+                .rev_prefix_iter(txn, &index_uid)?
+                .map(|entry| -> StdResult<_, heed::Error> {
+                    let (key, _) = entry?;
+                    let (_, id) =
+                        IndexUidTaskIdCodec::bytes_decode(key).ok_or(heed::Error::Decoding)?;
+                    Ok(id)
+                })
+                .skip_while(|entry| {
+                    entry
+                        .as_ref()
+                        .ok()
+                        // we skip all elements till we enter in the range
+                        .map(|key| !range.contains(key))
+                        // if we encounter an error we returns true to collect it later
+                        .unwrap_or(true)
+                })
+                .take_while(|entry| {
+                    entry
+                        .as_ref()
+                        .ok()
+                        // as soon as we are out of the range we exit
+                        .map(|key| range.contains(key))
+                        // if we encounter an error we returns true to collect it later
+                        .unwrap_or(true)
+                })
+                .try_for_each::<_, StdResult<(), heed::Error>>(|id| {
+                    candidates.push(id?);
+                    Ok(())
+                })?;
         }
 
         Ok(candidates)
@@ -254,8 +529,11 @@ pub mod test {
     use proptest::prelude::*;
     use tempfile::TempDir;
 
+    use meilisearch_error::{Code, ResponseError};
+    use milli::update::IndexDocumentsMethod;
+
     use crate::index_resolver::IndexUid;
-    use crate::tasks::task::TaskContent;
+    use crate::tasks::task::{TaskContent, TaskResult};
 
     use super::*;
 
@@ -318,6 +596,50 @@ pub mod test {
             }
         }
 
+        pub fn is_paused(&self, txn: &RoTxn) -> Result<bool> {
+            match self {
+                MockStore::Real(index) => index.is_paused(txn),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn set_paused(&self, txn: &mut RwTxn, paused: bool) -> Result<()> {
+            match self {
+                MockStore::Real(index) => index.set_paused(txn, paused),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn get_idempotent_task_id(
+            &self,
+            txn: &RoTxn,
+            key: &str,
+        ) -> Result<Option<(TaskId, u64)>> {
+            match self {
+                MockStore::Real(index) => index.get_idempotent_task_id(txn, key),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn put_idempotency_key(
+            &self,
+            txn: &mut RwTxn,
+            key: &IdempotencyKey,
+            id: TaskId,
+        ) -> Result<()> {
+            match self {
+                MockStore::Real(index) => index.put_idempotency_key(txn, key, id),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn prune_idempotency_keys(&self, txn: &mut RwTxn, now: DateTime<Utc>) -> Result<usize> {
+            match self {
+                MockStore::Real(index) => index.prune_idempotency_keys(txn, now),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
         pub fn put(&self, txn: &mut RwTxn, task: &Task) -> Result<()> {
             match self {
                 MockStore::Real(index) => index.put(txn, task),
@@ -332,6 +654,64 @@ pub mod test {
             }
         }
 
+        pub fn prune(
+            &self,
+            txn: &mut RwTxn,
+            older_than: Option<DateTime<Utc>>,
+            max_finished_tasks: Option<usize>,
+        ) -> Result<Vec<Task>> {
+            match self {
+                MockStore::Real(index) => index.prune(txn, older_than, max_finished_tasks),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn expired_payloads(
+            &self,
+            txn: &RoTxn,
+            older_than: DateTime<Utc>,
+            pruned: &[Task],
+        ) -> Result<Vec<Uuid>> {
+            match self {
+                MockStore::Real(index) => index.expired_payloads(txn, older_than, pruned),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn put_batch(&self, txn: &mut RwTxn, batch: &BatchInfo) -> Result<()> {
+            match self {
+                MockStore::Real(index) => index.put_batch(txn, batch),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn get_batch(&self, txn: &RoTxn, id: BatchId) -> Result<Option<BatchInfo>> {
+            match self {
+                MockStore::Real(index) => index.get_batch(txn, id),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn list_batches(
+            &self,
+            txn: &RoTxn,
+            from: Option<BatchId>,
+            filter: impl Fn(&BatchInfo) -> bool,
+            limit: Option<usize>,
+        ) -> Result<Vec<BatchInfo>> {
+            match self {
+                MockStore::Real(index) => index.list_batches(txn, from, filter, limit),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
+        pub fn last_batch_id(&self, txn: &RoTxn) -> Result<Option<BatchId>> {
+            match self {
+                MockStore::Real(index) => index.last_batch_id(txn),
+                MockStore::Fake(_) => todo!(),
+            }
+        }
+
         pub fn list_tasks<'a>(
             &self,
             txn: &'a RoTxn,
@@ -357,6 +737,7 @@ pub mod test {
                 index_uid: IndexUid::new_unchecked("test".to_string()),
                 content: TaskContent::IndexDeletion,
                 events: vec![],
+                not_before: None,
             })
             .collect::<Vec<_>>();
 
@@ -388,6 +769,7 @@ pub mod test {
             index_uid: IndexUid::new_unchecked("test".to_string()),
             content: TaskContent::IndexDeletion,
             events: vec![],
+            not_before: None,
         };
 
         let task_2 = Task {
@@ -395,6 +777,7 @@ pub mod test {
             index_uid: IndexUid::new_unchecked("test1".to_string()),
             content: TaskContent::IndexDeletion,
             events: vec![],
+            not_before: None,
         };
 
         let mut txn = store.wtxn().unwrap();
@@ -416,12 +799,14 @@ pub mod test {
             index_uid: IndexUid::new_unchecked("test".to_string()),
             content: TaskContent::IndexDeletion,
             events: vec![],
+            not_before: None,
         };
         let task_2 = Task {
             id: 1,
             index_uid: IndexUid::new_unchecked("test1".to_string()),
             content: TaskContent::IndexDeletion,
             events: vec![],
+            not_before: None,
         };
 
         let mut txn = store.wtxn().unwrap();
@@ -437,6 +822,326 @@ pub mod test {
         assert_eq!(&*tasks.first().unwrap().index_uid, "test");
     }
 
+    #[test]
+    fn test_list_tasks_filter_and_cursor() {
+        let tmp = tmp_env();
+        let store = Store::new(tmp.env()).unwrap();
+
+        let mut txn = store.wtxn().unwrap();
+        for id in 0..10 {
+            let task = Task {
+                id,
+                index_uid: IndexUid::new_unchecked("test".to_string()),
+                content: TaskContent::IndexDeletion,
+                events: vec![],
+                not_before: None,
+            };
+            store.put(&mut txn, &task).unwrap();
+        }
+
+        let mut filter = TaskFilter::default();
+        filter.filter_fn(|task| task.id % 2 == 0);
+        let ids = store
+            .list_tasks(&txn, Some(7), Some(filter), Some(2))
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![6, 4]);
+
+        let mut filter = TaskFilter::default();
+        filter.filter_index("test".into());
+        filter.filter_fn(|task| task.id % 2 == 1);
+        let ids = store
+            .list_tasks(&txn, Some(4), Some(filter), None)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![3, 1]);
+    }
+
+    #[test]
+    fn test_prune_keeps_unfinished_and_last_tasks() {
+        let tmp = tmp_env();
+        let store = Store::new(tmp.env()).unwrap();
+
+        let finished = |id| Task {
+            id,
+            index_uid: IndexUid::new_unchecked("test".to_string()),
+            content: TaskContent::IndexDeletion,
+            events: vec![
+                TaskEvent::Created(Utc::now()),
+                TaskEvent::Succeded {
+                    result: TaskResult::Other,
+                    timestamp: Utc::now(),
+                },
+            ],
+            not_before: None,
+        };
+        let enqueued = Task {
+            id: 2,
+            index_uid: IndexUid::new_unchecked("test".to_string()),
+            content: TaskContent::IndexDeletion,
+            events: vec![TaskEvent::Created(Utc::now())],
+            not_before: None,
+        };
+
+        let mut txn = store.wtxn().unwrap();
+        store.put(&mut txn, &finished(0)).unwrap();
+        store.put(&mut txn, &finished(1)).unwrap();
+        store.put(&mut txn, &enqueued).unwrap();
+        store.put(&mut txn, &finished(3)).unwrap();
+
+        // every finished task is too old, but the last task must be kept.
+        let deleted = store
+            .prune(&mut txn, Some(Utc::now() + chrono::Duration::days(1)), None)
+            .unwrap();
+        assert_eq!(deleted.len(), 2);
+
+        let mut filter = TaskFilter::default();
+        filter.filter_index("test".into());
+        let ids = store
+            .list_tasks(&txn, None, Some(filter), None)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .sorted()
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(store.next_task_id(&mut txn).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_reset_unfinished_tasks_finished_out_of_order() {
+        let tmp = tmp_env();
+        let mut store = Store::new(tmp.env()).unwrap();
+
+        let task = |id, events| Task {
+            id,
+            index_uid: IndexUid::new_unchecked("test".to_string()),
+            content: TaskContent::IndexDeletion,
+            events,
+            not_before: None,
+        };
+        let processing = task(
+            0,
+            vec![
+                TaskEvent::Created(Utc::now()),
+                TaskEvent::Processing(Utc::now()),
+            ],
+        );
+        let enqueued = task(1, vec![TaskEvent::Created(Utc::now())]);
+        let finished = task(
+            2,
+            vec![
+                TaskEvent::Created(Utc::now()),
+                TaskEvent::Succeded {
+                    result: TaskResult::Other,
+                    timestamp: Utc::now(),
+                },
+            ],
+        );
+
+        let mut txn = store.wtxn().unwrap();
+        store.put(&mut txn, &processing).unwrap();
+        store.put(&mut txn, &enqueued).unwrap();
+        store.put(&mut txn, &finished).unwrap();
+        txn.commit().unwrap();
+
+        let unfinished = store.reset_and_return_unfinished_tasks().unwrap();
+        let ids = unfinished
+            .into_iter()
+            .filter_map(|pending| match pending {
+                Pending::Task(id) => Some(id),
+                Pending::Job(_) => None,
+            })
+            .sorted()
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![0, 1]);
+
+        let txn = store.rtxn().unwrap();
+        let processing = store.get(&txn, 0).unwrap().unwrap();
+        assert!(matches!(&processing.events[..], [TaskEvent::Created(_)]));
+    }
+
+    #[test]
+    fn test_expired_payloads() {
+        let tmp = tmp_env();
+        let store = Store::new(tmp.env()).unwrap();
+
+        let expired_at = Utc::now() - chrono::Duration::days(2);
+        let older_than = Utc::now() - chrono::Duration::days(1);
+        let addition = |id, content_uuid, events| Task {
+            id,
+            index_uid: IndexUid::new_unchecked("test".to_string()),
+            content: TaskContent::DocumentAddition {
+                content_uuid,
+                merge_strategy: IndexDocumentsMethod::ReplaceDocuments,
+                primary_key: None,
+                documents_count: 1,
+                allow_index_creation: true,
+                retry_of: None,
+            },
+            events,
+            not_before: None,
+        };
+        let failed = |timestamp| {
+            vec![
+                TaskEvent::Created(timestamp),
+                TaskEvent::Failed {
+                    error: ResponseError::from_msg("failed".to_string(), Code::Internal),
+                    timestamp,
+                },
+            ]
+        };
+
+        let (expired, retried, recent, pruned_uuid) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+
+        let mut txn = store.wtxn().unwrap();
+        store
+            .put(&mut txn, &addition(0, expired, failed(expired_at)))
+            .unwrap();
+        store
+            .put(&mut txn, &addition(1, retried, failed(expired_at)))
+            .unwrap();
+        // a pending retry of the task 1 still uses its update file.
+        store
+            .put(
+                &mut txn,
+                &addition(2, retried, vec![TaskEvent::Created(Utc::now())]),
+            )
+            .unwrap();
+        store
+            .put(&mut txn, &addition(3, recent, failed(Utc::now())))
+            .unwrap();
+
+        let pruned = [addition(4, pruned_uuid, failed(Utc::now()))];
+        let payloads = store
+            .expired_payloads(&txn, older_than, &pruned)
+            .unwrap()
+            .into_iter()
+            .sorted()
+            .collect::<Vec<_>>();
+        let expected = [expired, pruned_uuid]
+            .into_iter()
+            .sorted()
+            .collect::<Vec<_>>();
+        assert_eq!(payloads, expected);
+    }
+
+    #[test]
+    fn test_idempotency_keys_expire() {
+        let tmp = tmp_env();
+        let store = Store::new(tmp.env()).unwrap();
+
+        let key = |key: &str, expires_at| IdempotencyKey {
+            key: key.to_string(),
+            expires_at,
+            fingerprint: 42,
+        };
+
+        let mut txn = store.wtxn().unwrap();
+        store
+            .put_idempotency_key(
+                &mut txn,
+                &key("valid", Utc::now() + chrono::Duration::hours(1)),
+                0,
+            )
+            .unwrap();
+        store
+            .put_idempotency_key(
+                &mut txn,
+                &key("expired", Utc::now() - chrono::Duration::hours(1)),
+                1,
+            )
+            .unwrap();
+
+        assert_eq!(
+            store.get_idempotent_task_id(&txn, "valid").unwrap(),
+            Some((0, 42))
+        );
+        assert_eq!(store.get_idempotent_task_id(&txn, "expired").unwrap(), None);
+        assert_eq!(store.get_idempotent_task_id(&txn, "unknown").unwrap(), None);
+
+        assert_eq!(
+            store.prune_idempotency_keys(&mut txn, Utc::now()).unwrap(),
+            1
+        );
+        assert!(store
+            .idempotency_keys
+            .get(&txn, "expired")
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            store.get_idempotent_task_id(&txn, "valid").unwrap(),
+            Some((0, 42))
+        );
+    }
+
+    #[test]
+    fn test_batches_are_pruned_with_their_tasks() {
+        let tmp = tmp_env();
+        let store = Store::new(tmp.env()).unwrap();
+
+        let finished = |id| Task {
+            id,
+            index_uid: IndexUid::new_unchecked("test".to_string()),
+            content: TaskContent::IndexDeletion,
+            events: vec![
+                TaskEvent::Created(Utc::now()),
+                TaskEvent::Succeded {
+                    result: TaskResult::Other,
+                    timestamp: Utc::now(),
+                },
+            ],
+            not_before: None,
+        };
+        let batch = |id, task_ids| BatchInfo {
+            id,
+            index_uid: "test".to_string(),
+            task_ids,
+            started_at: Utc::now(),
+            finished_at: Some(Utc::now()),
+            total_documents: 0,
+            steps: Vec::new(),
+        };
+
+        let mut txn = store.wtxn().unwrap();
+        for id in 0..3 {
+            store.put(&mut txn, &finished(id)).unwrap();
+        }
+        store.put_batch(&mut txn, &batch(0, vec![0])).unwrap();
+        store.put_batch(&mut txn, &batch(1, vec![1, 2])).unwrap();
+
+        let ids = |batches: Vec<BatchInfo>| batches.into_iter().map(|b| b.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(store.list_batches(&txn, None, |_| true, None).unwrap()),
+            vec![1, 0]
+        );
+        assert_eq!(
+            ids(store.list_batches(&txn, Some(0), |_| true, None).unwrap()),
+            vec![0]
+        );
+        assert_eq!(
+            ids(store.list_batches(&txn, None, |_| true, Some(1)).unwrap()),
+            vec![1]
+        );
+
+        // the tasks 0 and 1 are deleted, the batch 1 still contains the task 2.
+        store
+            .prune(&mut txn, Some(Utc::now() + chrono::Duration::days(1)), None)
+            .unwrap();
+        assert!(store.get_batch(&txn, 0).unwrap().is_none());
+        assert!(store.get_batch(&txn, 1).unwrap().is_some());
+        assert_eq!(store.last_batch_id(&txn).unwrap(), Some(1));
+    }
+
     proptest! {
         #[test]
         fn encode_decode_roundtrip(index_uid in any::<IndexUid>(), task_id in 0..TaskId::MAX) {
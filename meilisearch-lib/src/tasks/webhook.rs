@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use log::{debug, error, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::time::sleep;
+use uuid::Uuid;
+
+use super::error::{Result, TaskError};
+use crate::options::WebhookConfig;
+
+/// The delay before the first retry of a failed delivery, doubled after each attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+/// The maximum delay between two delivery attempts.
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+/// The time after which a request to a webhook is considered failed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub uid: Uuid,
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// The webhooks that are notified each time a task is finished.
+///
+/// Each notification is delivered in a background task, and failed deliveries are retried with
+/// an exponential backoff, so notifying the webhooks never waits for the remote servers.
+///
+/// The webhooks registered at runtime are only kept in memory: they are lost on restart, and
+/// aren't part of the dumps or snapshots. Only the webhooks given in the `WebhookConfig` are
+/// registered again at startup.
+#[derive(Clone)]
+pub struct Webhooks {
+    webhooks: Arc<RwLock<Vec<Webhook>>>,
+    client: Client,
+    max_retries: usize,
+}
+
+impl Webhooks {
+    /// Creates the webhooks from the urls and headers given at startup.
+    pub fn new(config: &WebhookConfig) -> Result<Self> {
+        let headers = config
+            .webhook_headers
+            .iter()
+            .map(|header| parse_header(header))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        let webhooks = config
+            .webhook_urls
+            .iter()
+            .map(|url| new_webhook(url.clone(), headers.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+
+        Ok(Self {
+            webhooks: Arc::new(RwLock::new(webhooks)),
+            client,
+            max_retries: config.webhook_max_retries,
+        })
+    }
+
+    pub async fn list(&self) -> Vec<Webhook> {
+        self.webhooks.read().await.clone()
+    }
+
+    /// Registers a webhook until the next restart, see `Webhooks`.
+    pub async fn register(
+        &self,
+        url: String,
+        headers: BTreeMap<String, String>,
+    ) -> Result<Webhook> {
+        let webhook = new_webhook(url, headers)?;
+        self.webhooks.write().await.push(webhook.clone());
+
+        Ok(webhook)
+    }
+
+    pub async fn delete(&self, uid: String) -> Result<Webhook> {
+        let mut webhooks = self.webhooks.write().await;
+        let position = Uuid::parse_str(&uid)
+            .ok()
+            .and_then(|uuid| webhooks.iter().position(|webhook| webhook.uid == uuid))
+            .ok_or(TaskError::UnexistingWebhook(uid))?;
+
+        Ok(webhooks.remove(position))
+    }
+
+    /// Sends the payload, serialized as JSON, to each webhook in the background.
+    pub async fn notify(&self, payload: &impl Serialize) -> Result<()> {
+        let webhooks = self.webhooks.read().await;
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+
+        let body = Bytes::from(serde_json::to_vec(payload)?);
+        for webhook in webhooks.iter() {
+            tokio::task::spawn(deliver(
+                self.client.clone(),
+                webhook.clone(),
+                body.clone(),
+                self.max_retries,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Posts the body to the webhook, retrying up to `max_retries` times until the webhook answers
+/// with a success status.
+async fn deliver(client: Client, webhook: Webhook, body: Bytes, max_retries: usize) {
+    let headers: HeaderMap = webhook
+        .headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+            let value = HeaderValue::from_str(value).ok()?;
+            Some((name, value))
+        })
+        .collect();
+
+    let mut delay = RETRY_BASE_DELAY;
+    for attempt in 0..=max_retries {
+        if attempt > 0 {
+            sleep(delay).await;
+            delay = (delay * 2).min(RETRY_MAX_DELAY);
+        }
+
+        let response = client
+            .post(&webhook.url)
+            .headers(headers.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => {
+                debug!("task delivered to webhook `{}`", webhook.url);
+                return;
+            }
+            Ok(response) => warn!(
+                "webhook `{}` answered with status {}",
+                webhook.url,
+                response.status()
+            ),
+            Err(e) => warn!("error sending a task to webhook `{}`: {}", webhook.url, e),
+        }
+    }
+
+    error!(
+        "giving up sending a task to webhook `{}` after {} attempts",
+        webhook.url,
+        max_retries + 1
+    );
+}
+
+fn new_webhook(url: String, headers: BTreeMap<String, String>) -> Result<Webhook> {
+    match Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => (),
+        _ => return Err(TaskError::InvalidWebhookUrl(url)),
+    }
+
+    for (name, value) in &headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err()
+        {
+            return Err(TaskError::InvalidWebhookHeader(format!(
+                "{}: {}",
+                name, value
+            )));
+        }
+    }
+
+    Ok(Webhook {
+        uid: Uuid::new_v4(),
+        url,
+        headers,
+    })
+}
+
+/// Parses a header formatted as `Name: value`.
+fn parse_header(header: &str) -> Result<(String, String)> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| TaskError::InvalidWebhookHeader(header.to_string()))?;
+
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header("Authorization: Bearer my-token").unwrap(),
+            ("Authorization".to_string(), "Bearer my-token".to_string())
+        );
+        assert!(matches!(
+            parse_header("Authorization"),
+            Err(TaskError::InvalidWebhookHeader(_))
+        ));
+    }
+
+    #[test]
+    fn test_new_webhook_validation() {
+        assert!(new_webhook("http://localhost:8080/hook".to_string(), BTreeMap::new()).is_ok());
+        assert!(matches!(
+            new_webhook("ftp://localhost/hook".to_string(), BTreeMap::new()),
+            Err(TaskError::InvalidWebhookUrl(_))
+        ));
+        assert!(matches!(
+            new_webhook("not an url".to_string(), BTreeMap::new()),
+            Err(TaskError::InvalidWebhookUrl(_))
+        ));
+
+        let headers = BTreeMap::from([("Invalid Name".to_string(), "value".to_string())]);
+        assert!(matches!(
+            new_webhook("https://localhost/hook".to_string(), headers),
+            Err(TaskError::InvalidWebhookHeader(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_register_and_delete_webhook() {
+        let webhooks = Webhooks::new(&WebhookConfig {
+            webhook_urls: vec!["http://localhost:8080/hook".to_string()],
+            webhook_headers: vec!["X-Token: secret".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let webhook = webhooks
+            .register("http://localhost:8081/hook".to_string(), BTreeMap::new())
+            .await
+            .unwrap();
+        let list = webhooks.list().await;
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].headers["X-Token"], "secret");
+
+        webhooks.delete(webhook.uid.to_string()).await.unwrap();
+        assert_eq!(webhooks.list().await.len(), 1);
+        assert!(matches!(
+            webhooks.delete(webhook.uid.to_string()).await,
+            Err(TaskError::UnexistingWebhook(_))
+        ));
+    }
+}